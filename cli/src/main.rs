@@ -10,17 +10,22 @@ extern crate structopt;
 #[macro_use]
 extern crate log;
 
+use za_compiler::debugger::{self, Debugger};
+use za_compiler::diagnostics::MessageFormat;
+use za_compiler::diff::{self, ConstraintSystem};
+use za_compiler::lint;
+use za_compiler::optimizer::Level;
+use za_compiler::profile;
+use za_compiler::project::Project;
+use za_compiler::tester;
 use za_compiler::tester::dump_error;
-use za_compiler::types::{print_info, Constraints, Signals};
-use za_compiler::{
-    evaluator::{Evaluator, Mode},
-    tester,
-};
+use za_parser::format_source;
 use za_prover::groth16;
 
+use std::fs;
 use std::fs::File;
 use std::io::prelude::*;
-use std::time::SystemTime;
+use std::process;
 
 const DEFAULT_CIRCUIT: &str = "circuit.za";
 const DEFAULT_PROVING_KEY: &str = "proving.key";
@@ -32,45 +37,67 @@ const VERIFIER_TYPE_SOLIDITY: &str = "solidity";
 const VERIFIER_TYPE_JSON: &str = "json";
 const DEFAULT_VERIFIER_TYPE: &str = VERIFIER_TYPE_SOLIDITY;
 
-fn compile_ram(filename: &str, print_all: bool) {
-    let mut start = SystemTime::now();
-    let mut eval = Evaluator::new(
-        Mode::GenConstraints,
-        Signals::default(),
-        Constraints::default(),
-    );
-    if let Err(err) = eval.eval_file(".", &filename) {
-        dump_error(&eval, &format!("{:?}", err));
+fn compile_ram(
+    project: &Project,
+    print_all: bool,
+    update_lock: bool,
+    provenance: Option<String>,
+    sym: Option<String>,
+) {
+    match project.compile(update_lock, print_all) {
+        Ok(circuit) => {
+            if let Some(path) = provenance {
+                fs::write(&path, circuit.constraints.provenance_json())
+                    .expect("cannot write provenance file");
+            }
+            if let Some(path) = sym.or_else(|| project.manifest.paths.sym.clone()) {
+                fs::write(&path, circuit.signals.sym(&circuit.removed_signals))
+                    .expect("cannot write symbols file");
+            }
+        }
+        Err(err) => {
+            warn!("Error: {:?}", err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Formats the sources, or with `check` lists the ones that are not
+///   formatted and exits with an error
+fn format_files(files: Vec<String>, check: bool) {
+    let files = if files.is_empty() {
+        let project = Project::discover(".")
+            .expect("cannot load project manifest")
+            .unwrap_or_else(|| Project::from_circuit(".", DEFAULT_CIRCUIT));
+        let file = project.root.join(project.circuit_file());
+        vec![file.to_string_lossy().to_string()]
     } else {
-        info!(
-            "Compile time: {:?}",
-            SystemTime::now().duration_since(start).unwrap()
-        );
-        start = SystemTime::now();
-
-        let Evaluator {
-            constraints,
-            signals,
-            ..
-        } = eval;
-        print_info("compile", &constraints, &signals, &[], print_all);
-
-        let irreductible_signals = signals.main_input_ids();
-        let (constraints, removed_signals) =
-            za_compiler::optimizer::optimize(&constraints, &irreductible_signals);
-
-        info!(
-            "Optimization time: {:?}",
-            SystemTime::now().duration_since(start).unwrap()
-        );
-
-        print_info(
-            "optimized",
-            &constraints,
-            &signals,
-            &removed_signals,
-            print_all,
-        );
+        files
+    };
+
+    let mut unformatted = 0;
+    for file in files {
+        let code = fs::read_to_string(&file).expect("cannot read source file");
+        let formatted = match format_source(&code) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                warn!("Error: {} {:?}", file, err);
+                process::exit(1);
+            }
+        };
+        if formatted == code {
+            continue;
+        }
+        if check {
+            println!("{}", file);
+            unformatted += 1;
+        } else {
+            fs::write(&file, formatted).expect("cannot write source file");
+            info!("Formatted {}", file);
+        }
+    }
+    if unformatted > 0 {
+        process::exit(1);
     }
 }
 
@@ -104,25 +131,141 @@ enum VerifierType {
     Solidity {},
 }
 
+#[derive(StructOpt)]
+struct CircuitOpt {
+    #[structopt(long = "circuit")]
+    /// Input circuit, defaults to circuit.za
+    circuit: Option<String>,
+
+    #[structopt(long = "main")]
+    /// Main template invocation, e.g. "Multiplier(3)"
+    main: Option<String>,
+
+    #[structopt(
+        long = "param",
+        number_of_values = 1,
+        parse(try_from_str = "parse_param")
+    )]
+    /// Main template argument as name=value, can be repeated
+    params: Vec<(String, String)>,
+
+    #[structopt(long = "nocache")]
+    /// Do not use the .za-cache folder
+    nocache: bool,
+
+    #[structopt(long = "message-format", default_value = "human")]
+    /// Diagnostics format, human or json
+    message_format: MessageFormat,
+}
+
+fn parse_param(param: &str) -> Result<(String, String), String> {
+    let mut parts = param.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(name), Some(value)) => Ok((name.trim().to_string(), value.trim().to_string())),
+        _ => Err(format!("parameter {} should be name=value", param)),
+    }
+}
+
+impl CircuitOpt {
+    /// Loads the `za.toml` project in the current folder if any, command line
+    ///   options override the manifest ones
+    fn load_project(self) -> Project {
+        let mut project = match Project::discover(".").expect("cannot load project manifest") {
+            Some(mut project) => {
+                if let Some(circuit) = self.circuit {
+                    project.manifest.circuit.file = circuit;
+                }
+                project
+            }
+            None => Project::from_circuit(
+                ".",
+                &self.circuit.unwrap_or_else(|| DEFAULT_CIRCUIT.to_string()),
+            ),
+        };
+        if let Some(main) = self.main {
+            project.manifest.circuit.main = Some(main);
+        }
+        for (name, value) in self.params {
+            project.manifest.circuit.params.insert(name, value);
+        }
+        let project = project.with_message_format(self.message_format);
+        if self.nocache {
+            project
+        } else {
+            project.with_cache()
+        }
+    }
+}
+
+#[derive(StructOpt)]
+struct OptimizeOpt {
+    #[structopt(long = "O0", raw(conflicts_with_all = r#"&["o1", "o2"]"#))]
+    /// Do not optimize the constraints
+    o0: bool,
+
+    #[structopt(long = "O1", raw(conflicts_with_all = r#"&["o0", "o2"]"#))]
+    /// Only remove the constraints that rename a signal or set it to a constant, the default
+    o1: bool,
+
+    #[structopt(long = "O2", raw(conflicts_with_all = r#"&["o0", "o1"]"#))]
+    /// Remove all the linear constraints
+    o2: bool,
+
+    #[structopt(long = "report")]
+    /// Print the optimization report, human or json
+    report: Option<MessageFormat>,
+}
+
+impl OptimizeOpt {
+    fn apply(&self, project: Project) -> Project {
+        let level = if self.o2 {
+            Level::O2
+        } else if self.o1 {
+            Level::O1
+        } else if self.o0 {
+            Level::O0
+        } else {
+            Level::default()
+        };
+        project.with_optimization(level, self.report)
+    }
+}
+
 #[derive(StructOpt)]
 enum Command {
     #[structopt(name = "compile")]
     /// Only compile the circuit
     Compile {
-        #[structopt(long = "circuit")]
-        /// Input circuit, defaults to circuit.za
-        circuit: Option<String>,
+        #[structopt(flatten)]
+        circuit: CircuitOpt,
+
+        #[structopt(flatten)]
+        optimize: OptimizeOpt,
 
         #[structopt(long = "print")]
         /// Print constaints and signals
         print: bool,
+
+        #[structopt(long = "updatelock")]
+        /// Update the za.lock file hashes
+        updatelock: bool,
+
+        #[structopt(long = "provenance")]
+        /// Output JSON file with where each optimized constraint is generated
+        provenance: Option<String>,
+
+        #[structopt(long = "sym")]
+        /// Output circom symbols file with the signal names
+        sym: Option<String>,
     },
     #[structopt(name = "setup")]
     /// Compile & generate trusted setup
     Setup {
-        #[structopt(long = "circuit")]
-        /// Input circuit, defaults to circuit.za
-        circuit: Option<String>,
+        #[structopt(flatten)]
+        circuit: CircuitOpt,
+
+        #[structopt(flatten)]
+        optimize: OptimizeOpt,
 
         #[structopt(long = "pk")]
         /// Output proving key output file, defaults to prover.key
@@ -135,6 +278,14 @@ enum Command {
         #[structopt(long = "verifiertype")]
         /// Verifier type, solidity (default) or json
         verifier_type: Option<String>,
+
+        #[structopt(long = "sym")]
+        /// Output circom symbols file with the signal names
+        sym: Option<String>,
+
+        #[structopt(long = "updatelock")]
+        /// Update the za.lock file hashes
+        updatelock: bool,
     },
 
     #[structopt(name = "prove")]
@@ -156,9 +307,8 @@ enum Command {
     #[structopt(name = "test")]
     /// Run embeeded circuit tests
     Test {
-        #[structopt(long = "circuit")]
-        /// Input circuit, defaults to circuit.za
-        circuit: Option<String>,
+        #[structopt(flatten)]
+        circuit: CircuitOpt,
 
         #[structopt(long = "debug")]
         /// Turn on debugging
@@ -176,6 +326,102 @@ enum Command {
         #[structopt(long = "prefix")]
         prefix: Option<String>,
     },
+
+    #[structopt(name = "debug")]
+    /// Generate the witness step by step, stopping in breakpoints
+    Debug {
+        #[structopt(flatten)]
+        circuit: CircuitOpt,
+
+        #[structopt(long = "input")]
+        /// Input inputs file, defaults to input.json
+        input: Option<String>,
+
+        #[structopt(long = "break", number_of_values = 1)]
+        /// Breakpoint as file:line or template name, can be repeated, stops in
+        /// the first statement if there are none
+        breakpoints: Vec<String>,
+
+        /// Test template to debug instead of the main component
+        test: Option<String>,
+    },
+
+    #[structopt(name = "lint")]
+    /// Check the circuit for unconstrained signals
+    Lint {
+        #[structopt(flatten)]
+        circuit: CircuitOpt,
+
+        #[structopt(long = "uniqueness")]
+        /// Also check that the signals are uniquely determined by the inputs
+        uniqueness: bool,
+    },
+
+    #[structopt(name = "profile")]
+    /// Count the constraints and signals of each component
+    Profile {
+        #[structopt(flatten)]
+        circuit: CircuitOpt,
+
+        #[structopt(flatten)]
+        optimize: OptimizeOpt,
+
+        #[structopt(long = "folded")]
+        /// Print flamegraph folded stacks of the optimized constraints
+        folded: bool,
+
+        #[structopt(long = "unoptimized")]
+        /// Use the generated constraints in the folded stacks
+        unoptimized: bool,
+
+        #[structopt(long = "json")]
+        /// Print the component tree as JSON
+        json: bool,
+    },
+
+    #[structopt(name = "diff")]
+    /// Compare the constraint systems of two circuits or proving keys
+    Diff {
+        #[structopt(flatten)]
+        optimize: OptimizeOpt,
+
+        #[structopt(long = "json")]
+        /// Print the differences as JSON
+        json: bool,
+
+        /// Old circuit file or proving key
+        old: String,
+
+        /// New circuit file or proving key
+        new: String,
+    },
+
+    #[structopt(name = "fmt")]
+    /// Format the circuit sources
+    Fmt {
+        #[structopt(long = "check")]
+        /// Do not write the files, list the unformatted ones and fail if any
+        check: bool,
+
+        /// Files to format, defaults to the project circuit
+        files: Vec<String>,
+    },
+}
+
+// circuit sources are compiled, other files are read as proving keys
+fn constraint_system(path: &str, optimize: &OptimizeOpt) -> ConstraintSystem {
+    if path.ends_with(".za") || path.ends_with(".circom") {
+        let project = optimize.apply(Project::from_circuit(".", path).with_cache());
+        match project.compile(false, false) {
+            Ok(circuit) => ConstraintSystem::from(circuit),
+            Err(err) => {
+                warn!("Error: {:?}", err);
+                process::exit(1);
+            }
+        }
+    } else {
+        groth16::helper::read_constraint_system(path).expect("cannot read proving key")
+    }
 }
 
 fn main() {
@@ -189,18 +435,34 @@ fn main() {
 
     let cmd = Command::from_args();
     match cmd {
-        Command::Compile { circuit, print } => {
-            let circuit = circuit.unwrap_or_else(|| DEFAULT_CIRCUIT.to_string());
-            compile_ram(&circuit, print)
-        }
+        Command::Compile {
+            circuit,
+            optimize,
+            print,
+            updatelock,
+            provenance,
+            sym,
+        } => compile_ram(
+            &optimize.apply(circuit.load_project()),
+            print,
+            updatelock,
+            provenance,
+            sym,
+        ),
         Command::Setup {
             circuit,
+            optimize,
             pk,
             verifier_file,
             verifier_type,
+            sym,
+            updatelock,
         } => {
-            let circuit = circuit.unwrap_or_else(|| DEFAULT_CIRCUIT.to_string());
-            let pk = pk.unwrap_or_else(|| DEFAULT_PROVING_KEY.to_string());
+            let project = optimize.apply(circuit.load_project());
+            let paths = &project.manifest.paths;
+            let pk = pk
+                .or_else(|| paths.pk.clone())
+                .unwrap_or_else(|| DEFAULT_PROVING_KEY.to_string());
             let verifier_type = match verifier_type
                 .unwrap_or_else(|| DEFAULT_VERIFIER_TYPE.to_string())
                 .as_ref()
@@ -209,15 +471,24 @@ fn main() {
                 VERIFIER_TYPE_SOLIDITY => groth16::helper::VerifierType::Solidity,
                 _ => panic!("unknown verifier type"),
             };
-            let verifier_file = verifier_file.unwrap_or_else(|| {
-                match verifier_type {
-                    groth16::helper::VerifierType::Solidity => DEFAULT_VERIFIER_SOLIDITY,
-                    groth16::helper::VerifierType::JSON => DEFAULT_VERIFIER_JSON,
-                }
-                .to_string()
-            });
-            let verifier = groth16::helper::setup(&circuit, &pk, verifier_type)
-                .expect("unable to create proof");
+            let verifier_file = verifier_file
+                .or_else(|| paths.verifier.clone())
+                .unwrap_or_else(|| {
+                    match verifier_type {
+                        groth16::helper::VerifierType::Solidity => DEFAULT_VERIFIER_SOLIDITY,
+                        groth16::helper::VerifierType::JSON => DEFAULT_VERIFIER_JSON,
+                    }
+                    .to_string()
+                });
+            let sym = sym.or_else(|| paths.sym.clone());
+            let verifier = groth16::helper::setup_project(
+                &project,
+                &pk,
+                verifier_type,
+                updatelock,
+                sym.as_deref(),
+            )
+            .expect("unable to create proof");
 
             File::create(verifier_file)
                 .expect("cannot create verifier file")
@@ -231,25 +502,137 @@ fn main() {
             skipcompile,
             prefix,
         } => {
-            let circuit = circuit.unwrap_or_else(|| DEFAULT_CIRCUIT.to_string());
             let prefix = prefix.unwrap_or_else(|| "".to_string());
-            match tester::run_embeeded_tests(
-                ".",
-                &circuit,
-                debug,
-                skipcompile,
-                outputwitness,
-                &prefix,
-            ) {
-                Ok(Some((eval, err))) => dump_error(&eval, &err),
+            let project = circuit.load_project();
+            match tester::run_project_tests(&project, debug, skipcompile, outputwitness, &prefix) {
+                Ok(Some((eval, err))) => dump_error(&eval, &err, project.message_format),
                 Err(err) => warn!("Error: {:?}", err),
                 _ => {}
             }
         }
+        Command::Lint {
+            circuit,
+            uniqueness,
+        } => {
+            let project = circuit.load_project();
+            let mut failed = match lint::lint_project(&project) {
+                Ok(warnings) => {
+                    lint::print_warnings(&warnings, project.message_format);
+                    if !warnings.is_empty() {
+                        warn!("{} lint warnings", warnings.len());
+                    }
+                    !warnings.is_empty()
+                }
+                Err(err) => {
+                    warn!("Error: {:?}", err);
+                    process::exit(1);
+                }
+            };
+            if uniqueness {
+                match lint::undetermined_project(&project) {
+                    Ok(names) => {
+                        lint::print_undetermined(&names, project.message_format);
+                        failed |= !names.is_empty();
+                    }
+                    Err(err) => {
+                        warn!("Error: {:?}", err);
+                        process::exit(1);
+                    }
+                }
+            }
+            if failed {
+                process::exit(1);
+            }
+        }
+        Command::Debug {
+            circuit,
+            input,
+            breakpoints,
+            test,
+        } => {
+            let project = circuit.load_project();
+            let mut debugger = Debugger::stdio();
+            for breakpoint in breakpoints {
+                match breakpoint.parse() {
+                    Ok(breakpoint) => debugger = debugger.with_breakpoint(breakpoint),
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        process::exit(1);
+                    }
+                }
+            }
+            if debugger.breakpoints.is_empty() {
+                debugger = debugger.with_step();
+            }
+
+            // tests define their own inputs
+            let inputs = if test.is_some() {
+                Vec::new()
+            } else {
+                let input_path = input
+                    .or_else(|| project.manifest.paths.input.clone())
+                    .unwrap_or_else(|| DEFAULT_INPUT.to_string());
+                let inputs_json = fs::read_to_string(input_path).expect("cannot read inputs file");
+                za_prover::groth16::flatten_json("main", &inputs_json)
+                    .expect("cannot parse inputs file")
+            };
+
+            if debugger::debug_project(&project, debugger, test.as_deref(), inputs).is_err() {
+                process::exit(1);
+            }
+        }
+        Command::Profile {
+            circuit,
+            optimize,
+            folded,
+            unoptimized,
+            json,
+        } => {
+            let project = optimize.apply(circuit.load_project());
+            match profile::profile_project(&project) {
+                Ok(profile) if folded => print!("{}", profile.folded(!unoptimized)),
+                Ok(profile) if json => println!("{}", profile.to_json()),
+                Ok(profile) => print!("{}", profile),
+                Err(err) => {
+                    warn!("Error: {:?}", err);
+                    process::exit(1);
+                }
+            }
+        }
+        Command::Diff {
+            optimize,
+            json,
+            old,
+            new,
+        } => {
+            let diff = diff::diff(
+                &constraint_system(&old, &optimize),
+                &constraint_system(&new, &optimize),
+            );
+            if json {
+                println!("{}", diff.to_json());
+            } else {
+                print!("{}", diff);
+            }
+            if !diff.is_empty() {
+                process::exit(1);
+            }
+        }
+        Command::Fmt { check, files } => format_files(files, check),
         Command::Prove { pk, input, proof } => {
-            let pk_path = pk.unwrap_or_else(|| DEFAULT_PROVING_KEY.to_string());
-            let input_path = input.unwrap_or_else(|| DEFAULT_INPUT.to_string());
-            let proof_path = proof.unwrap_or_else(|| DEFAULT_PROOF.to_string());
+            let paths = Project::discover(".")
+                .expect("cannot load project manifest")
+                .map(|project| project.manifest.paths)
+                .unwrap_or_default();
+            let pk_path = pk
+                .or(paths.pk)
+                .unwrap_or_else(|| DEFAULT_PROVING_KEY.to_string());
+            let input_path = input
+                .or(paths.input)
+                .unwrap_or_else(|| DEFAULT_INPUT.to_string());
+            let proof_path = proof
+                .or(paths.proof)
+                .unwrap_or_else(|| DEFAULT_PROOF.to_string());
 
             let mut inputs_json = String::new();
             File::open(input_path)
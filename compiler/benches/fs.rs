@@ -0,0 +1,67 @@
+// Compares the montgomery field element with the plain BigUint modular
+//   arithmetic that FS used before, run with `cargo bench -p za-compiler`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use num_bigint::{BigInt, BigUint};
+use num_traits::identities::{One, Zero};
+
+use za_compiler::algebra::FS;
+
+const FIELD: &str = "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+const A: &str = "9500355645281097298327339582917520358289373648211014545117025880451473325397";
+const B: &str = "17447564478838624580712913214431541307315473225689066735214290127106306185421";
+
+fn biguint(n: &str) -> BigUint {
+    BigUint::parse_bytes(n.as_bytes(), 10).unwrap()
+}
+
+// the inverse with the extended euclidean algorithm, as the previous FS
+fn gcd_inverse(a: &BigInt, field: &BigInt) -> BigInt {
+    let (mut s, mut old_s) = (BigInt::zero(), BigInt::one());
+    let (mut r, mut old_r) = (field.clone(), a.clone());
+    while !r.is_zero() {
+        let quotient = &old_r / &r;
+        old_r -= &quotient * &r;
+        std::mem::swap(&mut old_r, &mut r);
+        old_s -= &quotient * &s;
+        std::mem::swap(&mut old_s, &mut s);
+    }
+    ((old_s % field) + field) % field
+}
+
+fn bench_add(c: &mut Criterion) {
+    let (field, a, b) = (biguint(FIELD), biguint(A), biguint(B));
+    c.bench_function("biguint add", move |bench| {
+        bench.iter(|| (black_box(&a) + black_box(&b)) % &field)
+    });
+    let (a, b) = (FS::parse(A).unwrap(), FS::parse(B).unwrap());
+    c.bench_function("fs add", move |bench| {
+        bench.iter(|| black_box(&a) + black_box(&b))
+    });
+}
+
+fn bench_mul(c: &mut Criterion) {
+    let (field, a, b) = (biguint(FIELD), biguint(A), biguint(B));
+    c.bench_function("biguint mul", move |bench| {
+        bench.iter(|| (black_box(&a) * black_box(&b)) % &field)
+    });
+    let (a, b) = (FS::parse(A).unwrap(), FS::parse(B).unwrap());
+    c.bench_function("fs mul", move |bench| {
+        bench.iter(|| black_box(&a) * black_box(&b))
+    });
+}
+
+fn bench_inverse(c: &mut Criterion) {
+    let field = BigInt::from(biguint(FIELD));
+    let a = BigInt::from(biguint(A));
+    c.bench_function("biguint inverse", move |bench| {
+        bench.iter(|| gcd_inverse(black_box(&a), &field))
+    });
+    let (one, a) = (FS::one(), FS::parse(A).unwrap());
+    c.bench_function("fs inverse", move |bench| {
+        bench.iter(|| (&one / black_box(&a)).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_add, bench_mul, bench_inverse);
+criterion_main!(benches);
@@ -1,8 +1,6 @@
 use num_bigint::{BigInt, BigUint};
-use num_traits;
-use num_traits::cast::FromPrimitive;
-use num_traits::cast::ToPrimitive;
-use num_traits::identities::{One, Zero};
+use num_traits::identities::Zero;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use std::cmp::Ordering;
 use std::fmt;
@@ -20,21 +18,64 @@ lazy_static! {
         BigUint::parse_bytes(BABYJUB_FIELD.as_bytes(), 10).unwrap();
     static ref BABYJUB_FIELD_INT: BigInt =
         BigInt::parse_bytes(BABYJUB_FIELD.as_bytes(), 10).unwrap();
-    static ref BABYJUB_FIELD_UINT_NEG: BigUint =
-        (BigUint::parse_bytes(BABYJUB_FIELD.as_bytes(), 10).unwrap() - BigUint::one())
-            / BigUint::from(2u32);
-    static ref ZERO: BigUint = BigUint::zero();
-    static ref ONE: BigUint = BigUint::one();
-    static ref MASK32: BigUint = BigUint::parse_bytes(b"ffff", 16).unwrap();
 }
 
+// 256 bit numbers as little endian 64 bit limbs
+type Limbs = [u64; 4];
+
+// the field modulus
+const MODULUS: Limbs = [
+    0x43e1_f593_f000_0001,
+    0x2833_e848_79b9_7091,
+    0xb850_45b6_8181_585d,
+    0x3064_4e72_e131_a029,
+];
+
+// (MODULUS - 1) / 2, the greatest non negative value
+const HALF_MODULUS: Limbs = [
+    0xa1f0_fac9_f800_0000,
+    0x9419_f424_3cdc_b848,
+    0xdc28_22db_40c0_ac2e,
+    0x1832_2739_7098_d014,
+];
+
+// MODULUS - 2, the exponent of the inverse
+const MODULUS_MINUS_TWO: Limbs = [
+    0x43e1_f593_efff_ffff,
+    0x2833_e848_79b9_7091,
+    0xb850_45b6_8181_585d,
+    0x3064_4e72_e131_a029,
+];
+
+// 2^256 mod MODULUS, the montgomery form of one
+const R: Limbs = [
+    0xac96_341c_4fff_fffb,
+    0x36fc_7695_9f60_cd29,
+    0x666e_a36f_7879_462e,
+    0x0e0a_77c1_9a07_df2f,
+];
+
+// 2^512 mod MODULUS, to convert into the montgomery form
+const R2: Limbs = [
+    0x1bb8_e645_ae21_6da7,
+    0x53fe_3ab1_e35c_59e3,
+    0x8c49_833d_53bb_8085,
+    0x0216_d0b1_7f4e_44a5,
+];
+
+// -MODULUS^-1 mod 2^64
+const INV: u64 = 0xc2e1_f593_efff_ffff;
+
 // Field Scalar  ------------------------------------------------
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct FS(BigUint);
+/// An element of the BN256 scalar field, stored in montgomery form so the
+///   multiplications do not need a division. The comparisons, the formatting,
+///   the bitwise and integer operations use the canonical representative.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct FS(Limbs);
 
 impl FS {
-    fn field() -> &'static BigUint {
+    pub fn field() -> &'static BigUint {
         &BABYJUB_FIELD_UINT as &BigUint
     }
     fn field_int() -> &'static BigInt {
@@ -44,51 +85,53 @@ impl FS {
         if expr.starts_with("0x") {
             BigUint::parse_bytes(&expr.as_bytes()[2..], 16).map_or_else(
                 || Err(Error::InvalidFormat(format!("{} is not hexadecimal", expr))),
-                |v| Ok(FS(v)),
+                |v| Ok(FS::from(v)),
             )
         } else {
             BigUint::parse_bytes(expr.as_bytes(), 10).map_or_else(
                 || Err(Error::InvalidFormat(format!("{} is not decimal", expr))),
-                |v| Ok(FS(v)),
+                |v| Ok(FS::from(v)),
             )
         }
     }
 
     pub fn zero() -> Self {
-        FS(BigUint::zero())
+        FS([0; 4])
     }
     pub fn one() -> Self {
-        FS(BigUint::one())
+        FS(R)
     }
     pub fn to_bytes_le(&self) -> Vec<u8> {
-        self.0.to_bytes_le()
+        self.to_biguint().to_bytes_le()
     }
     pub fn into_repr(self) -> BigUint {
-        self.0
+        self.to_biguint()
     }
     pub fn is_one(&self) -> bool {
-        self.0.cmp(&ONE) == Ordering::Equal
+        self.0 == R
     }
     pub fn is_neg(&self) -> bool {
-        self.0.cmp(&BABYJUB_FIELD_UINT_NEG as &BigUint) == Ordering::Greater
+        cmp(&self.canonical(), &HALF_MODULUS) == Ordering::Greater
     }
     pub fn try_to_u64(&self) -> Option<u64> {
-        self.0.to_u64()
+        match self.canonical() {
+            [n, 0, 0, 0] => Some(n),
+            _ => None,
+        }
     }
     pub fn format(&self, plus_sign_at_start: bool) -> String {
         if self.is_neg() {
-            format!("-{}", (-self).0.to_str_radix(10))
+            format!("-{}", (-self).to_biguint().to_str_radix(10))
         } else if plus_sign_at_start {
-            format!("+{}", self.0.to_str_radix(10))
+            format!("+{}", self.to_biguint().to_str_radix(10))
         } else {
-            self.0.to_str_radix(10)
+            self.to_biguint().to_str_radix(10)
         }
     }
     pub fn shl(&self, rhs: &FS) -> Result<FS> {
-        if let Some(self_u64) = self.0.to_u64() {
-            if let Some(rhs_u64) = rhs.0.to_u64() {
-                let v = BigUint::from_u64(self_u64 << rhs_u64).unwrap();
-                return Ok(FS(v));
+        if let Some(self_u64) = self.try_to_u64() {
+            if let Some(rhs_u64) = rhs.try_to_u64() {
+                return Ok(FS::from(self_u64 << rhs_u64));
             }
         }
         Err(Error::InvalidOperation(
@@ -96,10 +139,9 @@ impl FS {
         ))
     }
     pub fn shr(&self, rhs: &FS) -> Result<FS> {
-        if let Some(self_u64) = self.0.to_u64() {
-            if let Some(rhs_u64) = rhs.0.to_u64() {
-                let v = BigUint::from_u64(self_u64 >> rhs_u64).unwrap();
-                return Ok(FS(v));
+        if let Some(self_u64) = self.try_to_u64() {
+            if let Some(rhs_u64) = rhs.try_to_u64() {
+                return Ok(FS::from(self_u64 >> rhs_u64));
             }
         }
         Err(Error::InvalidOperation(
@@ -107,54 +149,77 @@ impl FS {
         ))
     }
     pub fn pow(&self, rhs: &FS) -> FS {
-        FS::from(self.0.modpow(&rhs.0, FS::field()))
+        FS(pow(&self.0, &rhs.canonical()))
     }
 
     pub fn intdiv(&self, rhs: &FS) -> FS {
-        FS::from(&self.0 / &rhs.0)
+        FS::from(self.to_biguint() / rhs.to_biguint())
     }
     pub fn write_256_w32<W: Write>(&self, writer: &mut W) -> Result<()> {
-        let mut bytes = self.0.to_bytes_be();
-        while bytes.len() < 32 {
-            bytes.insert(0, 0);
+        // 32 bit words, least significant first, each one big endian
+        for limb in self.canonical().iter() {
+            writer.write_all(&((limb & 0xffff_ffff) as u32).to_be_bytes())?;
+            writer.write_all(&((limb >> 32) as u32).to_be_bytes())?;
         }
-        for n in (0..8).rev() {
-            writer.write_all(&bytes[n * 4..(n + 1) * 4])?;
-        }
-
         Ok(())
     }
-}
 
-impl fmt::Display for FS {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0.to_str_radix(10))
+    // the canonical representative, out of the montgomery form
+    fn canonical(&self) -> Limbs {
+        mont_mul(&self.0, &[1, 0, 0, 0])
+    }
+    fn from_canonical(limbs: Limbs) -> Self {
+        FS(mont_mul(&limbs, &R2))
+    }
+    fn to_biguint(&self) -> BigUint {
+        let bytes = self
+            .canonical()
+            .iter()
+            .flat_map(|limb| limb.to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+        BigUint::from_bytes_le(&bytes)
     }
 }
 
-impl PartialEq for FS {
-    fn eq(&self, other: &FS) -> bool {
-        self.0.eq(&other.0)
+impl fmt::Display for FS {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_biguint().to_str_radix(10))
     }
 }
 
 impl PartialOrd for FS {
     fn partial_cmp(&self, other: &FS) -> Option<Ordering> {
-        self.0.partial_cmp(&other.0)
+        Some(self.cmp(other))
     }
 }
 
-impl Eq for FS {}
-
 impl Ord for FS {
     fn cmp(&self, other: &FS) -> Ordering {
-        self.0.cmp(&other.0)
+        cmp(&self.canonical(), &other.canonical())
+    }
+}
+
+// serialized as the canonical BigUint, as in the previous representation
+impl Serialize for FS {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.to_biguint().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FS {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        BigUint::deserialize(deserializer).map(FS::from)
     }
 }
 
 impl From<&BigUint> for FS {
     fn from(n: &BigUint) -> Self {
-        FS(n % FS::field())
+        let bytes = (n % FS::field()).to_bytes_le();
+        let mut limbs = [0u64; 4];
+        for (i, byte) in bytes.iter().enumerate() {
+            limbs[i / 8] |= u64::from(*byte) << (8 * (i % 8));
+        }
+        FS::from_canonical(limbs)
     }
 }
 
@@ -166,7 +231,7 @@ impl From<BigUint> for FS {
 
 impl From<u64> for FS {
     fn from(n: u64) -> Self {
-        FS::from(BigUint::from_u64(n).unwrap())
+        FS::from_canonical([n, 0, 0, 0])
     }
 }
 
@@ -178,17 +243,17 @@ impl From<BigInt> for FS {
 
 impl From<&BigInt> for FS {
     fn from(n: &BigInt) -> Self {
-        let v = n % (&BABYJUB_FIELD_INT as &BigInt);
-        FS(v.to_biguint().unwrap())
+        let v = normalize(n, FS::field_int());
+        FS::from(v.to_biguint().unwrap())
     }
 }
 
 impl AlgZero for FS {
     fn zero() -> Self {
-        FS(num_traits::Zero::zero())
+        FS::zero()
     }
     fn is_zero(&self) -> bool {
-        <BigUint as num_traits::Zero>::is_zero(&self.0)
+        self.0 == [0; 4]
     }
 }
 
@@ -203,7 +268,11 @@ impl<'a> Neg for &'a FS {
     type Output = FS;
 
     fn neg(self) -> FS {
-        FS::from(FS::field() - &self.0)
+        if self.is_zero() {
+            FS::zero()
+        } else {
+            FS(sub(&MODULUS, &self.0))
+        }
     }
 }
 
@@ -212,7 +281,7 @@ impl<'a> Add<&'a FS> for &'a FS {
     type Output = FS;
 
     fn add(self, rhs: &'a FS) -> FS {
-        FS::from(&(self.0) + &rhs.0)
+        FS(reduce(add(&self.0, &rhs.0)))
     }
 }
 
@@ -221,7 +290,7 @@ impl<'a> Mul<&'a FS> for &'a FS {
     type Output = FS;
 
     fn mul(self, rhs: &'a FS) -> FS {
-        FS::from(&(self.0) * &rhs.0)
+        FS(mont_mul(&self.0, &rhs.0))
     }
 }
 
@@ -231,19 +300,15 @@ impl<'a> Div<&'a FS> for &'a FS {
 
     #[allow(clippy::suspicious_arithmetic_impl)]
     fn div(self, rhs: &'a FS) -> Result<FS> {
-        let GcdResult { gcd, c1: c, .. } = extended_gcd(
-            BigInt::from_biguint(num_bigint::Sign::Plus, rhs.0.clone()),
-            FS::field_int().clone(),
-        );
-
-        if gcd == BigInt::one() {
-            let rhs_inv = normalize(&c, FS::field_int()).to_biguint().unwrap();
-            Ok(FS::from(&self.0 * rhs_inv))
-        } else {
+        if rhs.is_zero() {
             Err(Error::InvalidOperation(format!(
                 "Cannot find inv gcd={}",
-                gcd.to_str_radix(10)
+                BABYJUB_FIELD
             )))
+        } else {
+            // a^(p-2) is the inverse of a by Fermat's little theorem
+            let rhs_inv = pow(&rhs.0, &MODULUS_MINUS_TWO);
+            Ok(FS(mont_mul(&self.0, &rhs_inv)))
         }
     }
 }
@@ -252,7 +317,7 @@ impl<'a> Div<&'a FS> for &'a FS {
 impl<'a> AddAssign<&'a FS> for FS {
     // addLCNum
     fn add_assign(&mut self, rhs: &'a FS) {
-        *self = (self as &Self) + rhs;
+        self.0 = reduce(add(&self.0, &rhs.0));
     }
 }
 
@@ -260,8 +325,8 @@ impl<'a> AddAssign<&'a FS> for FS {
 impl<'a> Rem<&'a FS> for &'a FS {
     type Output = Result<FS>;
     fn rem(self, rhs: &'a FS) -> Result<FS> {
-        if !rhs.0.is_zero() {
-            Ok(FS(&self.0 % &rhs.0))
+        if !rhs.is_zero() {
+            Ok(FS::from(self.to_biguint() % rhs.to_biguint()))
         } else {
             Err(Error::InvalidOperation("Divison by zero".to_string()))
         }
@@ -272,8 +337,8 @@ impl<'a> Rem<&'a FS> for &'a FS {
 impl<'a> Shl<&'a FS> for &'a FS {
     type Output = Result<FS>;
     fn shl(self, rhs: &'a FS) -> Result<FS> {
-        if let Some(rhs_usize) = rhs.0.to_usize() {
-            Ok(FS::from(&self.0 << rhs_usize))
+        if let Some(rhs_u64) = rhs.try_to_u64() {
+            Ok(FS::from(self.to_biguint() << rhs_u64 as usize))
         } else {
             Err(Error::InvalidOperation(
                 "Only can shl on 64 bit values".to_string(),
@@ -286,8 +351,8 @@ impl<'a> Shl<&'a FS> for &'a FS {
 impl<'a> Shr<&'a FS> for &'a FS {
     type Output = Result<FS>;
     fn shr(self, rhs: &'a FS) -> Result<FS> {
-        if let Some(rhs_usize) = rhs.0.to_usize() {
-            Ok(FS::from(&self.0 >> rhs_usize))
+        if let Some(rhs_u64) = rhs.try_to_u64() {
+            Ok(FS::from(self.to_biguint() >> rhs_u64 as usize))
         } else {
             Err(Error::InvalidOperation(
                 "Only can shr on 64 bit values".to_string(),
@@ -300,7 +365,8 @@ impl<'a> Shr<&'a FS> for &'a FS {
 impl<'a> BitAnd<&'a FS> for &'a FS {
     type Output = FS;
     fn bitand(self, rhs: &'a FS) -> FS {
-        FS(&self.0 & &rhs.0)
+        let (a, b) = (self.canonical(), rhs.canonical());
+        FS::from_canonical([a[0] & b[0], a[1] & b[1], a[2] & b[2], a[3] & b[3]])
     }
 }
 
@@ -308,7 +374,7 @@ impl<'a> BitAnd<&'a FS> for &'a FS {
 impl<'a> BitOr<&'a FS> for &'a FS {
     type Output = FS;
     fn bitor(self, rhs: &'a FS) -> FS {
-        FS::from(&self.0 | &rhs.0)
+        FS::from(self.to_biguint() | rhs.to_biguint())
     }
 }
 
@@ -316,46 +382,115 @@ impl<'a> BitOr<&'a FS> for &'a FS {
 impl<'a> BitXor<&'a FS> for &'a FS {
     type Output = FS;
     fn bitxor(self, rhs: &'a FS) -> FS {
-        FS::from(&self.0 ^ &rhs.0)
+        FS::from(self.to_biguint() ^ rhs.to_biguint())
     }
 }
 
-// helpers --------------------------------------------------------------------
+// montgomery arithmetic ---------------------------------------------------------
+
+// a + b + carry, with the carry out
+#[inline(always)]
+fn adc(a: u64, b: u64, carry: &mut u64) -> u64 {
+    let t = u128::from(a) + u128::from(b) + u128::from(*carry);
+    *carry = (t >> 64) as u64;
+    t as u64
+}
+
+// a - b - borrow, with the borrow out
+#[inline(always)]
+fn sbb(a: u64, b: u64, borrow: &mut u64) -> u64 {
+    let (d1, o1) = a.overflowing_sub(b);
+    let (d2, o2) = d1.overflowing_sub(*borrow);
+    *borrow = (o1 || o2) as u64;
+    d2
+}
 
-pub struct GcdResult {
-    /// Greatest common divisor.
-    pub gcd: BigInt,
-    /// Coefficients such that: gcd(a, b) = c1*a + c2*b
-    pub c1: BigInt,
-    pub c2: BigInt,
+// a + b * c + carry, with the carry out
+#[inline(always)]
+fn mac(a: u64, b: u64, c: u64, carry: &mut u64) -> u64 {
+    let t = u128::from(a) + u128::from(b) * u128::from(c) + u128::from(*carry);
+    *carry = (t >> 64) as u64;
+    t as u64
 }
 
-/// Taken from unknown source, re-check
-/// Calculate greatest common divisor and the corresponding coefficients.
-#[allow(clippy::many_single_char_names)]
-pub fn extended_gcd(a: BigInt, b: BigInt) -> GcdResult {
-    // Euclid's extended algorithm
-    let (mut s, mut old_s) = (BigInt::zero(), BigInt::one());
-    let (mut t, mut old_t) = (BigInt::one(), BigInt::zero());
-    let (mut r, mut old_r) = (b, a);
+fn cmp(a: &Limbs, b: &Limbs) -> Ordering {
+    for i in (0..4).rev() {
+        match a[i].cmp(&b[i]) {
+            Ordering::Equal => continue,
+            ord => return ord,
+        }
+    }
+    Ordering::Equal
+}
+
+// the modulus is lower than 2^254, so the sum of two reduced values fits
+fn add(a: &Limbs, b: &Limbs) -> Limbs {
+    let mut carry = 0;
+    let mut r = [0u64; 4];
+    for i in 0..4 {
+        r[i] = adc(a[i], b[i], &mut carry);
+    }
+    r
+}
 
-    while r != BigInt::zero() {
-        let quotient = &old_r / &r;
-        old_r -= &quotient * &r;
-        std::mem::swap(&mut old_r, &mut r);
-        old_s -= &quotient * &s;
-        std::mem::swap(&mut old_s, &mut s);
-        old_t -= quotient * &t;
-        std::mem::swap(&mut old_t, &mut t);
+fn sub(a: &Limbs, b: &Limbs) -> Limbs {
+    let mut borrow = 0;
+    let mut r = [0u64; 4];
+    for i in 0..4 {
+        r[i] = sbb(a[i], b[i], &mut borrow);
+    }
+    r
+}
+
+// a value lower than twice the modulus into [0, MODULUS)
+fn reduce(a: Limbs) -> Limbs {
+    if cmp(&a, &MODULUS) == Ordering::Less {
+        a
+    } else {
+        sub(&a, &MODULUS)
+    }
+}
+
+// a * b / 2^256 mod MODULUS, coarsely integrated operand scanning
+fn mont_mul(a: &Limbs, b: &Limbs) -> Limbs {
+    let mut t = [0u64; 6];
+    for b_i in b.iter() {
+        let mut carry = 0;
+        for j in 0..4 {
+            t[j] = mac(t[j], a[j], *b_i, &mut carry);
+        }
+        t[4] = adc(t[4], carry, &mut t[5]);
+
+        let m = t[0].wrapping_mul(INV);
+        let mut carry = 0;
+        mac(t[0], m, MODULUS[0], &mut carry);
+        for j in 1..4 {
+            t[j - 1] = mac(t[j], m, MODULUS[j], &mut carry);
+        }
+        let mut high = 0;
+        t[3] = adc(t[4], carry, &mut high);
+        t[4] = t[5] + high;
+        t[5] = 0;
     }
+    reduce([t[0], t[1], t[2], t[3]])
+}
 
-    GcdResult {
-        gcd: old_r,
-        c1: old_s,
-        c2: old_t,
+// base^exp for a montgomery base and a canonical exponent
+fn pow(base: &Limbs, exp: &Limbs) -> Limbs {
+    let mut res = R;
+    for i in (0..4).rev() {
+        for bit in (0..64).rev() {
+            res = mont_mul(&res, &res);
+            if (exp[i] >> bit) & 1 == 1 {
+                res = mont_mul(&res, base);
+            }
+        }
     }
+    res
 }
 
+// helpers --------------------------------------------------------------------
+
 /// Find the standard representation of a (mod n).
 pub fn normalize(a: &BigInt, n: &BigInt) -> BigInt {
     let a = a % n;
@@ -464,4 +599,56 @@ mod test {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_montgomery_matches_biguint() -> Result<()> {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let field = FS::field();
+        let random = |rng: &mut rand::rngs::ThreadRng| {
+            let bytes: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
+            BigUint::from_bytes_le(&bytes) % field
+        };
+        for _ in 0..100 {
+            let (a, b) = (random(&mut rng), random(&mut rng));
+            let (fa, fb) = (FS::from(&a), FS::from(&b));
+            assert_eq!(a, fa.clone().into_repr());
+            assert_eq!((&a + &b) % field, (&fa + &fb).into_repr());
+            assert_eq!((&a * &b) % field, (&fa * &fb).into_repr());
+            assert_eq!((field - &a) % field, (-&fa).into_repr());
+            assert_eq!(a.cmp(&b), fa.cmp(&fb));
+            assert_eq!(fa, &(&fa / &fb)? * &fb);
+            assert_eq!(
+                serde_cbor::to_vec(&a).unwrap(),
+                serde_cbor::to_vec(&fa).unwrap()
+            );
+            assert_eq!(
+                fa,
+                serde_cbor::from_slice(&serde_cbor::to_vec(&fa).unwrap()).unwrap()
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_fs_limits() {
+        let minus_one = -&FS::one();
+        assert_eq!(FS::zero(), &minus_one + &FS::one());
+        assert_eq!(FS::one(), &minus_one * &minus_one);
+        assert_eq!(FS::zero(), -&FS::zero());
+        assert_eq!(Some(u64::MAX), FS::from(u64::MAX).try_to_u64());
+        assert_eq!(FS::zero(), FS::parse(BABYJUB_FIELD).unwrap());
+        assert_eq!("8", FS::from(2).pow(&FS::from(3)).to_string());
+        assert!(FS::parse(
+            "10944121435919637611123202872628637544274182200208017171849102093287904247809"
+        )
+        .unwrap()
+        .is_neg());
+        assert!(!FS::parse(
+            "10944121435919637611123202872628637544274182200208017171849102093287904247808"
+        )
+        .unwrap()
+        .is_neg());
+        assert!((&FS::one() / &FS::zero()).is_err());
+    }
 }
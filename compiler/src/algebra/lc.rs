@@ -1,5 +1,8 @@
+use serde::{Deserialize, Deserializer};
+
+use std::cmp::Ordering;
 use std::fmt;
-use std::iter;
+use std::iter::{self, FromIterator};
 use std::ops::{Add, Mul, Neg};
 
 use super::fs::FS;
@@ -8,7 +11,11 @@ use super::AlgZero;
 pub type SignalId = usize;
 pub const SIGNAL_ONE: SignalId = 0;
 
-#[derive(Clone, Serialize, Deserialize)]
+/// A linear combination of signals, with its terms sorted by signal and
+///   without repeated signals. The operations rely on this order, so the
+///   terms written directly must be sorted, or the LC collected from an
+///   iterator that sorts and merges them
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
 pub struct LC(pub Vec<(SignalId, FS)>);
 
 impl LC {
@@ -18,25 +25,35 @@ impl LC {
     pub fn from_signal(signal: SignalId, fs: FS) -> Self {
         LC(vec![(signal, fs)])
     }
+    /// The terms, sorted by signal
+    pub fn terms(&self) -> &[(SignalId, FS)] {
+        &self.0
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, (SignalId, FS)> {
+        self.0.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
     pub fn get(&self, signal: SignalId) -> Option<&FS> {
-        if let Some(p) = self.0.iter().position(|x| x.0 == signal) {
-            Some(&self.0[p].1)
-        } else {
-            None
-        }
+        self.position(signal).ok().map(|p| &self.0[p].1)
     }
     pub fn set<F>(&mut self, signal: SignalId, func: F)
     where
         F: FnOnce(Option<&FS>) -> FS,
     {
-        if let Some(p) = self.0.iter().position(|x| x.0 == signal) {
-            self.0[p].1 = func(Some(&self.0[p].1));
-        } else {
-            self.0.push((signal, func(None)));
+        match self.position(signal) {
+            Ok(p) => self.0[p].1 = func(Some(&self.0[p].1)),
+            Err(p) => self.0.insert(p, (signal, func(None))),
         }
     }
     pub fn rm(&mut self, signal: SignalId) {
-        self.0.retain(|(s, _)| *s != signal);
+        if let Ok(p) = self.position(signal) {
+            self.0.remove(p);
+        }
     }
     pub fn format<F>(&self, func: F) -> String
     where
@@ -52,6 +69,42 @@ impl LC {
             "0".to_string()
         }
     }
+    fn position(&self, signal: SignalId) -> std::result::Result<usize, usize> {
+        self.0.binary_search_by_key(&signal, |(s, _)| *s)
+    }
+}
+
+// the terms in any order, the repeated signals are added and the zero terms
+//   removed
+impl FromIterator<(SignalId, FS)> for LC {
+    fn from_iter<I: IntoIterator<Item = (SignalId, FS)>>(terms: I) -> Self {
+        let mut terms = terms.into_iter().collect::<Vec<_>>();
+        terms.sort_by_key(|(s, _)| *s);
+        let mut v: Vec<(SignalId, FS)> = Vec::with_capacity(terms.len());
+        for (signal, e) in terms {
+            match v.last_mut() {
+                Some(last) if last.0 == signal => last.1 += &e,
+                _ => v.push((signal, e)),
+            }
+        }
+        v.retain(|v| !v.1.is_zero());
+        LC(v)
+    }
+}
+
+impl<'a> IntoIterator for &'a LC {
+    type Item = &'a (SignalId, FS);
+    type IntoIter = std::slice::Iter<'a, (SignalId, FS)>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+// the proving keys may have been generated with unsorted terms
+impl<'de> Deserialize<'de> for LC {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        Vec::<(SignalId, FS)>::deserialize(deserializer).map(|v| v.into_iter().collect())
+    }
 }
 
 impl Default for LC {
@@ -101,15 +154,10 @@ impl<'a> Add<&'a FS> for &'a LC {
     type Output = LC;
 
     fn add(self, rhs: &'a FS) -> LC {
-        let mut v = self.0.clone();
-
-        if let Some(i) = v.iter().position(|(s, _)| *s == SIGNAL_ONE) {
-            v[i].1 += rhs;
-        } else {
-            v.push((SIGNAL_ONE, rhs.clone()))
-        }
-        v.retain(|v| !v.1.is_zero());
-        LC(v)
+        let mut lc = self.clone();
+        lc.set(SIGNAL_ONE, |v| v.map_or(rhs.clone(), |v| v + rhs));
+        lc.0.retain(|v| !v.1.is_zero());
+        lc
     }
 }
 
@@ -131,12 +179,24 @@ impl<'a> Add<&'a LC> for &'a LC {
     type Output = LC;
 
     fn add(self, rhs: &'a LC) -> LC {
-        let mut v = self.0.clone();
-        for (signal, e) in &rhs.0 {
-            if let Some(i) = v.iter().position(|(s, _)| s == signal) {
-                v[i].1 += e;
-            } else {
-                v.push((*signal, e.clone()));
+        // merge both sorted terms
+        let mut v = Vec::with_capacity(self.0.len() + rhs.0.len());
+        let (mut lhs, mut rhs) = (self.0.iter().peekable(), rhs.0.iter().peekable());
+        loop {
+            let order = match (lhs.peek(), rhs.peek()) {
+                (Some(l), Some(r)) => l.0.cmp(&r.0),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            match order {
+                Ordering::Less => v.push(lhs.next().unwrap().clone()),
+                Ordering::Greater => v.push(rhs.next().unwrap().clone()),
+                Ordering::Equal => {
+                    let (signal, l) = lhs.next().unwrap();
+                    let (_, r) = rhs.next().unwrap();
+                    v.push((*signal, l + r));
+                }
             }
         }
         v.retain(|v| !v.1.is_zero());
@@ -183,10 +243,10 @@ mod test {
         let s1 = 1 as SignalId;
 
         let lc_1s1 = &LC::from_signal(s1, FS::one());
-        assert_eq!("1s1+2s0", (&(lc_1s1 + one) + one).to_string());
+        assert_eq!("2s0+1s1", (&(lc_1s1 + one) + one).to_string());
 
         let lc_1s1_4one = &(lc_1s1 + two);
-        assert_eq!("2s1+4s0", (lc_1s1_4one * two).to_string());
+        assert_eq!("4s0+2s1", (lc_1s1_4one * two).to_string());
     }
 
     #[test]
@@ -219,4 +279,33 @@ mod test {
         assert_eq!("2s1+1s2", lc_2s1_1s2.to_string());
         assert_eq!("[2s1+1s2]*[1s2]+[ ]", (&lc_2s1_1s2 * lc_1s2).to_string());
     }
+
+    #[test]
+    fn test_lc_sorted() {
+        let lc = |terms: &[(SignalId, u64)]| {
+            terms
+                .iter()
+                .map(|(s, v)| (*s, FS::from(*v)))
+                .collect::<LC>()
+        };
+        let lc_3s3_1s1_2s3 = lc(&[(3, 3), (1, 1), (3, 2)]);
+        assert_eq!("1s1+5s3", lc_3s3_1s1_2s3.to_string());
+
+        let mut sum = &lc(&[(1, 1), (4, 1), (6, 1)]) + &lc(&[(2, 1), (4, 1), (5, 1)]);
+        assert_eq!("1s1+1s2+2s4+1s5+1s6", sum.to_string());
+        sum = &sum + &-&lc(&[(4, 2), (6, 1)]);
+        assert_eq!("1s1+1s2+1s5", sum.to_string());
+
+        sum.set(3, |_| FS::from(7));
+        sum.set(0, |_| FS::from(8));
+        assert_eq!("8s0+1s1+1s2+7s3+1s5", sum.to_string());
+        assert_eq!("7", sum.get(3).unwrap().to_string());
+        assert!(sum.get(4).is_none());
+
+        // unsorted terms, as in the proving keys of the previous versions
+        let unsorted = serde_cbor::to_vec(&vec![(2, FS::from(1)), (1, FS::from(2))]).unwrap();
+        let lc: LC = serde_cbor::from_slice(&unsorted).unwrap();
+        assert_eq!("2s1+1s2", lc.to_string());
+        assert_eq!(unsorted.len(), serde_cbor::to_vec(&lc).unwrap().len());
+    }
 }
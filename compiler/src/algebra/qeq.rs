@@ -5,7 +5,7 @@ use super::fs::FS;
 use super::lc::{SignalId, LC};
 use super::AlgZero;
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct QEQ {
     pub a: LC,
     pub b: LC,
@@ -22,7 +22,7 @@ impl QEQ {
         F: Fn(SignalId) -> String,
     {
         let f = |v: &LC| {
-            if !v.0.is_empty() {
+            if !v.is_empty() {
                 v.format(&func)
             } else {
                 " ".to_string()
@@ -30,6 +30,25 @@ impl QEQ {
         };
         format!("[{}]*[{}]+[{}]", f(&self.a), f(&self.b), f(&self.c))
     }
+
+    /// The same constraint with `a` and `b` scaled so their first coefficient
+    ///   is one and `a` not greater than `b`, the linear ones only have `c`,
+    ///   scaled so its first coefficient is one
+    pub fn canonical(&self) -> QEQ {
+        // the inverse of the first coefficient, the zero LC is kept
+        let inv = |lc: &LC| match lc.terms().first() {
+            Some((_, k)) if !k.is_one() => (&FS::one() / k).unwrap(),
+            _ => FS::one(),
+        };
+        if self.a.is_zero() || self.b.is_zero() {
+            return QEQ::from(&self.c * &inv(&self.c));
+        }
+        let (inv_a, inv_b) = (inv(&self.a), inv(&self.b));
+        let (a, b) = (&self.a * &inv_a, &self.b * &inv_b);
+        let c = &self.c * &(&inv_a * &inv_b);
+        let (a, b) = if a <= b { (a, b) } else { (b, a) };
+        QEQ { a, b, c }
+    }
 }
 
 impl AlgZero for QEQ {
@@ -169,4 +188,30 @@ mod test {
         let neq_qeq = &-qeq;
         assert_eq!("[2s1]*[1s1]+[1s1]", (-neq_qeq).to_string());
     }
+
+    #[test]
+    fn test_qeq_canonical() {
+        let k = |n: u64| FS::from(n);
+        let lc = |terms: &[(SignalId, FS)]| terms.iter().cloned().collect::<LC>();
+
+        // 2s1 * 3s2 + 6s3 = 0 is s1 * s2 + s3 = 0
+        let qeq = &(&lc(&[(1, k(2))]) * &lc(&[(2, k(3))])) + &lc(&[(3, k(6))]);
+        assert_eq!("[1s1]*[1s2]+[1s3]", qeq.canonical().to_string());
+
+        // -2s1 + 4s2 = 0 is s1 - 2s2 = 0
+        let qeq = QEQ::from(lc(&[(1, -&k(2)), (2, k(4))]));
+        assert_eq!("[ ]*[ ]+[1s1-2s2]", qeq.canonical().to_string());
+
+        // the same constraint scaled, negated or with a and b swapped
+        let a = lc(&[(2, k(2)), (1, k(4))]);
+        let b = lc(&[(1, -&k(3))]);
+        let c = lc(&[(0, k(6)), (3, k(5))]);
+        let qeq = &(&a * &b) + &c;
+        let canonical = qeq.canonical();
+        assert_eq!(canonical, (&qeq * &k(7)).canonical());
+        assert_eq!(canonical, (-&qeq).canonical());
+        assert_eq!(canonical, (&(&b * &a) + &c).canonical());
+        assert_eq!(canonical, canonical.canonical());
+        assert_eq!(lc(&[(1, FS::one())]), canonical.a);
+    }
 }
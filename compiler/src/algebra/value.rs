@@ -30,8 +30,8 @@ impl Value {
     }
     pub fn try_to_signal(&self) -> Option<SignalId> {
         if let Value::LinearCombination(lc) = self {
-            if lc.0.len() == 1 && lc.0[0].1.is_one() {
-                return Some(lc.0[0].0);
+            if lc.len() == 1 && lc.terms()[0].1.is_one() {
+                return Some(lc.terms()[0].0);
             }
         }
         None
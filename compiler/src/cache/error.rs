@@ -0,0 +1,19 @@
+#[derive(Debug)]
+pub enum Error {
+    Io(String, String),
+    Cbor(String),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io("".to_string(), err.to_string())
+    }
+}
+
+impl From<serde_cbor::error::Error> for Error {
+    fn from(err: serde_cbor::error::Error) -> Self {
+        Error::Cbor(err.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
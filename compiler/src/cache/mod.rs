@@ -0,0 +1,294 @@
+mod error;
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use blake2_rfc::blake2b::Blake2b;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use za_parser::ast::{BodyElementP, SignalType};
+
+use crate::algebra::{SignalId, Value, QEQ};
+use crate::optimizer::{Report, Substitutions};
+use crate::types::{Constraints, Provenance, Signals};
+
+pub use self::error::{Error, Result};
+
+pub const CACHE_DIR: &str = ".za-cache";
+
+// changes when the layout of the cached asts or circuits changes
+const CACHE_FORMAT: u32 = 4;
+
+/// Blake2b hash of a source file, in hex
+pub fn hash(content: &[u8]) -> String {
+    let mut hasher = Blake2b::new(64);
+    hasher.update(content);
+    hex::encode(hasher.finalize().as_bytes())
+}
+
+/// A compiled and optimized constraint system, with the source files and
+///   asts used to generate it
+pub struct CompiledCircuit {
+    pub main: Option<String>,
+    pub processed_files: Vec<(String, String)>,
+    // paths searched for the includes without a file, see `Evaluator`
+    pub skipped_includes: Vec<String>,
+    pub asts: Vec<BodyElementP>,
+    pub signals: Signals,
+    pub constraints: Constraints,
+    pub removed_signals: Vec<SignalId>,
+    // how to compute the removed signals from the others
+    pub substitutions: Substitutions,
+    // what the optimizer did when the circuit was compiled
+    pub report: Report,
+    // `Constraints::circuit_hash` of the optimized constraints
+    pub circuit_hash: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedCircuit {
+    main: Option<String>,
+    processed_files: Vec<(String, String)>,
+    skipped_includes: Vec<String>,
+    asts: Vec<BodyElementP>,
+    signals: Vec<(String, SignalType, Option<Value>)>,
+    constraints: Vec<(QEQ, Option<Provenance>)>,
+    removed_signals: Vec<SignalId>,
+    substitutions: Substitutions,
+    report: Report,
+    circuit_hash: String,
+}
+
+impl CachedCircuit {
+    fn from_compiled(circuit: &CompiledCircuit) -> Self {
+        let signals = (1..circuit.signals.len())
+            .map(|id| {
+                let signal = circuit.signals.get_by_id(id).unwrap();
+                (
+                    signal.full_name.to_string(),
+                    signal.xtype,
+                    signal.value.clone(),
+                )
+            })
+            .collect();
+        let constraints = (0..circuit.constraints.len())
+            .map(|i| {
+                let provenance = circuit.constraints.get_provenance(i);
+                (circuit.constraints.get(i), provenance)
+            })
+            .collect();
+
+        CachedCircuit {
+            main: circuit.main.clone(),
+            processed_files: circuit.processed_files.clone(),
+            skipped_includes: circuit.skipped_includes.clone(),
+            asts: circuit.asts.clone(),
+            signals,
+            constraints,
+            removed_signals: circuit.removed_signals.clone(),
+            substitutions: circuit.substitutions.clone(),
+            report: circuit.report.clone(),
+            circuit_hash: circuit.circuit_hash.clone(),
+        }
+    }
+
+    fn into_compiled(self) -> CompiledCircuit {
+        let mut signals = Signals::default();
+        for (full_name, xtype, value) in self.signals {
+            signals.insert(full_name, xtype, value);
+        }
+        let mut constraints = Constraints::default();
+        for (qeq, provenance) in self.constraints {
+            constraints.push(qeq, provenance);
+        }
+
+        CompiledCircuit {
+            main: self.main,
+            processed_files: self.processed_files,
+            skipped_includes: self.skipped_includes,
+            asts: self.asts,
+            signals,
+            constraints,
+            removed_signals: self.removed_signals,
+            substitutions: self.substitutions,
+            report: self.report,
+            circuit_hash: self.circuit_hash,
+        }
+    }
+}
+
+/// On-disk cache of parsed asts, keyed by the source hash, and of compiled
+///   circuits, keyed by the project configuration and invalidated when any of
+///   its source files changes
+#[derive(Clone, Debug)]
+pub struct Cache {
+    pub path: PathBuf,
+}
+
+impl Cache {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Cache {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn load_ast(&self, hash: &str) -> Option<Vec<BodyElementP>> {
+        self.load(&self.ast_path(hash))
+    }
+
+    pub fn store_ast(&self, hash: &str, asts: &[BodyElementP]) -> Result<()> {
+        self.store(&self.ast_path(hash), &asts)
+    }
+
+    /// Loads the compiled circuit if none of its source files has been
+    ///   modified since it was stored, and no file has been added where it
+    ///   would be included instead of them
+    pub fn load_circuit(&self, key: &str) -> Option<CompiledCircuit> {
+        let cached: CachedCircuit = self.load(&self.circuit_path(key))?;
+        let unchanged = cached.processed_files.iter().all(|(path, file_hash)| {
+            fs::read(path)
+                .map(|content| &hash(&content) == file_hash)
+                .unwrap_or(false)
+        });
+        let resolved = cached
+            .skipped_includes
+            .iter()
+            .all(|path| !Path::new(path).exists());
+        if unchanged && resolved {
+            Some(cached.into_compiled())
+        } else {
+            None
+        }
+    }
+
+    pub fn store_circuit(&self, key: &str, circuit: &CompiledCircuit) -> Result<()> {
+        self.store(
+            &self.circuit_path(key),
+            &CachedCircuit::from_compiled(circuit),
+        )
+    }
+
+    // the entries of other compiler versions are not reused, since they can
+    //   parse or compile the same sources differently
+    fn versioned_path(&self) -> PathBuf {
+        self.path
+            .join(format!("v{}-{}", CACHE_FORMAT, env!("CARGO_PKG_VERSION")))
+    }
+
+    fn ast_path(&self, hash: &str) -> PathBuf {
+        self.versioned_path()
+            .join("ast")
+            .join(format!("{}.cbor", hash))
+    }
+
+    fn circuit_path(&self, key: &str) -> PathBuf {
+        self.versioned_path()
+            .join("circuit")
+            .join(format!("{}.cbor", key))
+    }
+
+    // unreadable or outdated entries are just cache misses
+    fn load<T: DeserializeOwned>(&self, path: &Path) -> Option<T> {
+        let file = File::open(path).ok()?;
+        serde_cbor::from_reader(file).ok()
+    }
+
+    fn store<T: Serialize>(&self, path: &Path, value: &T) -> Result<()> {
+        let internal = || {
+            fs::create_dir_all(path.parent().unwrap())?;
+            let content = serde_cbor::to_vec(value)?;
+            File::create(path)?.write_all(&content)?;
+            Ok(())
+        };
+        internal().map_err(|err| match err {
+            Error::Io(_, msg) => Error::Io(format!("{:?}", path), msg),
+            err => err,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algebra::{FS, LC};
+    use crate::optimizer::{Level, Stats};
+
+    fn tmp_cache(name: &str) -> Cache {
+        let path = std::env::temp_dir().join(format!("za-cache-test-{}", name));
+        let _ = fs::remove_dir_all(&path);
+        Cache::new(path)
+    }
+
+    #[test]
+    fn test_cache_ast() -> Result<()> {
+        let cache = tmp_cache("ast");
+        let asts = za_parser::parse("template T() { signal input a; }").unwrap();
+        assert!(cache.load_ast("01").is_none());
+        cache.store_ast("01", &asts)?;
+        assert_eq!(1, cache.load_ast("01").unwrap().len());
+
+        // stored apart from the entries of other versions
+        let version = format!("v{}-{}", CACHE_FORMAT, env!("CARGO_PKG_VERSION"));
+        assert!(cache.ast_path("01").starts_with(cache.path.join(version)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_circuit_invalidation() -> Result<()> {
+        let cache = tmp_cache("circuit");
+        let source = cache.path.join("circuit.za");
+        fs::create_dir_all(&cache.path)?;
+        fs::write(&source, "// v1")?;
+
+        let mut signals = Signals::default();
+        let a = signals.insert("main.a".to_string(), SignalType::PublicInput, None);
+        let mut constraints = Constraints::default();
+        let lc = LC::from_signal(a, FS::one());
+        constraints.push(QEQ::new(lc.clone(), lc, LC::new()), None);
+
+        let report = Report::new(
+            Level::O2,
+            &Stats::default(),
+            1,
+            1,
+            &signals,
+            &Substitutions::default(),
+            Default::default(),
+        );
+        let circuit_hash = constraints.circuit_hash(&signals);
+        let circuit = CompiledCircuit {
+            main: None,
+            processed_files: vec![(source.to_str().unwrap().to_string(), hash(b"// v1"))],
+            skipped_includes: vec![cache.path.join("lib.za").to_str().unwrap().to_string()],
+            asts: Vec::new(),
+            signals,
+            constraints,
+            removed_signals: vec![],
+            substitutions: Substitutions::default(),
+            report,
+            circuit_hash,
+        };
+        cache.store_circuit("key", &circuit)?;
+
+        let cached = cache.load_circuit("key").unwrap();
+        assert_eq!(2, cached.signals.len());
+        assert_eq!(
+            "main.a",
+            cached.signals.get_by_id(a).unwrap().full_name.to_string()
+        );
+        assert_eq!(1, cached.constraints.len());
+        assert_eq!(Level::O2, cached.report.level);
+        assert_eq!(circuit.circuit_hash, cached.circuit_hash);
+
+        // a file that would be included instead of another one
+        fs::write(cache.path.join("lib.za"), "// lib")?;
+        assert!(cache.load_circuit("key").is_none());
+        fs::remove_file(cache.path.join("lib.za"))?;
+        assert!(cache.load_circuit("key").is_some());
+
+        fs::write(&source, "// v2")?;
+        assert!(cache.load_circuit("key").is_none());
+        Ok(())
+    }
+}
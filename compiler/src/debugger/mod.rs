@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use za_parser::ast::StatementP;
+
+use crate::algebra::{Value, FS};
+use crate::evaluator::{Error, Evaluator, Mode, Result, Scope, ScopeValue};
+use crate::project::Project;
+use crate::types::{Constraints, Signals};
+
+const HELP: &str = "\
+commands:
+  s, step            evaluate until the next statement
+  n, next            evaluate until the next statement of this component or function
+  c, continue        evaluate until the next breakpoint
+  p, print <expr>    print the value of an expression
+  v, vars            print the variables in scope
+  signals [prefix]   print the signals of the component, or the ones with the prefix
+  bt, backtrace      print the components and functions being evaluated
+  l, list            print the source around the statement
+  b, break <bp>      add a breakpoint, as file:line or template name
+  d, delete <n>      delete a breakpoint
+  breakpoints        list the breakpoints
+  q, quit            stop the witness generation";
+
+/// Where the debugger stops
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breakpoint {
+    // statements starting in the line, 1-based
+    Line(String, usize),
+    // first statement of a template or function
+    Template(String),
+}
+
+impl FromStr for Breakpoint {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parts = s.rsplitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some(line), Some(file)) => match line.parse::<usize>() {
+                Ok(line) if line > 0 && !file.is_empty() => {
+                    Ok(Breakpoint::Line(file.to_string(), line))
+                }
+                _ => Err(format!("invalid breakpoint '{}', use file:line", s)),
+            },
+            _ if !s.is_empty() => Ok(Breakpoint::Template(s.to_string())),
+            _ => Err("empty breakpoint".to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::Line(file, line) => write!(f, "{}:{}", file, line),
+            Breakpoint::Template(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Run {
+    Step,
+    // until a statement with at most this number of frames
+    Next(usize),
+    Continue,
+}
+
+/// Interactive debugger of the witness generation, it stops before evaluating
+///   the statements in the breakpoints and reads commands from its input
+pub struct Debugger {
+    pub breakpoints: Vec<Breakpoint>,
+    // set when the user quits, the evaluation is aborted
+    pub quit: bool,
+
+    run: Run,
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+
+    // (file, line) of the last evaluated statement and number of frames
+    last_line: Option<(String, usize)>,
+    last_depth: usize,
+
+    // contents of the source files, to locate the statements
+    sources: HashMap<String, Option<String>>,
+}
+
+impl Debugger {
+    pub fn new(input: Box<dyn BufRead>, output: Box<dyn Write>) -> Self {
+        Debugger {
+            breakpoints: Vec::new(),
+            quit: false,
+            run: Run::Continue,
+            input,
+            output,
+            last_line: None,
+            last_depth: 0,
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Reads the commands from stdin and prints to stdout
+    pub fn stdio() -> Self {
+        Debugger::new(
+            Box::new(io::BufReader::new(io::stdin())),
+            Box::new(io::stdout()),
+        )
+    }
+
+    pub fn with_breakpoint(mut self, breakpoint: Breakpoint) -> Self {
+        self.breakpoints.push(breakpoint);
+        self
+    }
+
+    /// Stops in the first evaluated statement
+    pub fn with_step(mut self) -> Self {
+        self.run = Run::Step;
+        self
+    }
+
+    /// Called by the evaluator before evaluating each statement
+    pub fn on_statement(
+        &mut self,
+        eval: &mut Evaluator,
+        scope: &Scope,
+        stmt: &StatementP,
+    ) -> Result<()> {
+        if self.quit {
+            return Err(Error::Unexpected("debugger quit".to_string()));
+        }
+        // blocks only group the statements to stop in
+        if let StatementP::Block { .. } = stmt {
+            return Ok(());
+        }
+
+        let file = eval.current_file.clone();
+        let (line, column) = self.line_column(&file, stmt.meta().start);
+        let depth = eval.frames.len();
+        let current = Some((file.clone(), line));
+
+        let stop = match self.run {
+            Run::Step => true,
+            Run::Next(max_depth) => depth <= max_depth,
+            Run::Continue => false,
+        } || self.breakpoints.iter().any(|bp| match bp {
+            Breakpoint::Line(bp_file, bp_line) => {
+                *bp_line == line && Path::new(&file).ends_with(bp_file) && self.last_line != current
+            }
+            Breakpoint::Template(name) => {
+                depth > self.last_depth && eval.frames.last().map(|f| &f.name) == Some(name)
+            }
+        });
+        self.last_line = current;
+        self.last_depth = depth;
+
+        if stop {
+            self.write(&format!(
+                "{}:{}:{} {}",
+                file,
+                line,
+                column,
+                self.frame(eval)
+            ));
+            self.write_source(&file, line, 0);
+            self.repl(eval, scope)?;
+        }
+        Ok(())
+    }
+
+    // reads and runs commands until the evaluation continues
+    fn repl(&mut self, eval: &mut Evaluator, scope: &Scope) -> Result<()> {
+        loop {
+            write!(self.output, "(za) ").ok();
+            self.output.flush().ok();
+            let mut line = String::new();
+            if self.input.read_line(&mut line).unwrap_or(0) == 0 {
+                // end of input
+                self.quit = true;
+            }
+            let line = line.trim();
+            let (command, arg) = match line.find(' ') {
+                Some(n) => (&line[..n], line[n..].trim()),
+                None => (line, ""),
+            };
+            match command {
+                _ if self.quit => {}
+                "s" | "step" => self.run = Run::Step,
+                "n" | "next" => self.run = Run::Next(eval.frames.len()),
+                "c" | "continue" => self.run = Run::Continue,
+                "q" | "quit" => self.quit = true,
+                "p" | "print" => match eval.inspect(scope, arg) {
+                    Ok(value) => self.write(&value),
+                    Err(err) => self.write(&format!("error: {}", err)),
+                },
+                "v" | "vars" => self.write_vars(eval, scope),
+                "signals" => self.write_signals(eval, arg),
+                "bt" | "backtrace" => self.write_backtrace(eval),
+                "l" | "list" => {
+                    let (file, line) = self.last_line.clone().unwrap_or_default();
+                    self.write_source(&file, line, 5);
+                }
+                "b" | "break" => match arg.parse::<Breakpoint>() {
+                    Ok(bp) => {
+                        self.write(&format!("breakpoint {} at {}", self.breakpoints.len(), bp));
+                        self.breakpoints.push(bp);
+                    }
+                    Err(err) => self.write(&err),
+                },
+                "d" | "delete" => match arg.parse::<usize>() {
+                    Ok(n) if n < self.breakpoints.len() => {
+                        self.breakpoints.remove(n);
+                    }
+                    _ => self.write(&format!("no breakpoint {}", arg)),
+                },
+                "breakpoints" => {
+                    for (n, bp) in self.breakpoints.iter().enumerate() {
+                        writeln!(self.output, "{}: {}", n, bp).ok();
+                    }
+                }
+                "" => continue,
+                "h" | "help" => self.write(HELP),
+                _ => self.write(&format!("unknown command '{}', type help", command)),
+            }
+            match command {
+                _ if self.quit => return Err(Error::Unexpected("debugger quit".to_string())),
+                "s" | "step" | "n" | "next" | "c" | "continue" => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        writeln!(self.output, "{}", text).ok();
+    }
+
+    // the innermost component or function
+    fn frame(&self, eval: &Evaluator) -> String {
+        match eval.frames.last() {
+            Some(frame) => format!("in {}", frame),
+            None => "at the root".to_string(),
+        }
+    }
+
+    fn write_vars(&mut self, eval: &Evaluator, scope: &Scope) {
+        for (name, value) in scope.visible_vars() {
+            let value = match value {
+                ScopeValue::UndefVar | ScopeValue::UndefComponent => "undefined".to_string(),
+                ScopeValue::Bool(value) => value.to_string(),
+                ScopeValue::Algebra(value) => eval.signals.format(&value),
+                ScopeValue::List(list) => list.to_string(),
+                ScopeValue::Component { template, .. } => format!("component {}", template),
+                ScopeValue::Function { .. } | ScopeValue::Template { .. } => continue,
+            };
+            self.write(&format!("{} = {}", name, value));
+        }
+    }
+
+    fn write_signals(&mut self, eval: &Evaluator, prefix: &str) {
+        let prefix = if prefix.is_empty() {
+            format!("{}.", eval.current_component)
+        } else {
+            prefix.to_string()
+        };
+        for id in 1..eval.signals.len() {
+            let signal = eval.signals.get_by_id(id).unwrap();
+            if signal.full_name.0.starts_with(&prefix) {
+                let value = signal
+                    .value
+                    .as_ref()
+                    .map_or("?".to_string(), |value| eval.signals.format(value));
+                self.write(&format!("{} = {}", signal.full_name.0, value));
+            }
+        }
+    }
+
+    fn write_backtrace(&mut self, eval: &Evaluator) {
+        for (n, frame) in eval.frames.iter().rev().enumerate() {
+            let site = &frame.call_site;
+            let (line, column) = self.line_column(&site.file, site.meta.start);
+            let text = format!("#{} {} at {}:{}:{}", n, frame, site.file, line, column);
+            self.write(&text);
+        }
+    }
+
+    // prints the line and the ones around it, marking the line
+    fn write_source(&mut self, file: &str, line: usize, around: usize) {
+        let source = match self.source(file) {
+            Some(source) => source.to_string(),
+            None => return,
+        };
+        let first = line.saturating_sub(around).max(1);
+        for (n, text) in source
+            .lines()
+            .enumerate()
+            .skip(first - 1)
+            .take(2 * around + 1)
+        {
+            let marker = if n + 1 == line { ">" } else { " " };
+            self.write(&format!("{}{:>5} | {}", marker, n + 1, text));
+        }
+    }
+
+    fn source(&mut self, file: &str) -> Option<&String> {
+        self.sources
+            .entry(file.to_string())
+            .or_insert_with(|| fs::read_to_string(file).ok())
+            .as_ref()
+    }
+
+    // 1-based line and column of a position, 0 if the file cannot be read
+    fn line_column(&mut self, file: &str, pos: usize) -> (usize, usize) {
+        match self.source(file).and_then(|source| source.get(..pos)) {
+            Some(before) => {
+                let line_start = before.rfind('\n').map_or(0, |n| n + 1);
+                (
+                    before.matches('\n').count() + 1,
+                    before[line_start..].chars().count() + 1,
+                )
+            }
+            None => (0, 0),
+        }
+    }
+}
+
+/// Generates the witness of the project main component with the inputs, or of
+///   the `#[test]` template, under the control of the debugger
+pub fn debug_project(
+    project: &Project,
+    debugger: Debugger,
+    test: Option<&str>,
+    inputs: Vec<(String, FS)>,
+) -> Result<()> {
+    let mut eval = Evaluator::new(Mode::GenWitness, Signals::default(), Constraints::default());
+    eval.debugger = Some(debugger);
+
+    let res = match test {
+        Some(test) => {
+            let mut scan =
+                Evaluator::new(Mode::Collect, Signals::default(), Constraints::default());
+            match project.eval_file(&mut scan) {
+                Ok(scope) => eval.eval_template(&mut scope.clone(), test),
+                Err(err) => {
+                    project.report_error(&scan, &err);
+                    return Err(err);
+                }
+            }
+        }
+        None => {
+            for (signal, value) in inputs {
+                eval.set_deferred_value(signal, Value::from(value));
+            }
+            project.eval_file(&mut eval).map(|_| ())
+        }
+    };
+
+    match res {
+        Err(_) if matches!(&eval.debugger, Some(debugger) if debugger.quit) => Ok(()),
+        Err(err) => {
+            project.report_error(&eval, &err);
+            Err(err)
+        }
+        Ok(()) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_breakpoint_parse() {
+        assert_eq!(
+            Ok(Breakpoint::Line("circuit.za".to_string(), 3)),
+            "circuit.za:3".parse()
+        );
+        assert_eq!(
+            Ok(Breakpoint::Template("Num2Bits".to_string())),
+            "Num2Bits".parse()
+        );
+        assert!("circuit.za:x".parse::<Breakpoint>().is_err());
+    }
+
+    #[test]
+    fn test_debugger() {
+        let root = std::env::temp_dir().join("za-debugger-test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let circuit = "\
+template Square() {
+    signal input x;
+    signal output y;
+    y <== x * x;
+}
+template T() {
+    signal input a;
+    signal output b;
+    var k = 2;
+    component sq = Square();
+    sq.x <== a + k;
+    b <== sq.y;
+}
+component main = T();
+";
+        fs::write(root.join("circuit.za"), circuit).unwrap();
+        let project = Project::from_circuit(&root.to_string_lossy(), "circuit.za");
+
+        let commands = "\
+print k
+print a * 3
+vars
+continue
+bt
+signals
+next
+print y
+break nothing:
+quit
+";
+        let output = Output::default();
+        let debugger = Debugger::new(
+            Box::new(io::Cursor::new(commands.as_bytes().to_vec())),
+            Box::new(output.clone()),
+        )
+        .with_breakpoint(Breakpoint::Line("circuit.za".to_string(), 11))
+        .with_breakpoint(Breakpoint::Template("Square".to_string()));
+        let inputs = vec![("main.a".to_string(), FS::from(3))];
+        debug_project(&project, debugger, None, inputs).unwrap();
+
+        let output = String::from_utf8(output.0.borrow().clone()).unwrap();
+        let file = root.join("circuit.za").to_string_lossy().to_string();
+        let lines = output
+            .lines()
+            .map(|line| line.trim_start_matches("(za) "))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                format!("{}:11:5 in component main = T()", file),
+                ">   11 |     sq.x <== a + k;".to_string(),
+                "2".to_string(),
+                "9".to_string(),
+                "k = 2".to_string(),
+                "sq = component Square".to_string(),
+                format!("{}:2:5 in component main.sq = Square()", file),
+                ">    2 |     signal input x;".to_string(),
+                format!("#0 component main.sq = Square() at {}:10:5", file),
+                format!("#1 component main = T() at {}:14:1", file),
+                "main.sq.y = ?".to_string(),
+                "main.sq.x = 5".to_string(),
+                format!("{}:3:5 in component main.sq = Square()", file),
+                ">    3 |     signal output y;".to_string(),
+                "1main.sq.y".to_string(),
+                "invalid breakpoint 'nothing:', use file:line".to_string(),
+                "".to_string(),
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn test_inspect_error() {
+        let mut eval = Evaluator::new(Mode::GenWitness, Signals::default(), Constraints::default());
+        let mut scope = Scope::new(true, None, "root".to_string());
+        eval.eval_inline(&mut scope, "function f(x) { return x / 0; }")
+            .unwrap();
+
+        assert!(eval.inspect(&scope, "f(1)").is_err());
+        assert!(eval.frames.is_empty());
+        assert_eq!(None, eval.current_function);
+        assert!(eval.last_error.is_none());
+        assert_eq!("3", eval.inspect(&scope, "1 + 2").unwrap());
+    }
+}
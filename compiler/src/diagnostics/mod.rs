@@ -0,0 +1,273 @@
+mod report;
+
+use std::fs;
+use std::str::FromStr;
+
+use crate::evaluator::{Error, Evaluator, Location};
+
+pub use self::report::emit;
+
+/// How the diagnostics are printed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageFormat {
+    // source snippets with the labels, for the terminal
+    Human,
+    // one JSON object per line, for editors and CI
+    Json,
+}
+
+impl Default for MessageFormat {
+    fn default() -> Self {
+        MessageFormat::Human
+    }
+}
+
+impl FromStr for MessageFormat {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(MessageFormat::Human),
+            "json" => Ok(MessageFormat::Json),
+            _ => Err(format!("unknown message format '{}', use human or json", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub location: Location,
+    pub message: String,
+}
+
+/// An error or warning with its stable code and source locations
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: String,
+    pub message: String,
+    // where the diagnostic is raised, if it is located in a source file
+    pub primary: Option<Label>,
+    // related locations, e.g. the declaration of a misused signal
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &str, message: String) -> Self {
+        Diagnostic {
+            severity,
+            code: code.to_string(),
+            message,
+            primary: None,
+            secondary: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_primary(mut self, location: Location, message: &str) -> Self {
+        self.primary = Some(Label {
+            location,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    /// The diagnostic of an evaluation error, located by the evaluator last
+    ///   error context
+    pub fn from_error(eval: &Evaluator, err: &Error) -> Self {
+        let mut diagnostic = Diagnostic::new(Severity::Error, err.code(), err.to_string());
+        if let Some(ctx) = &eval.last_error {
+            if !ctx.file.is_empty() {
+                let location = Location {
+                    file: ctx.file.clone(),
+                    meta: ctx.meta.clone(),
+                };
+                diagnostic = diagnostic.with_primary(location, "");
+            }
+            diagnostic.secondary = ctx
+                .labels
+                .iter()
+                .map(|(location, message)| Label {
+                    location: location.clone(),
+                    message: message.clone(),
+                })
+                .collect();
+            // the backtrace, innermost frame first
+            for frame in ctx.backtrace.iter().rev() {
+                let note = format!("in {}, at {}", frame, position(&frame.call_site));
+                diagnostic.notes.push(note);
+            }
+            if ctx.backtrace.is_empty() && !ctx.component.is_empty() {
+                diagnostic
+                    .notes
+                    .push(format!("in component {}", ctx.component));
+            }
+        }
+        diagnostic
+    }
+
+    /// The diagnostic of an error collected with its location
+    pub fn from_collected(err: &Error, location: &Location) -> Self {
+        let diagnostic = Diagnostic::new(Severity::Error, err.code(), err.to_string());
+        if location.file.is_empty() {
+            diagnostic
+        } else {
+            diagnostic.with_primary(location.clone(), "")
+        }
+    }
+
+    /// Single line JSON, with the labels as byte ranges and 1-based line and
+    ///   column, primary label first
+    pub fn to_json(&self) -> String {
+        let labels = self
+            .primary
+            .iter()
+            .map(|label| (label, true))
+            .chain(self.secondary.iter().map(|label| (label, false)))
+            .map(|(label, primary)| {
+                let meta = &label.location.meta;
+                let position = fs::read_to_string(&label.location.file)
+                    .ok()
+                    .and_then(|code| line_column(&code, meta.start));
+                JsonLabel {
+                    file: &label.location.file,
+                    start: meta.start,
+                    end: meta.end,
+                    line: position.map(|(line, _)| line),
+                    column: position.map(|(_, column)| column),
+                    message: &label.message,
+                    primary,
+                }
+            })
+            .collect();
+        let json = JsonDiagnostic {
+            severity: self.severity,
+            code: &self.code,
+            message: &self.message,
+            labels,
+            notes: &self.notes,
+        };
+        serde_json::to_string(&json).unwrap()
+    }
+}
+
+#[derive(Serialize)]
+struct JsonLabel<'a> {
+    file: &'a str,
+    start: usize,
+    end: usize,
+    line: Option<usize>,
+    column: Option<usize>,
+    message: &'a str,
+    primary: bool,
+}
+
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    severity: Severity,
+    code: &'a str,
+    message: &'a str,
+    labels: Vec<JsonLabel<'a>>,
+    notes: &'a [String],
+}
+
+// `file:line:column` of a location, or `file:offset` if the file cannot be read
+fn position(location: &Location) -> String {
+    match fs::read_to_string(&location.file)
+        .ok()
+        .and_then(|code| line_column(&code, location.meta.start))
+    {
+        Some((line, column)) => format!("{}:{}:{}", location.file, line, column),
+        None => format!("{}:{}", location.file, location.meta.start),
+    }
+}
+
+// 1-based line and column, in characters, of a byte position
+pub(crate) fn line_column(code: &str, pos: usize) -> Option<(usize, usize)> {
+    let before = code.get(..pos)?;
+    let line_start = before.rfind('\n').map_or(0, |n| n + 1);
+    Some((
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::{Mode, Scope};
+    use crate::types::{Constraints, Signals};
+
+    #[test]
+    fn test_diagnostic() {
+        assert_eq!(Some((1, 1)), line_column("ab\ncd", 0));
+        assert_eq!(Some((2, 2)), line_column("ab\ncd", 4));
+
+        let mut eval = Evaluator::new(Mode::GenWitness, Signals::default(), Constraints::default());
+        let mut scope = Scope::new(true, None, "root".to_string());
+        let err = eval
+            .eval_inline(
+                &mut scope,
+                "template T() { signal a; signal a; } component main = T();",
+            )
+            .unwrap_err();
+
+        let diagnostic = Diagnostic::from_error(&eval, &err);
+        assert_eq!("E0002", diagnostic.code);
+        assert_eq!("already exists: signal main.a", diagnostic.message);
+        assert_eq!(vec!["in component main = T(), at :37"], diagnostic.notes);
+        assert_eq!(1, diagnostic.secondary.len());
+        assert_eq!(15, diagnostic.secondary[0].location.meta.start);
+        assert_eq!(
+            r#"{"severity":"error","code":"E0002","message":"already exists: signal main.a","labels":[{"file":"","start":15,"end":23,"line":null,"column":null,"message":"first declared here","primary":false}],"notes":["in component main = T(), at :37"]}"#,
+            diagnostic.to_json()
+        );
+    }
+
+    #[test]
+    fn test_backtrace() {
+        let mut eval = Evaluator::new(Mode::GenWitness, Signals::default(), Constraints::default());
+        let mut scope = Scope::new(true, None, "root".to_string());
+        let code = "
+            function f(x) { return x / 0; }
+            template B(n) { signal input a; var v = f(n); }
+            template A() { component b = B(3); b.a <== 1; }
+            component main = A();";
+        let err = eval.eval_inline(&mut scope, code).unwrap_err();
+
+        let at = |pattern: &str| format!(":{}", code.find(pattern).unwrap());
+        assert_eq!(
+            vec![
+                format!("in function f(3), at {}", at("f(n)")),
+                format!("in component main.b = B(3), at {}", at("component b")),
+                format!("in component main = A(), at {}", at("component main")),
+            ],
+            Diagnostic::from_error(&eval, &err).notes
+        );
+        // the frames are left when the error is returned
+        assert!(eval.frames.is_empty());
+        assert_eq!(None, eval.current_function);
+    }
+
+    #[test]
+    fn test_main_public_not_found() {
+        let mut eval = Evaluator::new(Mode::GenWitness, Signals::default(), Constraints::default());
+        let mut scope = Scope::new(true, None, "root".to_string());
+        let code = "template T() { signal input a; } component main {public [b]} = T();";
+        let err = eval.eval_inline(&mut scope, code).unwrap_err();
+
+        let diagnostic = Diagnostic::from_error(&eval, &err);
+        assert_eq!("not found: input signal b in main", diagnostic.message);
+        let ctx = eval.last_error.as_ref().unwrap();
+        assert_eq!(code.find("component main").unwrap(), ctx.meta.start);
+        assert_eq!(1, diagnostic.secondary.len());
+        assert_eq!("template T declared here", diagnostic.secondary[0].message);
+    }
+}
@@ -0,0 +1,108 @@
+use super::{Diagnostic, MessageFormat};
+
+/// Prints the diagnostics, JSON ones to stdout and human ones to stderr
+pub fn emit(diagnostics: &[Diagnostic], format: MessageFormat) {
+    for diagnostic in diagnostics {
+        match format {
+            MessageFormat::Json => println!("{}", diagnostic.to_json()),
+            MessageFormat::Human => emit_human(diagnostic),
+        }
+    }
+}
+
+#[cfg(target_os = "android")]
+fn emit_human(diagnostic: &Diagnostic) {
+    emit_plain(diagnostic)
+}
+
+#[cfg(not(target_os = "android"))]
+fn emit_human(diagnostic: &Diagnostic) {
+    use codespan::{ByteOffset, ByteSpan, CodeMap, Span};
+    use codespan_reporting::termcolor::{ColorChoice, StandardStream};
+    use codespan_reporting::{emit, Label, Severity};
+
+    let mut code_map: CodeMap<String> = CodeMap::new();
+    let mut files = Vec::new();
+    let mut span = |location: &crate::evaluator::Location| -> Option<ByteSpan> {
+        let start = match files.iter().find(|(file, _)| file == &location.file) {
+            Some((_, start)) => *start,
+            None => {
+                let start = code_map
+                    .add_filemap_from_disk(&location.file)
+                    .ok()?
+                    .span()
+                    .start();
+                files.push((location.file.clone(), start));
+                start
+            }
+        };
+        let meta = &location.meta;
+        Some(Span::from_offset(
+            start + ByteOffset(meta.start as i64),
+            ByteOffset((meta.end - meta.start) as i64),
+        ))
+    };
+
+    let primary = match &diagnostic.primary {
+        Some(label) => match span(&label.location) {
+            Some(span) => Some(Label::new_primary(span).with_message(label.message.clone())),
+            None => return emit_plain(diagnostic),
+        },
+        None => None,
+    };
+    let secondary = diagnostic
+        .secondary
+        .iter()
+        .filter_map(|label| {
+            span(&label.location)
+                .map(|span| Label::new_secondary(span).with_message(label.message.clone()))
+        })
+        .collect::<Vec<_>>();
+
+    let severity = match diagnostic.severity {
+        super::Severity::Error => Severity::Error,
+        super::Severity::Warning => Severity::Warning,
+    };
+    let mut diagnostics =
+        vec![
+            codespan_reporting::Diagnostic::new(severity, diagnostic.message.clone())
+                .with_code(diagnostic.code.clone())
+                .with_labels(primary.into_iter().chain(secondary)),
+        ];
+    for note in &diagnostic.notes {
+        diagnostics.push(codespan_reporting::Diagnostic::new_note(note.clone()));
+    }
+
+    let writer = StandardStream::stderr(ColorChoice::Auto);
+    for diagnostic in diagnostics {
+        emit(&mut writer.lock(), &code_map, &diagnostic).unwrap();
+    }
+}
+
+// plain text, when the sources cannot be shown
+fn emit_plain(diagnostic: &Diagnostic) {
+    let severity = match diagnostic.severity {
+        super::Severity::Error => "error",
+        super::Severity::Warning => "warning",
+    };
+    match &diagnostic.primary {
+        Some(label) => eprintln!(
+            "{}[{}]: {} at {}:{}",
+            severity,
+            diagnostic.code,
+            diagnostic.message,
+            label.location.file,
+            label.location.meta.start
+        ),
+        None => eprintln!("{}[{}]: {}", severity, diagnostic.code, diagnostic.message),
+    }
+    for label in &diagnostic.secondary {
+        eprintln!(
+            "  {} at {}:{}",
+            label.message, label.location.file, label.location.meta.start
+        );
+    }
+    for note in &diagnostic.notes {
+        eprintln!("  = note: {}", note);
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use crate::algebra::{SignalId, FS, LC, QEQ};
+use crate::cache::CompiledCircuit;
+use crate::types::{Constraints, Signals};
+
+/// The constraints of a circuit version with the signals they refer to
+pub struct ConstraintSystem {
+    pub signals: Signals,
+    pub constraints: Constraints,
+    // signals removed by the optimizer
+    pub removed_signals: Vec<SignalId>,
+}
+
+impl From<CompiledCircuit> for ConstraintSystem {
+    fn from(circuit: CompiledCircuit) -> Self {
+        ConstraintSystem {
+            signals: circuit.signals,
+            constraints: circuit.constraints,
+            removed_signals: circuit.removed_signals,
+        }
+    }
+}
+
+impl ConstraintSystem {
+    // signal full names with their type, and if they are removed
+    fn signal_kinds(&self) -> BTreeMap<String, String> {
+        let removed: HashSet<_> = self.removed_signals.iter().collect();
+        (1..self.signals.len())
+            .map(|id| {
+                let signal = self.signals.get_by_id(id).unwrap();
+                let mut kind = format!("{:?}", signal.xtype);
+                if removed.contains(&id) {
+                    kind.push_str(", removed");
+                }
+                (signal.full_name.to_string(), kind)
+            })
+            .collect()
+    }
+
+    // normalized constraints, with the times that each one appears
+    fn normalized_constraints(&self) -> BTreeMap<NamedQEQ, usize> {
+        // the signals renumbered in full name order, so the canonical form
+        //   of the constraints does not depend on the signal ids
+        let mut names = (0..self.signals.len())
+            .map(|id| {
+                (
+                    self.signals.get_by_id(id).unwrap().full_name.to_string(),
+                    id,
+                )
+            })
+            .collect::<Vec<_>>();
+        names.sort();
+        let mut ranks = vec![0; names.len()];
+        for (rank, (_, id)) in names.iter().enumerate() {
+            ranks[*id] = rank;
+        }
+        let names = names.into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+        let ranked = |lc: &LC| {
+            lc.iter()
+                .map(|(s, v)| (ranks[*s], v.clone()))
+                .collect::<LC>()
+        };
+
+        let mut constraints = BTreeMap::new();
+        for i in 0..self.constraints.len() {
+            let qeq = self.constraints.get(i);
+            let qeq = QEQ {
+                a: ranked(&qeq.a),
+                b: ranked(&qeq.b),
+                c: ranked(&qeq.c),
+            };
+            *constraints.entry(NamedQEQ::new(qeq, &names)).or_default() += 1;
+        }
+        constraints
+    }
+}
+
+// a linear combination with the signals by full name, sorted
+type NamedLC = Vec<(String, FS)>;
+
+/// A constraint that does not depend on the signal ids, the constraints are
+///   compared by their canonical form with the signals numbered in name
+///   order, and printed with the terms sorted by signal name
+#[derive(Clone)]
+struct NamedQEQ {
+    canonical: QEQ,
+    repr: String,
+    // sorted signal names, to match the constraints that changed
+    signals: Vec<String>,
+}
+
+impl NamedQEQ {
+    // `qeq` has the signals numbered by their position in `names`
+    fn new(qeq: QEQ, names: &[String]) -> Self {
+        let named = |lc: &LC| {
+            lc.iter()
+                .map(|(s, v)| (names[*s].clone(), v.clone()))
+                .collect::<NamedLC>()
+        };
+        let mut signals = qeq
+            .a
+            .iter()
+            .chain(&qeq.b)
+            .chain(&qeq.c)
+            .map(|(s, _)| names[*s].clone())
+            .collect::<Vec<_>>();
+        signals.sort();
+        signals.dedup();
+        let (a, b, c) = (named(&qeq.a), named(&qeq.b), named(&qeq.c));
+        let (a, b, c) = (format_lc(&a), format_lc(&b), format_lc(&c));
+        let (a, b) = if a <= b { (a, b) } else { (b, a) };
+        NamedQEQ {
+            canonical: qeq.canonical(),
+            repr: format!("[{}]*[{}]+[{}]", a, b, c),
+            signals,
+        }
+    }
+}
+
+impl PartialEq for NamedQEQ {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical == other.canonical
+    }
+}
+
+impl Eq for NamedQEQ {}
+
+impl PartialOrd for NamedQEQ {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NamedQEQ {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.canonical.cmp(&other.canonical)
+    }
+}
+
+fn format_lc(lc: &NamedLC) -> String {
+    if lc.is_empty() {
+        return " ".to_string();
+    }
+    lc.iter()
+        .enumerate()
+        .map(|(i, (name, v))| format!("{}{}", v.format(i > 0), name))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SignalChange {
+    pub name: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConstraintChange {
+    pub old: String,
+    pub new: String,
+}
+
+/// Differences between the constraint systems of two circuit versions
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Diff {
+    pub added_signals: Vec<String>,
+    pub removed_signals: Vec<String>,
+    // signals whose type changed, or that are removed only in one version
+    pub changed_signals: Vec<SignalChange>,
+    pub added_constraints: Vec<String>,
+    pub removed_constraints: Vec<String>,
+    // an added and a removed constraint with the same signals
+    pub changed_constraints: Vec<ConstraintChange>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self == &Diff::default()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "signals: {} added, {} removed, {} changed",
+            self.added_signals.len(),
+            self.removed_signals.len(),
+            self.changed_signals.len()
+        )?;
+        for name in &self.added_signals {
+            writeln!(f, "+ {}", name)?;
+        }
+        for name in &self.removed_signals {
+            writeln!(f, "- {}", name)?;
+        }
+        for change in &self.changed_signals {
+            writeln!(f, "~ {}: {} -> {}", change.name, change.old, change.new)?;
+        }
+        writeln!(
+            f,
+            "constraints: {} added, {} removed, {} changed",
+            self.added_constraints.len(),
+            self.removed_constraints.len(),
+            self.changed_constraints.len()
+        )?;
+        for qeq in &self.added_constraints {
+            writeln!(f, "+ {}", qeq)?;
+        }
+        for qeq in &self.removed_constraints {
+            writeln!(f, "- {}", qeq)?;
+        }
+        for change in &self.changed_constraints {
+            writeln!(f, "~ {}\n  {}", change.old, change.new)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the signals by full name and the constraints in a form that
+///   does not depend on the signal ids or on the order of the terms
+pub fn diff(old: &ConstraintSystem, new: &ConstraintSystem) -> Diff {
+    let mut diff = Diff::default();
+
+    let (old_signals, new_signals) = (old.signal_kinds(), new.signal_kinds());
+    for (name, kind) in &old_signals {
+        match new_signals.get(name) {
+            None => diff.removed_signals.push(name.clone()),
+            Some(new_kind) if new_kind != kind => diff.changed_signals.push(SignalChange {
+                name: name.clone(),
+                old: kind.clone(),
+                new: new_kind.clone(),
+            }),
+            _ => {}
+        }
+    }
+    for name in new_signals.keys() {
+        if !old_signals.contains_key(name) {
+            diff.added_signals.push(name.clone());
+        }
+    }
+
+    let (old_constraints, new_constraints) =
+        (old.normalized_constraints(), new.normalized_constraints());
+    let surplus = |a: &BTreeMap<NamedQEQ, usize>, b: &BTreeMap<NamedQEQ, usize>| {
+        a.iter()
+            .flat_map(|(qeq, n)| {
+                let times = n.saturating_sub(*b.get(qeq).unwrap_or(&0));
+                (0..times).map(move |_| qeq.clone())
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut removed_by_signals = HashMap::<Vec<String>, Vec<NamedQEQ>>::new();
+    for qeq in surplus(&old_constraints, &new_constraints)
+        .into_iter()
+        .rev()
+    {
+        removed_by_signals
+            .entry(qeq.signals.clone())
+            .or_default()
+            .push(qeq);
+    }
+    for qeq in surplus(&new_constraints, &old_constraints) {
+        match removed_by_signals.get_mut(&qeq.signals).and_then(Vec::pop) {
+            Some(old) => diff.changed_constraints.push(ConstraintChange {
+                old: old.repr,
+                new: qeq.repr,
+            }),
+            None => diff.added_constraints.push(qeq.repr),
+        }
+    }
+    let mut removed = removed_by_signals
+        .into_values()
+        .flatten()
+        .map(|qeq| qeq.repr)
+        .collect::<Vec<_>>();
+    removed.sort();
+    diff.removed_constraints = removed;
+
+    diff
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::{Evaluator, Mode, Scope};
+
+    fn constraint_system(code: &str) -> ConstraintSystem {
+        let mut eval = Evaluator::new(
+            Mode::GenConstraints,
+            Signals::default(),
+            Constraints::default(),
+        );
+        let mut scope = Scope::new(true, None, "root".to_string());
+        eval.eval_inline(&mut scope, code).unwrap();
+        ConstraintSystem {
+            signals: eval.signals,
+            constraints: eval.constraints,
+            removed_signals: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = constraint_system(
+            "
+            template T() {
+                signal input a;
+                signal input b;
+                signal output c;
+                signal output d;
+                c <== a * b;
+                d <== 2 * a + b;
+            }
+            component main = T();",
+        );
+
+        // the signals are declared in other order and the expressions rewritten
+        let refactored = constraint_system(
+            "
+            template T() {
+                signal output d;
+                signal output c;
+                signal input b;
+                signal input a;
+                b * a === c;
+                b + a + a ==> d;
+            }
+            component main = T();",
+        );
+        assert!(diff(&old, &refactored).is_empty());
+
+        let new = constraint_system(
+            "
+            template T() {
+                signal input a;
+                signal private input b;
+                signal output c;
+                signal output d;
+                signal output e;
+                c <== a * b;
+                d <== 3 * a + b;
+                e <== d * d;
+            }
+            component main = T();",
+        );
+        let diff = diff(&old, &new);
+        assert_eq!(vec!["main.e"], diff.added_signals);
+        assert!(diff.removed_signals.is_empty());
+        assert_eq!(
+            vec![SignalChange {
+                name: "main.b".to_string(),
+                old: "PublicInput".to_string(),
+                new: "PrivateInput".to_string(),
+            }],
+            diff.changed_signals
+        );
+        assert_eq!(
+            vec!["[-1main.d]*[1main.d]+[1main.e]"],
+            diff.added_constraints
+        );
+        assert!(diff.removed_constraints.is_empty());
+        assert_eq!(
+            vec![ConstraintChange {
+                old: "[ ]*[ ]+[-2main.a-1main.b+1main.d]".to_string(),
+                new: "[ ]*[ ]+[-3main.a-1main.b+1main.d]".to_string(),
+            }],
+            diff.changed_constraints
+        );
+    }
+}
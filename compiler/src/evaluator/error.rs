@@ -1,3 +1,5 @@
+use std::fmt;
+
 use crate::algebra;
 
 #[derive(Debug)]
@@ -15,9 +17,63 @@ pub enum Error {
     CannotGenerateConstrain(String),
     CannotTestConstrain(String),
     CannotCheckConstrain(String),
+    AssertFailed(String),
     CannotConvertToU64(algebra::FS),
     Io(String, String),
     Unexpected(String),
 }
 
+impl Error {
+    /// Stable code of the error kind, shown in the diagnostics
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::NotFound(_) => "E0001",
+            Error::AlreadyExists(_) => "E0002",
+            Error::Parse(_) => "E0003",
+            Error::InvalidParameter(_) => "E0004",
+            Error::InvalidSelector(_) => "E0005",
+            Error::BadFunctionReturn(_) => "E0006",
+            Error::InvalidTag(_) => "E0007",
+            Error::InvalidType(_) => "E0008",
+            Error::NotYetImplemented(_) => "E0009",
+            Error::Algebra(_) => "E0010",
+            Error::CannotGenerateConstrain(_) => "E0011",
+            Error::CannotTestConstrain(_) => "E0012",
+            Error::CannotCheckConstrain(_) => "E0013",
+            Error::AssertFailed(_) => "E0014",
+            Error::CannotConvertToU64(_) => "E0015",
+            Error::Io(_, _) => "E0016",
+            Error::Unexpected(_) => "E0017",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound(msg) => write!(f, "not found: {}", msg),
+            Error::AlreadyExists(msg) => write!(f, "already exists: {}", msg),
+            Error::Parse(msg) => write!(f, "parse error: {}", msg),
+            Error::InvalidParameter(msg) => write!(f, "invalid parameter: {}", msg),
+            Error::InvalidSelector(msg) => write!(f, "invalid selector: {}", msg),
+            Error::BadFunctionReturn(msg) => write!(f, "bad function return: {}", msg),
+            Error::InvalidTag(msg) => write!(f, "invalid tag: {}", msg),
+            Error::InvalidType(msg) => write!(f, "invalid type: {}", msg),
+            Error::NotYetImplemented(msg) => write!(f, "not yet implemented: {}", msg),
+            Error::Algebra(algebra::Error::InvalidOperation(msg))
+            | Error::Algebra(algebra::Error::InvalidFormat(msg)) => write!(f, "{}", msg),
+            Error::Algebra(algebra::Error::Io(err)) => write!(f, "{}", err),
+            Error::CannotGenerateConstrain(msg) => {
+                write!(f, "cannot generate constraint: {}", msg)
+            }
+            Error::CannotTestConstrain(msg) => write!(f, "constraint does not hold: {}", msg),
+            Error::CannotCheckConstrain(msg) => write!(f, "cannot check constraint: {}", msg),
+            Error::AssertFailed(msg) => write!(f, "assertion failed: {}", msg),
+            Error::CannotConvertToU64(fs) => write!(f, "cannot convert {} to u64", fs),
+            Error::Io(path, msg) => write!(f, "cannot read {}: {}", path, msg),
+            Error::Unexpected(msg) => write!(f, "unexpected: {}", msg),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::PathBuf;
@@ -9,16 +10,17 @@ use za_parser::ast::{
     VariableType,
 };
 
-use blake2_rfc::blake2b::Blake2b;
-use hex;
 use itertools::Itertools;
 use num_bigint::BigInt;
 
 use crate::algebra;
 use crate::algebra::{AlgZero, SignalId};
+use crate::cache::{self, Cache};
+use crate::debugger::Debugger;
 use crate::types::{Constraints, Signals};
 
 use super::error::*;
+use super::references::body_element_references;
 use super::scope::*;
 use super::types::{List, ReturnValue};
 
@@ -29,6 +31,64 @@ pub struct ErrorContext {
     pub file: String,
     pub component: String,
     pub function: Option<String>,
+
+    // secondary locations related with the error, with its messages
+    pub labels: Vec<(Location, String)>,
+
+    // components and functions being evaluated, the innermost last
+    pub backtrace: Vec<Frame>,
+}
+
+/// A location in a source file
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub file: String,
+    pub meta: Meta,
+}
+
+/// A component or function being evaluated, with the location where it has
+///   been instantiated or called
+#[derive(Debug, Clone)]
+pub struct Frame {
+    // full name of the component, None for functions
+    pub component: Option<String>,
+    // template or function name, with its argument values
+    pub name: String,
+    pub args: Vec<String>,
+    pub call_site: Location,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.component {
+            Some(component) => write!(
+                f,
+                "component {} = {}({})",
+                component,
+                self.name,
+                self.args.join(", ")
+            ),
+            None => write!(f, "function {}({})", self.name, self.args.join(", ")),
+        }
+    }
+}
+
+/// Signal assigned by a `<--`
+#[derive(Debug, Clone)]
+pub enum Assigned {
+    Signal(SignalId),
+    // full name of the signal array, when the indexes cannot be evaluated
+    //   because the assignment is inside a skipped `#[w]` statement
+    Array(String),
+}
+
+/// Signal sources collected while generating constraints, used by the linter
+#[derive(Debug, Default)]
+pub struct SignalSources {
+    pub assignments: Vec<(Assigned, Location)>,
+
+    // constraints inside `#[w]` statements, that are not generated
+    pub skipped_constraints: Vec<Location>,
 }
 
 #[derive(PartialEq, Debug)]
@@ -58,22 +118,68 @@ pub struct Evaluator {
     pub signals: Signals,
     pub constraints: Constraints,
 
-    // processed includes
-    pub processed_files: Vec<String>,
+    // processed includes, as (path, blake2b hash)
+    pub processed_files: Vec<(String, String)>,
+    // paths searched before the resolved includes, where there is no file,
+    //   a file added in one of them changes how the includes are resolved
+    pub skipped_includes: Vec<String>,
     pub collected_asts: Vec<BodyElementP>,
 
     // last got error
     pub last_error: Option<ErrorContext>,
 
+    // labels for the next registered error
+    error_labels: Vec<(Location, String)>,
+
+    // components and functions being evaluated
+    pub frames: Vec<Frame>,
+
+    // if set, the errors of the body elements are collected and the evaluation
+    //   continues, with the calls to undefined templates or functions
+    pub collected_errors: Option<Vec<(Error, Location)>>,
+
+    // templates and functions called, to check them when collecting errors
+    references: Vec<(String, Location)>,
+
     // evaluation mode
     pub mode: Mode,
 
     // default path
     pub path: PathBuf,
 
+    // additional include search paths and named library roots
+    pub include_paths: Vec<PathBuf>,
+    pub libraries: HashMap<String, PathBuf>,
+
+    // main template invocation, evaluated after the file as `component main = ...;`,
+    //   if there are main parameters it is just the template name
+    pub main: Option<String>,
+    pub main_params: Vec<(String, String)>,
+
+    // inputs declared public with `component main {public [...]}`, in order
+    pub main_public: Option<Vec<String>>,
+
+    // on-disk cache of parsed files
+    pub cache: Option<Cache>,
+
+    // number of instantiated anonymous components, used to name them
+    pub anonymous_components: usize,
+
     // deferred signal values
     pub deferred_signal_values: HashMap<String, algebra::Value>,
 
+    // where each signal has been declared
+    pub signal_declarations: HashMap<SignalId, Location>,
+
+    // template of each expanded component, by component full name
+    pub component_templates: HashMap<String, String>,
+
+    // signal assignments and skipped constraints, only collected if set
+    pub sources: Option<SignalSources>,
+
+    // called before evaluating each statement, if set
+    pub debugger: Option<Debugger>,
+
     // turn on debugging
     pub debug: bool,
 }
@@ -89,10 +195,26 @@ impl Evaluator {
             current_function: None,
             debug_last_constraint: std::time::Instant::now(),
             processed_files: Vec::new(),
+            skipped_includes: Vec::new(),
             collected_asts: Vec::new(),
             last_error: None,
+            error_labels: Vec::new(),
+            frames: Vec::new(),
+            collected_errors: None,
+            references: Vec::new(),
             path: PathBuf::from("."),
+            include_paths: Vec::new(),
+            libraries: HashMap::new(),
+            main: None,
+            main_params: Vec::new(),
+            main_public: None,
+            cache: None,
+            anonymous_components: 0,
             deferred_signal_values: HashMap::new(),
+            signal_declarations: HashMap::new(),
+            component_templates: HashMap::new(),
+            sources: None,
+            debugger: None,
             debug: false,
         }
     }
@@ -105,20 +227,44 @@ impl Evaluator {
                 self.eval_body_elements_p(&Meta::new(0, 0, None), scope, &elements)?;
                 self.collected_asts.append(&mut elements);
             }
-            Err(za_parser::Error::ParseError(err, meta)) => {
-                return self.register_error(&meta, &scope, Err(Error::Parse(err)));
-            }
+            Err(err) => return self.parse_error(scope, err),
         }
         Ok(())
     }
 
+    /// Evaluates an expression typed by the user in a scope and formats its
+    ///   value, the errors are not registered and the paused evaluation is
+    ///   left as it was
+    pub fn inspect(&mut self, scope: &Scope, code: &str) -> Result<String> {
+        let expr = za_parser::parse_expression(code)
+            .map_err(|err| Error::Parse(err.errors().remove(0).0))?;
+        let last_error = self.last_error.take();
+        let frames = self.frames.len();
+        let current_file = self.current_file.clone();
+        let current_function = self.current_function.clone();
+        let res = self.eval_expression_p(scope, &expr);
+        self.last_error = last_error;
+        self.error_labels.clear();
+        self.frames.truncate(frames);
+        self.current_file = current_file;
+        self.current_function = current_function;
+        Ok(match res? {
+            ReturnValue::Algebra(value) => self.signals.format(&value),
+            value => value.to_string(),
+        })
+    }
+
     pub fn eval_template(&mut self, scope: &mut Scope, template_name: &str) -> Result<()> {
         let err_not_found = || Error::NotFound(format!("template {}", template_name));
 
         match &*scope.get(&template_name).ok_or_else(err_not_found)? {
             ScopeValue::Template { stmt, path, .. } => {
                 let mut scope = Scope::new(true, Some(scope), path.to_string());
-                Ok(self.eval_statement_p(&mut scope, &stmt)?)
+                let mut current_file = path.to_string();
+                std::mem::swap(&mut current_file, &mut self.current_file);
+                let res = self.eval_statement_p(&mut scope, stmt);
+                std::mem::swap(&mut current_file, &mut self.current_file);
+                res
             }
             _ => Err(err_not_found()),
         }
@@ -127,10 +273,32 @@ impl Evaluator {
     pub fn eval_file(&mut self, path: &str, filename: &str) -> Result<Scope> {
         self.path = PathBuf::from(path);
         let mut scope = Scope::new(true, None, filename.to_string());
-        self.eval_include(&Meta::new(0, 0, None), &mut scope, filename)?;
+        let res = self.eval_include(&Meta::new(0, 0, None), &mut scope, filename);
+        self.collect_error(res)?;
+        if self.collected_errors.is_some() {
+            self.check_references(&scope);
+        }
+        let res = self.eval_main(&mut scope);
+        self.collect_error(res)?;
         Ok(scope)
     }
 
+    /// Declares `component main` if a main template is set, the main
+    ///   invocation is updated with the parameters used. The parameters
+    ///   without a main template are an error, since they would be ignored
+    pub fn eval_main(&mut self, scope: &mut Scope) -> Result<()> {
+        if let Some(main) = self.main.clone() {
+            let main = self.main_invocation(scope, &main)?;
+            self.eval_inline(scope, &format!("component main = {};", main))?;
+            self.main = Some(main);
+        } else if !self.main_params.is_empty() {
+            return Err(Error::InvalidParameter(
+                "parameters require a main template".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     pub fn eval_asts(&mut self, asts: &[BodyElementP]) -> Result<Scope> {
         let mut scope = Scope::new(true, None, "".to_string());
 
@@ -153,8 +321,11 @@ impl Evaluator {
             }
         }
         for body_element in asts.iter() {
-            if let Declaration { decl, .. } = body_element {
-                self.eval_statement_p(&mut scope, decl)?;
+            match body_element {
+                Declaration { .. } | MainComponent { .. } => {
+                    self.eval_body_element_p(&mut scope, body_element)?
+                }
+                _ => (),
             }
         }
         Ok(scope)
@@ -174,6 +345,8 @@ impl Evaluator {
                 file: self.current_file.clone(),
                 component: self.current_component.clone(),
                 function: self.current_function.clone(),
+                labels: std::mem::take(&mut self.error_labels),
+                backtrace: self.frames.clone(),
             });
         }
         res
@@ -215,11 +388,49 @@ impl Evaluator {
             PrefixOp { meta, op, rhe } => self.eval_prefix_op(meta, scope, *op, rhe),
             InfixOp { meta, lhe, op, rhe } => self.eval_infix_op(meta, scope, lhe, *op, rhe),
             Array { meta, values } => self.eval_array(meta, scope, values),
+            Ternary {
+                meta,
+                cond,
+                if_true,
+                if_false,
+            } => self.eval_ternary(meta, scope, cond, if_true, if_false),
+            StringLiteral { meta, .. } => self.register_error(
+                meta,
+                scope,
+                Err(Error::InvalidType(
+                    "strings are only allowed in log".to_string(),
+                )),
+            ),
+            Tuple { meta, .. } => self.register_error(
+                meta,
+                scope,
+                Err(Error::InvalidType(
+                    "tuples are only allowed in assignments".to_string(),
+                )),
+            ),
+            AnonymousComponent { meta, template, .. } => self.register_error(
+                meta,
+                scope,
+                Err(Error::InvalidType(format!(
+                    "anonymous component {} is only allowed as the value of an assignment",
+                    template
+                ))),
+            ),
         }
     }
 
     fn eval_statement_p(&mut self, scope: &mut Scope, v: &StatementP) -> Result<()> {
         use za_parser::ast::StatementP::*;
+
+        if self.sources.is_some() && self.mode.skip_eval(v.meta()) {
+            self.collect_skipped_sources(scope, v, true);
+        }
+        if let Some(mut debugger) = self.debugger.take() {
+            let res = debugger.on_statement(self, scope, v);
+            self.debugger = Some(debugger);
+            res?;
+        }
+
         match v {
             IfThenElse {
                 meta,
@@ -241,28 +452,52 @@ impl Evaluator {
                 xtype,
                 name,
                 init,
-            } => self.eval_declaration(meta, scope, *xtype, name, init),
+            } => {
+                let anon = match init {
+                    Some((op, value)) => self
+                        .eval_anonymous_output(scope, value)?
+                        .map(|value| Some((*op, Box::new(value)))),
+                    None => None,
+                };
+                self.eval_declaration(meta, scope, *xtype, name, anon.as_ref().unwrap_or(init))
+            }
             Substitution {
                 meta,
                 name,
                 op,
                 value,
-            } => self.eval_substitution(meta, scope, name, *op, value),
+            } => {
+                let anon = self.eval_anonymous_output(scope, value)?;
+                self.eval_substitution(meta, scope, name, *op, anon.as_ref().unwrap_or(value))
+            }
             Block { meta, stmts } => self.eval_block(meta, scope, stmts),
             SignalLeft {
                 meta,
                 name,
                 op,
                 value,
-            } => self.eval_signal_left(meta, scope, name, *op, value),
+            } => {
+                let anon = self.eval_anonymous_output(scope, value)?;
+                self.eval_signal_left(meta, scope, name, *op, anon.as_ref().unwrap_or(value))
+            }
             SignalRight {
                 meta,
                 value,
                 op,
                 name,
-            } => self.eval_signal_right(meta, scope, value, *op, name),
+            } => {
+                let anon = self.eval_anonymous_output(scope, value)?;
+                self.eval_signal_right(meta, scope, anon.as_ref().unwrap_or(value), *op, name)
+            }
             SignalEq { meta, lhe, rhe, .. } => self.eval_signal_eq(meta, scope, lhe, rhe),
             InternalCall { meta, name, args } => self.eval_internal_call(meta, scope, name, args),
+            Tuple {
+                meta,
+                xtype,
+                names,
+                op,
+                value,
+            } => self.eval_tuple(meta, scope, *xtype, names, *op, value),
         }
     }
 
@@ -283,7 +518,93 @@ impl Evaluator {
                 stmt,
             } => self.eval_template_def(meta, scope, name, args, stmt),
             Declaration { decl, .. } => self.eval_statement_p(scope, decl),
+            MainComponent { meta, public, decl } => {
+                self.eval_main_component(meta, scope, public, decl)
+            }
+        }
+    }
+
+    fn eval_main_component(
+        &mut self,
+        meta: &Meta,
+        scope: &mut Scope,
+        public: &[String],
+        decl: &StatementP,
+    ) -> Result<()> {
+        let internal = || match decl {
+            StatementP::Declaration { name, .. } if name.name == "main" => Ok(()),
+            _ => Err(Error::InvalidParameter(
+                "public inputs can only be declared in component main".to_string(),
+            )),
+        };
+        let res = internal().and_then(|_| self.check_main_public(scope, public, decl));
+        self.register_error(meta, scope, res)?;
+
+        self.main_public = Some(public.to_vec());
+        self.eval_statement_p(scope, decl)
+    }
+
+    // checks that the inputs declared public are input signals of the main
+    //   template, the templates that are not found are reported when main is
+    //   instantiated
+    fn check_main_public(
+        &mut self,
+        scope: &Scope,
+        public: &[String],
+        decl: &StatementP,
+    ) -> Result<()> {
+        let template_name = match decl {
+            StatementP::Declaration {
+                init: Some((_, init)),
+                ..
+            } => match &**init {
+                ExpressionP::FunctionCall { name, .. } => name,
+                _ => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        let template = match scope.root().get(template_name) {
+            Some(template) => template,
+            None => return Ok(()),
+        };
+        let (template_meta, stmts, path) = match &*template {
+            ScopeValue::Template {
+                meta, stmt, path, ..
+            } => match &**stmt {
+                StatementP::Block { stmts, .. } => (meta, stmts, path),
+                _ => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+
+        let inputs = stmts
+            .iter()
+            .filter_map(|stmt| match &**stmt {
+                StatementP::Declaration {
+                    name,
+                    xtype: VariableType::Signal(xtype),
+                    ..
+                } if *xtype == SignalType::PublicInput || *xtype == SignalType::PrivateInput => {
+                    Some(&name.name)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for input in public {
+            if !inputs.contains(&input) {
+                let location = Location {
+                    file: path.to_string(),
+                    meta: template_meta.clone(),
+                };
+                self.error_labels.push((
+                    location,
+                    format!("template {} declared here", template_name),
+                ));
+                return Err(Error::NotFound(format!("input signal {} in main", input)));
+            }
         }
+        Ok(())
     }
 
     fn eval_internal_call(
@@ -305,7 +626,61 @@ impl Evaluator {
                 println!();
                 Ok(())
             }
-            _ => Err(Error::NotFound(format!("internal funcion {}!", name))),
+            "log" => {
+                if self.mode == Mode::GenWitness {
+                    match params.split_first() {
+                        Some((first, args)) if format_string(first).is_some() => {
+                            let fmt = format_string(first).unwrap();
+                            println!("{}", self.eval_log_format(scope, fmt, args)?);
+                        }
+                        _ => {
+                            let mut values = Vec::new();
+                            for param in params {
+                                values.push(self.eval_log_value(scope, param)?);
+                            }
+                            println!("{}", values.join(" "));
+                        }
+                    }
+                }
+                Ok(())
+            }
+            "assert" => {
+                let message = match params.get(1).map(|param| &**param) {
+                    None if params.len() == 1 => None,
+                    Some(ExpressionP::StringLiteral { value, .. }) => Some(value),
+                    _ => {
+                        return Err(Error::InvalidParameter(
+                            "assert requires a condition and an optional message".to_string(),
+                        ));
+                    }
+                };
+                // signals are only known when generating the witness, so the
+                //   assertion does not add constraints
+                if self.mode == Mode::GenWitness {
+                    match self.eval_expression_p(scope, &params[0])? {
+                        ReturnValue::Bool(true) => {}
+                        ReturnValue::Bool(false) => {
+                            let message = match message {
+                                Some(fmt) => self.eval_log_format(scope, fmt, &params[2..])?,
+                                None => format!("{:?}", params[0]),
+                            };
+                            self.label(params[0].meta(), "this condition is false".to_string());
+                            return Err(Error::AssertFailed(message));
+                        }
+                        _ => {
+                            return Err(Error::InvalidType(
+                                "assert condition is not boolean".to_string(),
+                            ));
+                        }
+                    }
+                }
+                Ok(())
+            }
+            _ => {
+                // function called as statement, the returned value is discarded
+                self.eval_function_call(meta, scope, name, params)?;
+                Ok(())
+            }
         };
 
         let res = internal();
@@ -326,7 +701,9 @@ impl Evaluator {
 
             let function = scope.root().get(name).ok_or_else(err_not_found)?;
             let (args, stmt, path) = match &*function {
-                ScopeValue::Function { args, stmt, path } => Ok((args, stmt, path)),
+                ScopeValue::Function {
+                    args, stmt, path, ..
+                } => Ok((args, stmt, path)),
                 _ => Err(err_not_found()),
             }?;
 
@@ -340,10 +717,18 @@ impl Evaluator {
                 format!("{}:{}", self.current_file, meta.start),
             );
 
+            let mut values = Vec::new();
             for n in 0..args.len() {
                 let value = self.eval_expression_p(scope, &*params[n])?;
+                values.push(value.to_string());
                 func_scope.insert(args[n].clone(), ScopeValue::from(value))?;
             }
+            self.frames.push(Frame {
+                component: None,
+                name: name.to_string(),
+                args: values,
+                call_site: self.location(meta),
+            });
 
             let mut new_current_function = Some(name.to_string());
             let mut new_current_file = path.to_string();
@@ -351,10 +736,12 @@ impl Evaluator {
             std::mem::swap(&mut new_current_function, &mut self.current_function);
             std::mem::swap(&mut new_current_file, &mut self.current_file);
 
-            self.eval_statement_p(&mut func_scope, stmt)?;
+            let res = self.eval_statement_p(&mut func_scope, stmt);
 
             std::mem::swap(&mut self.current_function, &mut new_current_function);
             std::mem::swap(&mut self.current_file, &mut new_current_file);
+            self.frames.pop();
+            res?;
 
             func_scope
                 .take_return()
@@ -365,6 +752,235 @@ impl Evaluator {
         self.register_error(meta, scope, res)
     }
 
+    fn eval_log_value(&mut self, scope: &Scope, expr: &ExpressionP) -> Result<String> {
+        if let ExpressionP::StringLiteral { value, .. } = expr {
+            return Ok(value.clone());
+        }
+        Ok(match self.eval_expression_p(scope, expr)? {
+            ReturnValue::Algebra(value) => self.signals.format(&value),
+            value => format!("{:?}", value),
+        })
+    }
+
+    // replaces each `{}` of the format string by the value of the next argument
+    //   and each `{:?}` by its name and value, `{{` and `}}` are escaped braces
+    fn eval_log_format(
+        &mut self,
+        scope: &Scope,
+        fmt: &str,
+        args: &[Box<ExpressionP>],
+    ) -> Result<String> {
+        let mut args = args.iter();
+        let mut next_arg = || {
+            args.next().ok_or_else(|| {
+                Error::InvalidParameter(format!("missing argument in format string {:?}", fmt))
+            })
+        };
+        let mut out = String::new();
+        let mut rest = fmt;
+        while let Some(c) = rest.chars().next() {
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                out.push(c);
+                rest = &rest[2..];
+            } else if rest.starts_with("{}") {
+                let value = self.eval_log_value(scope, next_arg()?)?;
+                out.push_str(&value);
+                rest = &rest[2..];
+            } else if rest.starts_with("{:?}") {
+                let arg = next_arg()?;
+                let value = self.eval_log_value(scope, arg)?;
+                out.push_str(&format!("{} = {}", self.log_name(scope, arg)?, value));
+                rest = &rest[4..];
+            } else {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        if next_arg().is_ok() {
+            return Err(Error::InvalidParameter(format!(
+                "too many arguments for format string {:?}",
+                fmt
+            )));
+        }
+        Ok(out)
+    }
+
+    // the full name of a signal, or the expression as written
+    fn log_name(&mut self, scope: &Scope, expr: &ExpressionP) -> Result<String> {
+        if let ExpressionP::Variable { name, .. } = expr {
+            let name_sel = self.expand_selectors(scope, name, None)?;
+            let full_name = self.expand_full_name(&name_sel);
+            if self.signals.get_by_name(&full_name).is_some() {
+                return Ok(full_name);
+            }
+        }
+        Ok(format!("{:?}", expr))
+    }
+
+    fn eval_anonymous_output(
+        &mut self,
+        scope: &Scope,
+        value: &ExpressionP,
+    ) -> Result<Option<ExpressionP>> {
+        if let ExpressionP::AnonymousComponent {
+            meta,
+            template,
+            params,
+            args,
+        } = value
+        {
+            let mut outputs = self.eval_anonymous_component(meta, scope, template, params, args)?;
+            if outputs.len() != 1 {
+                let err = Err(Error::InvalidParameter(format!(
+                    "anonymous component {} has {} outputs, only one is allowed here",
+                    template,
+                    outputs.len()
+                )));
+                return self.register_error(meta, scope, err);
+            }
+            Ok(Some(outputs.remove(0)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn eval_anonymous_component(
+        &mut self,
+        meta: &Meta,
+        scope: &Scope,
+        template: &str,
+        params: &[Box<ExpressionP>],
+        args: &[Box<ExpressionP>],
+    ) -> Result<Vec<ExpressionP>> {
+        self.trace(meta, || format!("eval_anonymous_component {}", template));
+
+        let mut internal = || {
+            // inputs and outputs of the template, in declaration order
+            let (inputs, outputs) = {
+                let err_invalid_template = || {
+                    Error::InvalidType(format!(
+                        "anonymous component only can be initialized with existing template {}",
+                        template
+                    ))
+                };
+                let value = scope
+                    .root()
+                    .get(template)
+                    .ok_or_else(err_invalid_template)?;
+                let stmt = match &*value {
+                    ScopeValue::Template { stmt, .. } => Ok(stmt),
+                    _ => Err(err_invalid_template()),
+                }?;
+
+                let mut inputs = Vec::new();
+                let mut outputs = Vec::new();
+                if let StatementP::Block { stmts, .. } = &**stmt {
+                    for stmt in stmts {
+                        if let StatementP::Declaration {
+                            name,
+                            xtype: VariableType::Signal(xtype),
+                            ..
+                        } = &**stmt
+                        {
+                            match xtype {
+                                SignalType::Output => outputs.push(name.name.clone()),
+                                SignalType::PublicInput | SignalType::PrivateInput => {
+                                    inputs.push(name.name.clone())
+                                }
+                                SignalType::Internal => {}
+                            }
+                        }
+                    }
+                }
+                (inputs, outputs)
+            };
+
+            if inputs.len() != args.len() {
+                return Err(Error::InvalidParameter(format!(
+                    "Invalid input count when instantiating anonymous {}",
+                    template
+                )));
+            }
+
+            let name = format!("anon${}", self.anonymous_components);
+            self.anonymous_components += 1;
+
+            let first_signal = self.signals.len();
+            let var = VariableP {
+                meta: meta.clone(),
+                name: name.clone(),
+                sels: Vec::new(),
+            };
+            let init = ExpressionP::FunctionCall {
+                meta: meta.clone(),
+                name: template.to_string(),
+                args: params.to_vec(),
+            };
+            self.eval_component_decl(meta, scope, &var)?;
+            self.eval_component_inst(meta, scope, &name, &init)?;
+
+            // wire the inputs, arrays element by element
+            let full_name = self.expand_full_name(&name);
+            let signal_names = (first_signal..self.signals.len())
+                .map(|id| (*self.signals.get_by_id(id).unwrap().full_name.0).clone())
+                .collect::<Vec<_>>();
+
+            for (input, arg) in inputs.iter().zip(args) {
+                let input_name = format!("{}.{}", full_name, input);
+                for signal_name in &signal_names {
+                    let indexes = if signal_name == &input_name {
+                        Vec::new()
+                    } else if signal_name.starts_with(&format!("{}[", input_name)) {
+                        let suffix = &signal_name[input_name.len() + 1..signal_name.len() - 1];
+                        suffix
+                            .split("][")
+                            .map(|index| index.parse::<u64>().unwrap())
+                            .collect::<Vec<_>>()
+                    } else {
+                        continue;
+                    };
+
+                    let mut sels = vec![Box::new(SelectorP::Pin {
+                        meta: meta.clone(),
+                        name: input.clone(),
+                    })];
+                    sels.extend(index_selectors(meta, &indexes));
+                    let signal = VariableP {
+                        meta: meta.clone(),
+                        name: name.clone(),
+                        sels,
+                    };
+                    let value = select_element(arg, &indexes)?;
+                    self.eval_signal_left(
+                        meta,
+                        scope,
+                        &signal,
+                        Opcode::SignalContrainLeft,
+                        &value,
+                    )?;
+                }
+            }
+
+            Ok(outputs
+                .into_iter()
+                .map(|output| ExpressionP::Variable {
+                    meta: meta.clone(),
+                    name: Box::new(VariableP {
+                        meta: meta.clone(),
+                        name: name.clone(),
+                        sels: vec![Box::new(SelectorP::Pin {
+                            meta: meta.clone(),
+                            name: output,
+                        })],
+                    }),
+                })
+                .collect())
+        };
+
+        let res = internal();
+        self.register_error(meta, scope, res)
+    }
+
     fn eval_component_decl(&mut self, _meta: &Meta, scope: &Scope, name: &VariableP) -> Result<()> {
         for selector_name in self.generate_selectors(scope, &name)? {
             scope.insert(selector_name, ScopeValue::UndefComponent)?;
@@ -412,7 +1028,6 @@ impl Evaluator {
                     )));
                 }
                 let mut evalargs = Vec::new();
-                let mut all_pending_input_signals: Vec<SignalId> = Vec::new();
 
                 // create a new scope, and put into arguments
                 let mut template_scope = Scope::new(
@@ -427,46 +1042,26 @@ impl Evaluator {
                     template_scope.insert(args[n].clone(), ScopeValue::from(value))?;
                 }
 
+                let call_site = self.location(meta);
                 let mut new_current_component = self.expand_full_name(component_name);
                 let mut new_current_file = path.to_string();
+                self.component_templates
+                    .insert(new_current_component.clone(), template_name.to_string());
+                self.frames.push(Frame {
+                    component: Some(new_current_component.clone()),
+                    name: template_name.to_string(),
+                    args: evalargs.iter().map(ReturnValue::to_string).collect(),
+                    call_site: call_site.clone(),
+                });
 
                 std::mem::swap(&mut new_current_file, &mut self.current_file);
                 std::mem::swap(&mut new_current_component, &mut self.current_component);
 
-                if let StatementP::Block { stmts, .. } = &**stmt {
-                    let signals = stmts
-                        .iter()
-                        .filter_map(|stmt| {
-                            if let StatementP::Declaration {
-                                meta,
-                                name,
-                                xtype: VariableType::Signal(xtype),
-                                ..
-                            } = &**stmt
-                            {
-                                Some((meta, name, xtype))
-                            } else {
-                                None
-                            }
-                        })
-                        .sorted_by(|(_, _, xtype1), (_, _, xtype2)| Ord::cmp(xtype1, xtype2));
-
-                    for (meta, name, xtype) in signals {
-                        let mut pending_signals =
-                            self.eval_declaration_signals(meta, &mut template_scope, *xtype, name)?;
-                        let is_public_or_private =
-                            *xtype == SignalType::PublicInput || *xtype == SignalType::PrivateInput;
-                        let is_not_main_in_genconstraints =
-                            !(component_name == "main" && self.mode == Mode::GenConstraints);
-                        if is_public_or_private && is_not_main_in_genconstraints {
-                            all_pending_input_signals.append(&mut pending_signals);
-                        }
-                    }
-                } else {
-                    unreachable!();
-                }
+                let res = self.eval_template_signals(&mut template_scope, component_name, stmt);
                 std::mem::swap(&mut self.current_file, &mut new_current_file);
                 std::mem::swap(&mut self.current_component, &mut new_current_component);
+                self.frames.pop();
+                let all_pending_input_signals = res?;
 
                 (
                     all_pending_input_signals.is_empty(),
@@ -474,6 +1069,7 @@ impl Evaluator {
                         template: template_name.to_string(),
                         path: path.to_string(),
                         args: evalargs,
+                        call_site,
                         pending_inputs: all_pending_input_signals,
                     },
                 )
@@ -495,6 +1091,73 @@ impl Evaluator {
         self.register_error(meta, scope, res)
     }
 
+    // declares the signals of a template being instantiated, returns the input
+    //   signals that have to be set before expanding it
+    fn eval_template_signals(
+        &mut self,
+        template_scope: &mut Scope,
+        component_name: &str,
+        stmt: &StatementP,
+    ) -> Result<Vec<SignalId>> {
+        let mut all_pending_input_signals: Vec<SignalId> = Vec::new();
+        if let StatementP::Block { stmts, .. } = stmt {
+            // the public inputs of the main component can be overriden
+            let main_public = if self.current_component == "main" {
+                self.main_public.clone()
+            } else {
+                None
+            };
+            let is_input = |xtype: SignalType| {
+                xtype == SignalType::PublicInput || xtype == SignalType::PrivateInput
+            };
+
+            let signals = stmts
+                .iter()
+                .filter_map(|stmt| {
+                    if let StatementP::Declaration {
+                        meta,
+                        name,
+                        xtype: VariableType::Signal(xtype),
+                        ..
+                    } = &**stmt
+                    {
+                        Some((meta, name, *xtype))
+                    } else {
+                        None
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            // public inputs are ordered as declared in the main public list
+            let signals = signals
+                .into_iter()
+                .map(|(meta, name, xtype)| match &main_public {
+                    Some(public) if is_input(xtype) => {
+                        match public.iter().position(|p| p == &name.name) {
+                            Some(pos) => (meta, name, SignalType::PublicInput, pos),
+                            None => (meta, name, SignalType::PrivateInput, 0),
+                        }
+                    }
+                    _ => (meta, name, xtype, 0),
+                })
+                .sorted_by_key(|(_, _, xtype, pos)| (*xtype, *pos));
+
+            for (meta, name, xtype, _) in signals {
+                let res = self.eval_declaration_signals(meta, template_scope, xtype, name);
+                let mut pending_signals = self.register_error(meta, template_scope, res)?;
+                let is_public_or_private = is_input(xtype);
+                let is_not_main_in_genconstraints =
+                    !(component_name == "main" && self.mode == Mode::GenConstraints);
+                if is_public_or_private && is_not_main_in_genconstraints {
+                    all_pending_input_signals.append(&mut pending_signals);
+                }
+            }
+        } else {
+            unreachable!();
+        }
+        Ok(all_pending_input_signals)
+    }
+
     fn eval_component_expand(
         &mut self,
         meta: &Meta,
@@ -504,10 +1167,23 @@ impl Evaluator {
         self.trace(meta, || format!("eval_component_expand {}", component_name));
 
         let component = scope.get(component_name).unwrap();
-        let (template, values) = match &*component {
-            ScopeValue::Component { template, args, .. } => (template, args),
+        let (template, values, call_site) = match &*component {
+            ScopeValue::Component {
+                template,
+                args,
+                call_site,
+                ..
+            } => (template, args, call_site),
             _ => unreachable!(),
         };
+        let frame = Frame {
+            component: Some(self.expand_full_name(component_name)),
+            name: template.to_string(),
+            args: values.iter().map(ReturnValue::to_string).collect(),
+            call_site: call_site.clone(),
+        };
+        self.component_templates
+            .insert(frame.component.clone().unwrap(), template.to_string());
 
         let template = scope.root().get(template).unwrap();
         let (args, stmt, path) = match &*template {
@@ -535,13 +1211,15 @@ impl Evaluator {
         std::mem::swap(&mut new_current_component, &mut self.current_component);
 
         // execute the template
-        self.eval_statement_p(&mut template_scope, stmt)?;
+        self.frames.push(frame);
+        let res = self.eval_statement_p(&mut template_scope, stmt);
+        self.frames.pop();
 
         // revert previous state
         std::mem::swap(&mut self.current_file, &mut new_current_file);
         std::mem::swap(&mut self.current_component, &mut new_current_component);
 
-        Ok(())
+        res
     }
 
     fn eval_variable(
@@ -610,6 +1288,25 @@ impl Evaluator {
         self.register_error(meta, scope, res)
     }
 
+    fn eval_ternary(
+        &mut self,
+        meta: &Meta,
+        scope: &Scope,
+        cond: &ExpressionP,
+        if_true: &ExpressionP,
+        if_false: &ExpressionP,
+    ) -> Result<ReturnValue> {
+        let mut internal = || match self.eval_expression_p(scope, cond)? {
+            ReturnValue::Bool(true) => self.eval_expression_p(scope, if_true),
+            ReturnValue::Bool(false) => self.eval_expression_p(scope, if_false),
+            _ => Err(Error::InvalidType(
+                "ternary condition is not boolean".to_string(),
+            )),
+        };
+        let res = internal();
+        self.register_error(meta, scope, res)
+    }
+
     fn eval_prefix_op(
         &mut self,
         meta: &Meta,
@@ -838,7 +1535,7 @@ impl Evaluator {
 
     fn eval_declaration_signals(
         &mut self,
-        _meta: &Meta,
+        meta: &Meta,
         scope: &mut Scope,
         xtype: SignalType,
         var: &VariableP,
@@ -847,15 +1544,22 @@ impl Evaluator {
 
         for signal_name in self.generate_selectors(scope, &var)? {
             let full_name = self.expand_full_name(&signal_name);
-            if self.signals.get_by_name(&full_name).is_some() {
+            if let Some(signal) = self.signals.get_by_name(&full_name) {
+                self.label_signal(signal.id, "first declared here");
                 return Err(Error::AlreadyExists(format!("signal {}", full_name)));
             }
-            if let Some(v) = self.deferred_signal_values.remove(&full_name) {
-                self.signals.insert(full_name, xtype, Some(v));
+            let signal_id = if let Some(v) = self.deferred_signal_values.remove(&full_name) {
+                self.signals.insert(full_name, xtype, Some(v))
             } else {
                 let signal_id = self.signals.insert(full_name, xtype, None);
                 pending_signals.push(signal_id);
-            }
+                signal_id
+            };
+            let location = Location {
+                file: self.current_file.clone(),
+                meta: meta.clone(),
+            };
+            self.signal_declarations.insert(signal_id, location);
         }
 
         Ok(pending_signals)
@@ -1018,6 +1722,96 @@ impl Evaluator {
         self.register_error(meta, scope, res)
     }
 
+    fn eval_tuple(
+        &mut self,
+        meta: &Meta,
+        scope: &mut Scope,
+        xtype: VariableType,
+        vars: &[Box<VariableP>],
+        op: Opcode,
+        expr: &ExpressionP,
+    ) -> Result<()> {
+        self.trace(meta, || format!("eval_tuple {:?}", expr));
+
+        let mut internal = || {
+            let values = match expr {
+                ExpressionP::Tuple { values, .. } => {
+                    values.iter().map(|value| (**value).clone()).collect()
+                }
+                ExpressionP::AnonymousComponent {
+                    meta,
+                    template,
+                    params,
+                    args,
+                } => self.eval_anonymous_component(meta, scope, template, params, args)?,
+                _ => {
+                    return Err(Error::InvalidType(format!(
+                        "cannot assign {:?} to a tuple",
+                        expr
+                    )));
+                }
+            };
+
+            if vars.len() != values.len() {
+                return Err(Error::InvalidParameter(format!(
+                    "cannot assign {} values to {} variables",
+                    values.len(),
+                    vars.len()
+                )));
+            }
+
+            match (xtype, op) {
+                (VariableType::Var, _) => {
+                    for (var, value) in vars.iter().zip(values) {
+                        let init = Some((Opcode::Assig, Box::new(value)));
+                        self.eval_declaration(meta, scope, xtype, var, &init)?;
+                    }
+                }
+                (_, Opcode::SignalContrainLeft) | (_, Opcode::SignalWireLeft) => {
+                    for (var, value) in vars.iter().zip(values) {
+                        self.eval_signal_left(meta, scope, var, op, &value)?;
+                    }
+                }
+                _ => {
+                    // all values are evaluated before the substitutions, so (a,b) = (b,a) swaps
+                    let mut tuple_scope = Scope::new(
+                        false,
+                        Some(scope),
+                        format!("{}:{}", self.current_file, meta.start),
+                    );
+                    let tmps = (0..values.len())
+                        .map(|n| VariableP {
+                            meta: meta.clone(),
+                            name: format!("tuple${}", n),
+                            sels: Vec::new(),
+                        })
+                        .collect::<Vec<_>>();
+                    for (tmp, value) in tmps.iter().zip(values) {
+                        let init = Some((Opcode::Assig, Box::new(value)));
+                        self.eval_declaration(
+                            meta,
+                            &mut tuple_scope,
+                            VariableType::Var,
+                            tmp,
+                            &init,
+                        )?;
+                    }
+                    for (var, tmp) in vars.iter().zip(tmps) {
+                        let value = ExpressionP::Variable {
+                            meta: meta.clone(),
+                            name: Box::new(tmp),
+                        };
+                        self.eval_substitution(meta, &mut tuple_scope, var, op, &value)?;
+                    }
+                }
+            }
+            Ok(())
+        };
+
+        let res = internal();
+        self.register_error(meta, scope, res)
+    }
+
     fn eval_block(
         &mut self,
         meta: &Meta,
@@ -1051,7 +1845,7 @@ impl Evaluator {
     fn eval_signal_left(
         &mut self,
         meta: &Meta,
-        scope: &mut Scope,
+        scope: &Scope,
         signal: &VariableP,
         op: Opcode,
         expr: &ExpressionP,
@@ -1079,6 +1873,10 @@ impl Evaluator {
             //    S === 1  // constrain verification
             //
 
+            if self.sources.is_some() && op == Opcode::SignalWireLeft {
+                self.collect_assignment(meta, scope, signal);
+            }
+
             // eval == iff in GenContraints
             if self.mode == Mode::GenConstraints && op == Opcode::SignalContrainLeft {
                 self.eval_signal_eq(
@@ -1103,6 +1901,7 @@ impl Evaluator {
                     if let ReturnValue::Algebra(a) = v {
                         self.signals.update(signal_id, a);
                     } else {
+                        self.label_signal(signal_id, "signal declared here");
                         return Err(Error::InvalidType(format!(
                             "Cannot assign {:?} to signal",
                             v
@@ -1195,7 +1994,7 @@ impl Evaluator {
     fn eval_signal_eq(
         &mut self,
         meta: &Meta,
-        scope: &mut Scope,
+        scope: &Scope,
         lhe: &ExpressionP,
         rhe: &ExpressionP,
     ) -> Result<()> {
@@ -1210,6 +2009,8 @@ impl Evaluator {
                 match constrain {
                     algebra::Value::FieldScalar(ref fs) if fs.is_zero() => {}
                     _ => {
+                        self.label(lhe.meta(), format!("evaluates to {}", left));
+                        self.label(rhe.meta(), format!("evaluates to {}", right));
                         return Err(Error::CannotTestConstrain(format!(
                             "{:?}==={:?} => {}==={}",
                             lhe,
@@ -1232,12 +2033,13 @@ impl Evaluator {
                     _ => constrain.into_qeq(),
                 };
 
-                let count = if self.debug {
-                    self.constraints
-                        .push(qeq, Some(format!("{}:{}", self.current_file, meta.start)))
-                } else {
-                    self.constraints.push(qeq, None)
-                };
+                let count = self.constraints.push_located(
+                    qeq,
+                    &self.current_file,
+                    meta.start,
+                    meta.end,
+                    &self.current_component,
+                );
 
                 if count > 0 && count % 100_000 == 0 {
                     let now = std::time::Instant::now();
@@ -1259,9 +2061,13 @@ impl Evaluator {
 
     fn eval_include(&mut self, meta: &Meta, scope: &mut Scope, filename: &str) -> Result<()> {
         let mut internal = || {
-            let mut full_path = PathBuf::new();
-            full_path.push(&self.path);
-            full_path.push(&filename);
+            let (full_path, skipped) = self.resolve_include(filename);
+            for path in skipped {
+                let path = path.to_string_lossy().to_string();
+                if !self.skipped_includes.contains(&path) {
+                    self.skipped_includes.push(path);
+                }
+            }
 
             let mut code = String::new();
             if let Err(ioerr) =
@@ -1270,29 +2076,23 @@ impl Evaluator {
                 return Err(Error::Io(format!("{:?}", full_path), ioerr.to_string()));
             }
 
-            let mut hasher = Blake2b::new(64);
-            hasher.update(&code.as_bytes());
-
-            let hash = hasher.finalize();
-            let hash_hex = hex::encode(hash.as_bytes());
+            let hash_hex = cache::hash(code.as_bytes());
 
-            if !self.processed_files.iter().any(|h| h == &hash_hex) {
-                self.processed_files.push(hash_hex);
+            if !self.processed_files.iter().any(|(_, h)| h == &hash_hex) {
+                self.processed_files
+                    .push((full_path.to_str().unwrap().to_string(), hash_hex.clone()));
 
                 let mut new_current_file = full_path.to_str().unwrap().to_string();
                 let mut new_path = full_path.parent().unwrap().to_path_buf();
                 std::mem::swap(&mut new_current_file, &mut self.current_file);
                 std::mem::swap(&mut new_path, &mut self.path);
 
-                match za_parser::parse(&code) {
+                match self.parse_cached(&code, &hash_hex) {
                     Ok(mut elements) => {
                         self.eval_body_elements_p(&Meta::new(0, 0, None), scope, &elements)?;
                         self.collected_asts.append(&mut elements);
                     }
-                    Err(za_parser::Error::ParseError(err, meta)) => {
-                        let err: Result<()> = Err(Error::Parse(err));
-                        return self.register_error(&meta, scope, err);
-                    }
+                    Err(err) => self.parse_error(scope, err)?,
                 }
 
                 std::mem::swap(&mut self.path, &mut new_path);
@@ -1306,6 +2106,64 @@ impl Evaluator {
         self.register_error(meta, scope, res)
     }
 
+    // build the main template invocation, matching the main parameters by name
+    //   with the template arguments
+    fn main_invocation(&self, scope: &Scope, main: &str) -> Result<String> {
+        if self.main_params.is_empty() {
+            return Ok(if main.contains('(') {
+                main.to_string()
+            } else {
+                format!("{}()", main)
+            });
+        }
+        if main.contains('(') {
+            return Err(Error::InvalidParameter(format!(
+                "main {} already has arguments",
+                main
+            )));
+        }
+
+        let err_not_found = || Error::NotFound(format!("template {}", main));
+        let args = match &*scope.get(main).ok_or_else(err_not_found)? {
+            ScopeValue::Template { args, .. } => args.clone(),
+            _ => return Err(err_not_found()),
+        };
+        if let Some((name, _)) = self.main_params.iter().find(|(n, _)| !args.contains(n)) {
+            return Err(Error::InvalidParameter(format!(
+                "template {} has no argument {}",
+                main, name
+            )));
+        }
+        let values = args
+            .iter()
+            .map(|arg| {
+                self.main_params
+                    .iter()
+                    .find(|(name, _)| name == arg)
+                    .map(|(_, value)| value.clone())
+                    .ok_or_else(|| {
+                        Error::InvalidParameter(format!("missing main parameter {}", arg))
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(format!("{}({})", main, values.join(",")))
+    }
+
+    // parse the code, reusing the cached ast if any
+    fn parse_cached(&self, code: &str, hash: &str) -> za_parser::Result<Vec<BodyElementP>> {
+        if let Some(elements) = self.cache.as_ref().and_then(|c| c.load_ast(hash)) {
+            return Ok(elements);
+        }
+        let elements = za_parser::parse(code)?;
+        if let Some(cache) = &self.cache {
+            if let Err(err) = cache.store_ast(hash, &elements) {
+                warn!("Cannot store ast in cache: {:?}", err);
+            }
+        }
+        Ok(elements)
+    }
+
     fn eval_function_def(
         &mut self,
         meta: &Meta,
@@ -1318,6 +2176,7 @@ impl Evaluator {
             scope.insert(
                 name.to_string(),
                 ScopeValue::Function {
+                    meta: meta.clone(),
                     args: args.to_vec(),
                     stmt: Box::new(stmt.clone()),
                     path: self.current_file.to_string(),
@@ -1341,7 +2200,7 @@ impl Evaluator {
             scope.insert(
                 name.to_string(),
                 ScopeValue::Template {
-                    attrs: meta.attrs.clone(),
+                    meta: meta.clone(),
                     args: args.to_vec(),
                     stmt: Box::new(stmt.clone()),
                     path: self.current_file.clone(),
@@ -1361,7 +2220,16 @@ impl Evaluator {
     ) -> Result<()> {
         let mut internal = || {
             for be in bes {
-                self.eval_body_element_p(scope, &be)?;
+                if self.collected_errors.is_some() {
+                    let mut refs = Vec::new();
+                    body_element_references(be, &mut refs);
+                    for (name, meta) in refs {
+                        let location = self.location(meta);
+                        self.references.push((name.to_string(), location));
+                    }
+                }
+                let res = self.eval_body_element_p(scope, be);
+                self.collect_error(res)?;
             }
             Ok(())
         };
@@ -1371,6 +2239,177 @@ impl Evaluator {
 
     // helpers  -------------------------------------------------------------------------------
 
+    // registers the syntax errors, that are collected if the errors are being
+    //   collected or returned otherwise
+    fn parse_error(&mut self, scope: &mut Scope, err: za_parser::Error) -> Result<()> {
+        let mut errors = err.errors();
+        if let Some(collected) = &mut self.collected_errors {
+            for (message, meta) in errors {
+                let location = Location {
+                    file: self.current_file.clone(),
+                    meta,
+                };
+                collected.push((Error::Parse(message), location));
+            }
+            // continue with the elements parsed by recovering from the errors
+            self.eval_body_elements_p(&Meta::new(0, 0, None), scope, err.recovered())
+        } else {
+            let (message, meta) = errors.remove(0);
+            self.register_error(&meta, scope, Err(Error::Parse(message)))
+        }
+    }
+
+    // if the errors are being collected, records the error located by the last
+    //   error context and continues
+    fn collect_error(&mut self, res: Result<()>) -> Result<()> {
+        match (res, self.collected_errors.is_some()) {
+            (Err(err), true) => {
+                let location = match self.last_error.take() {
+                    Some(ctx) => Location {
+                        file: ctx.file,
+                        meta: ctx.meta,
+                    },
+                    None => self.location(&Meta::new(0, 0, None)),
+                };
+                self.error_labels.clear();
+                if let Some(collected) = &mut self.collected_errors {
+                    collected.push((err, location));
+                }
+                Ok(())
+            }
+            (res, _) => res,
+        }
+    }
+
+    /// Records the calls to templates or functions that are not defined in
+    ///   the root scope, if the errors are being collected. The calls are not
+    ///   checked if a file cannot be parsed, since its definitions are missing
+    pub fn check_references(&mut self, scope: &Scope) {
+        let references = std::mem::take(&mut self.references);
+        let unparsed = match &self.collected_errors {
+            Some(collected) => collected
+                .iter()
+                .any(|(err, _)| matches!(err, Error::Parse(_))),
+            None => true,
+        };
+        if unparsed {
+            return;
+        }
+        for (name, location) in references {
+            let defined = matches!(
+                scope.get(&name).as_deref(),
+                Some(ScopeValue::Template { .. }) | Some(ScopeValue::Function { .. })
+            );
+            if !defined {
+                if let Some(collected) = &mut self.collected_errors {
+                    let err = Error::NotFound(format!("template or function {}", name));
+                    collected.push((err, location));
+                }
+            }
+        }
+    }
+
+    fn location(&self, meta: &Meta) -> Location {
+        Location {
+            file: self.current_file.clone(),
+            meta: meta.clone(),
+        }
+    }
+
+    // adds a secondary label in the current file to the next registered error
+    fn label(&mut self, meta: &Meta, message: String) {
+        let location = self.location(meta);
+        self.error_labels.push((location, message));
+    }
+
+    // adds a secondary label in the declaration of a signal to the next registered error
+    fn label_signal(&mut self, signal_id: SignalId, message: &str) {
+        if let Some(location) = self.signal_declarations.get(&signal_id) {
+            self.error_labels
+                .push((location.clone(), message.to_string()));
+        }
+    }
+
+    // records the signal assigned by a `<--`, if the indexes cannot be evaluated
+    //   the whole signal array is recorded
+    fn collect_assignment(&mut self, meta: &Meta, scope: &Scope, signal: &VariableP) {
+        let last_error = self.last_error.take();
+        let assigned = match self.expand_selectors(scope, signal, None) {
+            Ok(name) => {
+                let full_name = self.expand_full_name(&name);
+                match self.signals.get_by_name(&full_name) {
+                    Some(signal) => Assigned::Signal(signal.id),
+                    None => Assigned::Array(full_name),
+                }
+            }
+            Err(_) => Assigned::Array(self.expand_full_name(&signal.name)),
+        };
+        self.last_error = last_error;
+        self.error_labels.clear();
+
+        let location = Location {
+            file: self.current_file.clone(),
+            meta: meta.clone(),
+        };
+        if let Some(sources) = &mut self.sources {
+            sources.assignments.push((assigned, location));
+        }
+    }
+
+    // records the assignments and constraints inside a statement skipped by `#[w]`,
+    //   the root `<--`, `<==` and `===` statements are processed as usual
+    fn collect_skipped_sources(&mut self, scope: &Scope, stmt: &StatementP, root: bool) {
+        use za_parser::ast::StatementP::*;
+
+        let skipped_constraint = |eval: &mut Self, meta: &Meta| {
+            let location = Location {
+                file: eval.current_file.clone(),
+                meta: meta.clone(),
+            };
+            if let Some(sources) = &mut eval.sources {
+                sources.skipped_constraints.push(location);
+            }
+        };
+
+        match stmt {
+            SignalLeft { meta, name, op, .. } if !root => match op {
+                Opcode::SignalWireLeft => self.collect_assignment(meta, scope, name),
+                _ => skipped_constraint(self, meta),
+            },
+            SignalRight { meta, name, op, .. } => match op {
+                Opcode::SignalWireRight => self.collect_assignment(meta, scope, name),
+                _ => skipped_constraint(self, meta),
+            },
+            SignalEq { meta, .. } if !root => skipped_constraint(self, meta),
+            Tuple {
+                meta, names, op, ..
+            } if !root => match op {
+                Opcode::SignalWireLeft => {
+                    for name in names {
+                        self.collect_assignment(meta, scope, name);
+                    }
+                }
+                Opcode::SignalContrainLeft => skipped_constraint(self, meta),
+                _ => {}
+            },
+            Block { stmts, .. } => {
+                for stmt in stmts {
+                    self.collect_skipped_sources(scope, stmt, false);
+                }
+            }
+            IfThenElse { xthen, xelse, .. } => {
+                self.collect_skipped_sources(scope, xthen, false);
+                if let Some(xelse) = xelse {
+                    self.collect_skipped_sources(scope, xelse, false);
+                }
+            }
+            For { stmt, .. } | While { stmt, .. } => {
+                self.collect_skipped_sources(scope, stmt, false)
+            }
+            _ => {}
+        }
+    }
+
     fn trace<F>(&self, meta: &Meta, f: F)
     where
         F: FnOnce() -> String,
@@ -1380,6 +2419,32 @@ impl Evaluator {
         }
     }
 
+    // returns the path of an include and the paths searched before it
+    fn resolve_include(&self, filename: &str) -> (PathBuf, Vec<PathBuf>) {
+        // includes are searched relative to the current file, then in the
+        //   named libraries (`include "lib/file.circom"`) and finally in the
+        //   include paths. If not found, the relative path is returned so the
+        //   error refers to it.
+        let mut candidates = vec![self.path.join(filename)];
+
+        let mut parts = filename.splitn(2, '/');
+        if let (Some(lib), Some(rest)) = (parts.next(), parts.next()) {
+            if let Some(lib_path) = self.libraries.get(lib) {
+                candidates.push(lib_path.join(rest));
+            }
+        }
+
+        candidates.extend(self.include_paths.iter().map(|dir| dir.join(filename)));
+        match candidates.iter().position(|candidate| candidate.exists()) {
+            Some(n) => {
+                let path = candidates[n].clone();
+                candidates.truncate(n);
+                (path, candidates)
+            }
+            None => (candidates.swap_remove(0), Vec::new()),
+        }
+    }
+
     fn generate_selectors(&mut self, scope: &Scope, var: &VariableP) -> Result<Vec<String>> {
         // TODO: convert into iterable collection?
         fn generate_selectors_1(
@@ -1570,3 +2635,60 @@ impl Evaluator {
         Ok(())
     }
 }
+
+// the first argument of `log` is a format string if it has any placeholder
+fn format_string(expr: &ExpressionP) -> Option<&str> {
+    match expr {
+        ExpressionP::StringLiteral { value, .. }
+            if ["{}", "{:?}", "{{", "}}"].iter().any(|p| value.contains(p)) =>
+        {
+            Some(value)
+        }
+        _ => None,
+    }
+}
+
+// index selectors for the specified positions
+#[allow(clippy::vec_box)]
+fn index_selectors(meta: &Meta, indexes: &[u64]) -> Vec<Box<SelectorP>> {
+    indexes
+        .iter()
+        .map(|index| {
+            Box::new(SelectorP::Index {
+                meta: meta.clone(),
+                pos: Box::new(ExpressionP::Number {
+                    meta: meta.clone(),
+                    value: BigInt::from(*index),
+                }),
+            })
+        })
+        .collect()
+}
+
+// selects the element of an array expression, `v` => `v[i][j]`, `[[a,b],[c,d]]` => `c`
+fn select_element(expr: &ExpressionP, indexes: &[u64]) -> Result<ExpressionP> {
+    if indexes.is_empty() {
+        return Ok(expr.clone());
+    }
+    match expr {
+        ExpressionP::Variable { meta, name } => {
+            let mut name = name.clone();
+            name.sels.extend(index_selectors(meta, indexes));
+            Ok(ExpressionP::Variable {
+                meta: meta.clone(),
+                name,
+            })
+        }
+        ExpressionP::Array { values, .. } => match values.get(indexes[0] as usize) {
+            Some(value) => select_element(value, &indexes[1..]),
+            None => Err(Error::InvalidSelector(format!(
+                "index {} out of bounds in {:?}",
+                indexes[0], expr
+            ))),
+        },
+        _ => Err(Error::InvalidParameter(format!(
+            "cannot wire {:?} to an array input",
+            expr
+        ))),
+    }
+}
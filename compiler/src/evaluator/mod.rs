@@ -2,10 +2,11 @@ pub use super::algebra;
 
 mod error;
 mod eval;
+mod references;
 mod scope;
 mod test;
 mod types;
 
 pub use self::error::*;
-pub use self::eval::{ErrorContext, Evaluator, Mode};
+pub use self::eval::{Assigned, ErrorContext, Evaluator, Frame, Location, Mode, SignalSources};
 pub use self::scope::{Scope, ScopeValue};
@@ -0,0 +1,130 @@
+use za_parser::ast::{BodyElementP, ExpressionP, Meta, SelectorP, StatementP, VariableP};
+
+// internal calls that are not user functions
+const BUILTINS: &[&str] = &["dbg", "dbg_signals", "log", "assert"];
+
+/// Names and locations of the templates and functions called in a body element
+pub fn body_element_references<'a>(element: &'a BodyElementP, refs: &mut Vec<(&'a str, &'a Meta)>) {
+    match element {
+        BodyElementP::Include { .. } => {}
+        BodyElementP::FunctionDef { stmt, .. } | BodyElementP::TemplateDef { stmt, .. } => {
+            statement_references(stmt, refs)
+        }
+        BodyElementP::Declaration { decl, .. } | BodyElementP::MainComponent { decl, .. } => {
+            statement_references(decl, refs)
+        }
+    }
+}
+
+fn statement_references<'a>(stmt: &'a StatementP, refs: &mut Vec<(&'a str, &'a Meta)>) {
+    use za_parser::ast::StatementP::*;
+    match stmt {
+        IfThenElse {
+            xif, xthen, xelse, ..
+        } => {
+            expression_references(xif, refs);
+            statement_references(xthen, refs);
+            if let Some(xelse) = xelse {
+                statement_references(xelse, refs);
+            }
+        }
+        For {
+            init,
+            cond,
+            step,
+            stmt,
+            ..
+        } => {
+            statement_references(init, refs);
+            expression_references(cond, refs);
+            statement_references(step, refs);
+            statement_references(stmt, refs);
+        }
+        While { cond, stmt, .. } => {
+            expression_references(cond, refs);
+            statement_references(stmt, refs);
+        }
+        Return { value, .. } => expression_references(value, refs),
+        Declaration { name, init, .. } => {
+            variable_references(name, refs);
+            if let Some((_, init)) = init {
+                expression_references(init, refs);
+            }
+        }
+        Substitution { name, value, .. }
+        | SignalLeft { name, value, .. }
+        | SignalRight { name, value, .. } => {
+            variable_references(name, refs);
+            expression_references(value, refs);
+        }
+        Block { stmts, .. } => stmts
+            .iter()
+            .for_each(|stmt| statement_references(stmt, refs)),
+        SignalEq { lhe, rhe, .. } => {
+            expression_references(lhe, refs);
+            expression_references(rhe, refs);
+        }
+        InternalCall { meta, name, args } => {
+            if !BUILTINS.contains(&name.as_str()) {
+                refs.push((name, meta));
+            }
+            args.iter().for_each(|arg| expression_references(arg, refs));
+        }
+        Tuple { names, value, .. } => {
+            names
+                .iter()
+                .for_each(|name| variable_references(name, refs));
+            expression_references(value, refs);
+        }
+    }
+}
+
+fn expression_references<'a>(expr: &'a ExpressionP, refs: &mut Vec<(&'a str, &'a Meta)>) {
+    use za_parser::ast::ExpressionP::*;
+    match expr {
+        FunctionCall { meta, name, args } => {
+            refs.push((name, meta));
+            args.iter().for_each(|arg| expression_references(arg, refs));
+        }
+        AnonymousComponent {
+            meta,
+            template,
+            params,
+            args,
+        } => {
+            refs.push((template, meta));
+            params
+                .iter()
+                .chain(args)
+                .for_each(|expr| expression_references(expr, refs));
+        }
+        Variable { name, .. } => variable_references(name, refs),
+        PrefixOp { rhe, .. } => expression_references(rhe, refs),
+        InfixOp { lhe, rhe, .. } => {
+            expression_references(lhe, refs);
+            expression_references(rhe, refs);
+        }
+        Array { values, .. } | Tuple { values, .. } => values
+            .iter()
+            .for_each(|value| expression_references(value, refs)),
+        Ternary {
+            cond,
+            if_true,
+            if_false,
+            ..
+        } => {
+            expression_references(cond, refs);
+            expression_references(if_true, refs);
+            expression_references(if_false, refs);
+        }
+        Number { .. } | StringLiteral { .. } => {}
+    }
+}
+
+fn variable_references<'a>(var: &'a VariableP, refs: &mut Vec<(&'a str, &'a Meta)>) {
+    for sel in &var.sels {
+        if let SelectorP::Index { pos, .. } = &**sel {
+            expression_references(pos, refs);
+        }
+    }
+}
@@ -5,10 +5,11 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 
-use za_parser::ast::{Attributes, StatementP};
+use za_parser::ast::{Meta, StatementP};
 
 use super::algebra;
 use super::error::*;
+use super::eval::Location;
 use super::types::*;
 
 #[derive(Debug, Clone)]
@@ -18,12 +19,13 @@ pub enum ScopeValue {
     Bool(bool),
     Algebra(algebra::Value),
     Function {
+        meta: Meta,
         args: Vec<String>,
         stmt: Box<StatementP>,
         path: String,
     },
     Template {
-        attrs: Attributes,
+        meta: Meta,
         args: Vec<String>,
         stmt: Box<StatementP>,
         path: String,
@@ -33,6 +35,9 @@ pub enum ScopeValue {
         path: String,
         args: Vec<ReturnValue>,
 
+        // where the component is instantiated
+        call_site: Location,
+
         // None => Component already expanded
         // Some(n) => Signals pending for expansion
         pending_inputs: Vec<algebra::SignalId>,
@@ -124,6 +129,24 @@ impl<'a> Scope<'a> {
         it
     }
 
+    /// The variables visible from this scope up to the start of its template
+    ///   or function, innermost first
+    pub fn visible_vars(&self) -> Vec<(String, ScopeValue)> {
+        let mut vars = Vec::new();
+        let mut it = self;
+        loop {
+            for k in sorted(it.vars.borrow().keys()) {
+                if !vars.iter().any(|(name, _)| name == k) {
+                    vars.push((k.clone(), it.vars.borrow()[k].clone()));
+                }
+            }
+            match it.prev {
+                Some(prev) if !it.start => it = prev,
+                _ => return vars,
+            }
+        }
+    }
+
     pub fn insert(&self, k: String, v: ScopeValue) -> Result<()> {
         if self.vars.borrow().contains_key(&k) {
             Err(Error::AlreadyExists(k))
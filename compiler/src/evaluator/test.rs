@@ -72,7 +72,7 @@ mod test {
         eval_constraint
             .constraints
             .satisfies_with_signals(&eval.signals)
-            .map_err(Error::Unexpected)?;
+            .map_err(|report| Error::Unexpected(report.to_string()))?;
 
         Ok((eval, scope))
     }
@@ -397,7 +397,7 @@ mod test {
         ",
         )?;
 
-        constrain_eq(&eval, 0, "[ ]*[ ]+[1main.a-1one]");
+        constrain_eq(&eval, 0, "[ ]*[ ]+[-1one+1main.a]");
         Ok(())
     }
 
@@ -416,7 +416,7 @@ mod test {
         )?;
 
         signal_eq(&eval, "main.const", "main.const:Internal:Some(2)");
-        constrain_eq(&eval, 0, "[ ]*[ ]+[-2main.in+1one]");
+        constrain_eq(&eval, 0, "[ ]*[ ]+[1one-2main.in]");
 
         Ok(())
     }
@@ -434,8 +434,8 @@ mod test {
             component main=t();
         ",
         )?;
-        constrain_eq(&eval, 0, "[ ]*[ ]+[1main.out-1main.in]");
-        constrain_eq(&eval, 1, "[ ]*[ ]+[1main.out-1one]");
+        constrain_eq(&eval, 0, "[ ]*[ ]+[-1main.in+1main.out]");
+        constrain_eq(&eval, 1, "[ ]*[ ]+[-1one+1main.out]");
         Ok(())
     }
 
@@ -452,8 +452,8 @@ mod test {
             component main=t();
         ",
         )?;
-        constrain_eq(&eval, 0, "[ ]*[ ]+[1main.const-2one]");
-        constrain_eq(&eval, 1, "[ ]*[ ]+[-2main.in+1one]");
+        constrain_eq(&eval, 0, "[ ]*[ ]+[-2one+1main.const]");
+        constrain_eq(&eval, 1, "[ ]*[ ]+[1one-2main.in]");
         Ok(())
     }
 
@@ -490,7 +490,7 @@ mod test {
             component main=t();
         ",
         )?;
-        constrain_eq(&eval, 0, "[ ]*[ ]+[1main.in[1][0]+1main.in[0][1]]");
+        constrain_eq(&eval, 0, "[ ]*[ ]+[1main.in[0][1]+1main.in[1][0]]");
         Ok(())
     }
 
@@ -507,8 +507,8 @@ mod test {
             component main=t();
         ",
         )?;
-        constrain_eq(&eval, 0, "[ ]*[ ]+[1main.in[0]-1one]");
-        constrain_eq(&eval, 1, "[ ]*[ ]+[-1main.in[1]+1one]");
+        constrain_eq(&eval, 0, "[ ]*[ ]+[-1one+1main.in[0]]");
+        constrain_eq(&eval, 1, "[ ]*[ ]+[1one-1main.in[1]]");
         Ok(())
     }
 
@@ -528,7 +528,7 @@ mod test {
             component main=t1();
         ",
         )?;
-        constrain_eq(&eval, 0, "[ ]*[ ]+[1main.T0.t0in-5one]");
+        constrain_eq(&eval, 0, "[ ]*[ ]+[-5one+1main.T0.t0in]");
         Ok(())
     }
 
@@ -551,7 +551,7 @@ mod test {
             component main=t1();
         ",
         )?;
-        constrain_eq(&eval, 0, "[ ]*[ ]+[1main.T0[0].t0in-5one]");
+        constrain_eq(&eval, 0, "[ ]*[ ]+[-5one+1main.T0[0].t0in]");
         Ok(())
     }
 
@@ -766,4 +766,245 @@ mod test {
         .for_each(|(n, s)| assert_eq!(1 + n, eval.signals.get_by_name(s).unwrap().id));
         Ok(())
     }
+    #[test]
+    fn test_main_params() -> Result<()> {
+        let circuit = "
+            template T(a, b) {
+                signal input x[a];
+                signal output y[b];
+            }";
+
+        let mut eval = Evaluator::new(
+            Mode::GenConstraints,
+            Signals::default(),
+            Constraints::default(),
+        );
+        let mut scope = Scope::new(true, None, "root".to_string());
+        eval.eval_inline(&mut scope, circuit)?;
+        eval.main = Some("T".to_string());
+        eval.main_params = vec![
+            ("b".to_string(), "3".to_string()),
+            ("a".to_string(), "2".to_string()),
+        ];
+        eval.eval_main(&mut scope)?;
+
+        assert_eq!(Some("T(2,3)".to_string()), eval.main);
+        signal_eq(&eval, "main.x[1]", "main.x[1]:PublicInput:None");
+        signal_eq(&eval, "main.y[2]", "main.y[2]:Output:None");
+
+        let mut eval = Evaluator::new(
+            Mode::GenConstraints,
+            Signals::default(),
+            Constraints::default(),
+        );
+        let mut scope = Scope::new(true, None, "root".to_string());
+        eval.eval_inline(&mut scope, circuit)?;
+        eval.main = Some("T".to_string());
+        eval.main_params = vec![("a".to_string(), "2".to_string())];
+        assert!(eval.eval_main(&mut scope).is_err());
+
+        eval.main_params = vec![
+            ("a".to_string(), "2".to_string()),
+            ("b".to_string(), "2".to_string()),
+            ("c".to_string(), "2".to_string()),
+        ];
+        assert!(eval.eval_main(&mut scope).is_err());
+
+        // the parameters are not ignored without a main template
+        eval.main = None;
+        eval.main_params = vec![("a".to_string(), "2".to_string())];
+        match eval.eval_main(&mut scope) {
+            Err(Error::InvalidParameter(msg)) => {
+                assert_eq!("parameters require a main template", msg)
+            }
+            _ => panic!("the parameters without main are accepted"),
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_main_public_inputs() -> Result<()> {
+        let (eval, _) = eval_constraint(
+            "
+            template T() {
+                signal input a;
+                signal private input b;
+                signal input c[2];
+                signal output d;
+            }
+            component main {public [c, b]} = T();",
+        )?;
+
+        signal_eq(&eval, "main.a", "main.a:PrivateInput:None");
+        signal_eq(&eval, "main.b", "main.b:PublicInput:None");
+        signal_eq(&eval, "main.c[1]", "main.c[1]:PublicInput:None");
+        assert_eq!(
+            vec!["main.d", "main.c[0]", "main.c[1]", "main.b"],
+            eval.signals.main_public_input_names()
+        );
+
+        assert!(eval_constraint(
+            "
+            template T() { signal input a; signal output d; }
+            component main {public [d]} = T();",
+        )
+        .is_err());
+
+        assert!(eval_constraint(
+            "
+            template T() { signal input a; }
+            component other {public [a]} = T();",
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_circom2_vars() -> Result<()> {
+        let (_, scope) = eval_constraint(
+            "
+            var a[3] = [1, 2, 3];
+            var b = a[1] > 1 ? 10 : 20;
+            var c = a[1] > 2 ? 10 : a[1] > 1 ? 30 : 40;
+            function f() {
+                var (d, e) = (4, 5);
+                (d, e) = (e, d);
+                d++;
+                e--;
+                return d * 10 + e;
+            }
+            var d = f();",
+        )?;
+
+        scope_eq(
+            &scope,
+            "a",
+            "Some(List(List([Algebra(1), Algebra(2), Algebra(3)])))",
+        );
+        scope_eq(&scope, "b", "Some(Algebra(10))");
+        scope_eq(&scope, "c", "Some(Algebra(30))");
+        scope_eq(&scope, "d", "Some(Algebra(63))");
+
+        assert!(eval_constraint("var b = 1 ? 2 : 3;").is_err());
+        assert!(eval_constraint(
+            "
+            function f() { var (a, b) = (1, 2, 3); return a; }
+            var a = f();"
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_circom2_anonymous_components() -> Result<()> {
+        let circuit = "
+            template Mul() {
+                signal input a;
+                signal input b;
+                signal output c;
+                c <== a * b;
+            }
+            template Sum(n) {
+                signal input in[n];
+                signal output out;
+                signal output count;
+                var acc = 0;
+                for (var i = 0; i < n; i+=1) {
+                    acc += in[i];
+                }
+                out <== acc;
+                count <-- n;
+            }
+            template T() {
+                signal input x[2];
+                signal output y;
+                signal output z;
+                signal m;
+                m <== Mul()(x[0], x[1]);
+                (y, z) <== Sum(3)([m, x[0], x[1]]);
+            }
+            component main = T();
+        ";
+        let (eval, _) = eval_witness_with_defer(
+            circuit,
+            vec![("main.x[0]".to_string(), 2), ("main.x[1]".to_string(), 3)],
+        )?;
+        signal_eq(&eval, "main.m", "main.m:Internal:Some(6)");
+        signal_eq(&eval, "main.y", "main.y:Output:Some(11)");
+        signal_eq(&eval, "main.z", "main.z:Output:Some(3)");
+        signal_eq(&eval, "main.anon$0.c", "main.anon$0.c:Output:Some(6)");
+        signal_eq(
+            &eval,
+            "main.anon$1.in[2]",
+            "main.anon$1.in[2]:PublicInput:Some(3)",
+        );
+
+        assert!(eval_constraint(
+            "
+            template Mul() { signal input a; signal input b; signal output c; c <== a * b; }
+            template T() { signal input x; signal output y; y <== Mul()(x); }
+            component main = T();",
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_circom2_log_and_assert() -> Result<()> {
+        let circuit = "
+            function double(x) {
+                return 2*x;
+            }
+            template T() {
+                signal input a;
+                signal output b;
+                log(\"a is\", a);
+                assert(a < 10);
+                double(a);
+                b <== a;
+            }
+            component main = T();
+        ";
+        eval_witness_with_defer(circuit, vec![("main.a".to_string(), 4)])?;
+        match eval_witness_with_defer(circuit, vec![("main.a".to_string(), 12)]) {
+            Err(Error::AssertFailed(_)) => {}
+            _ => panic!("assert should fail"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_assert_message_and_log_format() -> Result<()> {
+        let circuit = "
+            template T() {
+                signal input a;
+                signal output b;
+                var limit = 10;
+                log!(\"a = {}, {:?}, {{limit}}\", a, a);
+                assert!(a < limit, \"{:?} must be lower than {}\", a, limit);
+                b <== a;
+            }
+            component main = T();
+        ";
+        // the assertion does not add constraints
+        let (eval, _) = eval_constraint(circuit)?;
+        assert_eq!(1, eval.constraints.len());
+
+        eval_witness_with_defer(circuit, vec![("main.a".to_string(), 4)])?;
+        match eval_witness_with_defer(circuit, vec![("main.a".to_string(), 12)]) {
+            Err(Error::AssertFailed(message)) => {
+                assert_eq!("main.a = 12 must be lower than 10", message)
+            }
+            _ => panic!("assert should fail"),
+        }
+
+        let circuit = "
+            template T() { signal input a; log!(\"{} {}\", a); }
+            component main = T();";
+        match eval_witness_with_defer(circuit, vec![("main.a".to_string(), 1)]) {
+            Err(Error::InvalidParameter(_)) => {}
+            _ => panic!("missing format argument"),
+        }
+        Ok(())
+    }
 }
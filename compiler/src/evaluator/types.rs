@@ -1,3 +1,5 @@
+use std::fmt;
+
 use crate::types::{Signal, Signals};
 
 use super::algebra;
@@ -74,6 +76,28 @@ pub enum ReturnValue {
     List(List),
 }
 
+impl fmt::Display for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            List::Algebra(value) => write!(f, "{}", value),
+            List::List(values) => {
+                let values = values.iter().map(List::to_string).collect::<Vec<_>>();
+                write!(f, "[{}]", values.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ReturnValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReturnValue::Bool(value) => write!(f, "{}", value),
+            ReturnValue::Algebra(value) => write!(f, "{}", value),
+            ReturnValue::List(list) => write!(f, "{}", list),
+        }
+    }
+}
+
 impl ReturnValue {
     pub fn from_signal_name(full_name: &str, signals: &Signals) -> Result<ReturnValue> {
         match signals.get_by_name(full_name) {
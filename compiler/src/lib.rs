@@ -11,6 +11,7 @@ extern crate blake2_rfc;
 extern crate hex;
 extern crate serde;
 extern crate serde_cbor;
+extern crate serde_json;
 #[macro_use]
 extern crate serde_derive;
 #[macro_use]
@@ -18,9 +19,17 @@ extern crate log;
 extern crate fxhash;
 extern crate itertools;
 extern crate rand;
+extern crate toml;
 
 pub mod algebra;
+pub mod cache;
+pub mod debugger;
+pub mod diagnostics;
+pub mod diff;
 pub mod evaluator;
+pub mod lint;
 pub mod optimizer;
+pub mod profile;
+pub mod project;
 pub mod tester;
 pub mod types;
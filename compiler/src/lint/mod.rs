@@ -0,0 +1,306 @@
+mod uniqueness;
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::algebra::{AlgZero, SignalId};
+use crate::diagnostics::{emit, Diagnostic, MessageFormat, Severity};
+use crate::evaluator::{Assigned, Evaluator, Location, Mode, SignalSources};
+use crate::project::{Project, Result};
+use crate::types::{Constraints, Signals};
+use za_parser::ast::SignalType;
+
+pub use self::uniqueness::undetermined_signals;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lint {
+    // signal assigned with `<--` that is not in any constraint
+    UnconstrainedAssignment,
+    // output signal that is not in any constraint
+    UnconstrainedOutput,
+    // input signal that is not in any constraint, except the main public
+    //   inputs that are bound by the proof
+    UnconstrainedInput,
+    // `<==`, `==>` or `===` inside a `#[w]` statement, that is not generated
+    SkippedConstraint,
+}
+
+impl Lint {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnconstrainedAssignment => "unconstrained-assignment",
+            Lint::UnconstrainedOutput => "unconstrained-output",
+            Lint::UnconstrainedInput => "unconstrained-input",
+            Lint::SkippedConstraint => "skipped-constraint",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Warning {
+    pub lint: Lint,
+    pub location: Location,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, self.lint.name(), self.message.clone())
+            .with_primary(self.location.clone(), "")
+    }
+}
+
+/// Prints the warnings, with the lint name as code
+pub fn print_warnings(warnings: &[Warning], format: MessageFormat) {
+    let diagnostics = warnings.iter().map(Warning::diagnostic).collect::<Vec<_>>();
+    emit(&diagnostics, format);
+}
+
+/// Prints the signals that are not uniquely determined by the inputs
+pub fn print_undetermined(names: &[String], format: MessageFormat) {
+    let diagnostics = names
+        .iter()
+        .map(|name| {
+            let message = format!("signal `{}` is not uniquely determined by the inputs", name);
+            Diagnostic::new(Severity::Warning, "undetermined-signal", message)
+        })
+        .collect::<Vec<_>>();
+    emit(&diagnostics, format);
+}
+
+/// Generates the constraints of the project circuit and checks them
+pub fn lint_project(project: &Project) -> Result<Vec<Warning>> {
+    let mut eval = Evaluator::new(
+        Mode::GenConstraints,
+        Signals::default(),
+        Constraints::default(),
+    );
+    eval.sources = Some(SignalSources::default());
+    if let Err(err) = project.eval_file(&mut eval) {
+        project.report_error(&eval, &err);
+        return Err(err.into());
+    }
+    Ok(lint(&eval))
+}
+
+/// Compiles the project circuit and returns the full names of the signals that
+///   are not uniquely determined by the main inputs
+pub fn undetermined_project(project: &Project) -> Result<Vec<String>> {
+    let circuit = project.compile(false, false)?;
+    Ok(undetermined_signals(
+        &circuit.signals,
+        &circuit.constraints,
+        &circuit.substitutions,
+    )
+    .into_iter()
+    .map(|id| circuit.signals.get_by_id(id).unwrap().full_name.to_string())
+    .collect())
+}
+
+/// Checks the signals that are not in any of the constraints generated by an
+///   evaluator with `sources` set, the warnings are grouped by source location
+///   and sorted
+pub fn lint(eval: &Evaluator) -> Vec<Warning> {
+    let (signals, constraints) = (&eval.signals, &eval.constraints);
+    let sources = eval
+        .sources
+        .as_ref()
+        .expect("signal sources are not collected");
+    let mut constrained = HashSet::new();
+    for n in 0..constraints.len() {
+        let qeq = constraints.get(n);
+        for (signal, value) in qeq.a.iter().chain(&qeq.b).chain(&qeq.c) {
+            if !value.is_zero() {
+                constrained.insert(*signal);
+            }
+        }
+    }
+
+    // signal names by (file, location, lint)
+    type Group = (Lint, Location, Vec<String>);
+    let mut groups: BTreeMap<(String, usize, usize), Group> = BTreeMap::new();
+    let mut add = |lint: Lint, location: &Location, name: Option<String>| {
+        let key = (location.file.clone(), location.meta.start, lint as usize);
+        let names = &mut groups
+            .entry(key)
+            .or_insert_with(|| (lint, location.clone(), Vec::new()))
+            .2;
+        names.extend(name);
+    };
+    let name_of = |id: SignalId| signals.get_by_id(id).unwrap().full_name.to_string();
+
+    // signals assigned with `<--`
+    let mut reported = HashSet::new();
+    let mut arrays = HashSet::new();
+    for (assigned, location) in &sources.assignments {
+        let ids = match assigned {
+            Assigned::Signal(id) => vec![*id],
+            Assigned::Array(name) => {
+                if !arrays.insert((name, &location.file, location.meta.start)) {
+                    continue;
+                }
+                let prefix = format!("{}[", name);
+                (1..signals.len())
+                    .filter(|id| {
+                        let full_name = &signals.get_by_id(*id).unwrap().full_name.0;
+                        **full_name == *name || full_name.starts_with(&prefix)
+                    })
+                    .collect()
+            }
+        };
+        for id in ids {
+            if !constrained.contains(&id) && reported.insert(id) {
+                add(Lint::UnconstrainedAssignment, location, Some(name_of(id)));
+            }
+        }
+    }
+
+    // outputs and inputs
+    for id in 1..signals.len() {
+        if constrained.contains(&id) || reported.contains(&id) {
+            continue;
+        }
+        let signal = signals.get_by_id(id).unwrap();
+        let lint = match signal.xtype {
+            SignalType::Output => Lint::UnconstrainedOutput,
+            SignalType::PrivateInput => Lint::UnconstrainedInput,
+            SignalType::PublicInput if !signal.is_main_public_input() => Lint::UnconstrainedInput,
+            _ => continue,
+        };
+        if let Some(location) = eval.signal_declarations.get(&id) {
+            add(lint, location, Some(name_of(id)));
+        }
+    }
+
+    for location in &sources.skipped_constraints {
+        add(Lint::SkippedConstraint, location, None);
+    }
+
+    groups
+        .into_iter()
+        .map(|(_, (lint, location, names))| {
+            let names = format_names(&names);
+            let message = match lint {
+                Lint::UnconstrainedAssignment => {
+                    format!("{} assigned with `<--` but not constrained", names)
+                }
+                Lint::UnconstrainedOutput => format!("output {} is not constrained", names),
+                Lint::UnconstrainedInput => format!("input {} is not constrained", names),
+                Lint::SkippedConstraint => {
+                    "constraint inside a `#[w]` statement is not generated".to_string()
+                }
+            };
+            Warning {
+                lint,
+                location,
+                message,
+            }
+        })
+        .collect()
+}
+
+// the first names of a group of signals
+fn format_names(names: &[String]) -> String {
+    const SHOWN: usize = 3;
+    let shown = names
+        .iter()
+        .take(SHOWN)
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if names.len() > SHOWN {
+        format!("{} and {} more", shown, names.len() - SHOWN)
+    } else {
+        shown
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::Scope;
+
+    #[test]
+    fn test_lint() {
+        let code = "
+            template T() {
+                signal private input a;
+                signal private input unused;
+                signal input b;
+                signal input unused_public;
+                signal output c;
+                signal output d;
+                signal t;
+                signal u[2];
+                signal v[2];
+                t <-- a * b;
+                c <== a * b;
+                #[w] {
+                    u[0] <-- 1;
+                    u[1] <-- 2;
+                }
+                u[0] === 1;
+                #[w] for (var i = 0; i < 2; i += 1) {
+                    v[i] <-- i;
+                    c === a;
+                }
+            }
+            component main = T();
+        ";
+
+        let mut eval = Evaluator::new(
+            Mode::GenConstraints,
+            Signals::default(),
+            Constraints::default(),
+        );
+        eval.sources = Some(SignalSources::default());
+        let mut scope = Scope::new(true, None, "root".to_string());
+        eval.eval_inline(&mut scope, code).unwrap();
+
+        let warnings = lint(&eval)
+            .into_iter()
+            .map(|w| {
+                (
+                    w.lint,
+                    code[w.location.meta.start..].lines().next().unwrap().trim(),
+                    w.message,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                (
+                    Lint::UnconstrainedInput,
+                    "signal private input unused;",
+                    "input `main.unused` is not constrained".to_string()
+                ),
+                (
+                    Lint::UnconstrainedOutput,
+                    "signal output d;",
+                    "output `main.d` is not constrained".to_string()
+                ),
+                (
+                    Lint::UnconstrainedAssignment,
+                    "t <-- a * b;",
+                    "`main.t` assigned with `<--` but not constrained".to_string()
+                ),
+                (
+                    Lint::UnconstrainedAssignment,
+                    "u[1] <-- 2;",
+                    "`main.u[1]` assigned with `<--` but not constrained".to_string()
+                ),
+                (
+                    Lint::UnconstrainedAssignment,
+                    "v[i] <-- i;",
+                    "`main.v[0]`, `main.v[1]` assigned with `<--` but not constrained".to_string()
+                ),
+                (
+                    Lint::SkippedConstraint,
+                    "c === a;",
+                    "constraint inside a `#[w]` statement is not generated".to_string()
+                ),
+            ],
+            warnings
+        );
+    }
+}
@@ -0,0 +1,499 @@
+use std::collections::{HashMap, HashSet};
+
+use num_bigint::BigUint;
+use num_traits::Zero;
+use za_parser::ast::SignalType;
+
+use crate::algebra::{AlgZero, SignalId, FS, LC, QEQ, SIGNAL_ONE};
+use crate::optimizer::Substitutions;
+use crate::types::{Constraints, Signals};
+
+/// Signals that are not uniquely determined by the main inputs, that is, there
+///   can be two witnesses with the same inputs and different values for them.
+///
+/// The signals determined by the inputs are propagated through the constraints
+///   that are linear in a single unknown signal, or in a decomposition in bits
+///   of unknown boolean signals. Constraints `a*b+c=0` where `a` is known are
+///   solved by searching in both the `a=0` and `a!=0` cases. The analysis is
+///   conservative, a reported signal can still be determined by constraints
+///   that require a deeper reasoning.
+///
+/// `substitutions` are the signals removed by the optimizer, they are not in
+///   the constraints and are determined if all the signals of their
+///   substitution are.
+pub fn undetermined_signals(
+    signals: &Signals,
+    constraints: &Constraints,
+    substitutions: &Substitutions,
+) -> Vec<SignalId> {
+    let mut analysis = Analysis::new(signals.len(), constraints);
+    let mut queue = Vec::new();
+    analysis.set_known(SIGNAL_ONE, &mut queue);
+    for id in 1..signals.len() {
+        let signal = signals.get_by_id(id).unwrap();
+        if signal.is_main_input() && signal.xtype != SignalType::Output {
+            analysis.set_known(id, &mut queue);
+        }
+    }
+    analysis.propagate((0..analysis.constraints.len()).collect());
+    analysis.search();
+
+    // in reverse order, as `Substitutions::reconstruct` evaluates them
+    for (signal, value) in substitutions.0.iter().rev() {
+        analysis.known[*signal] = value.iter().all(|(s, _)| analysis.known[*s]);
+    }
+    (1..signals.len())
+        .filter(|id| !analysis.known[*id])
+        .collect()
+}
+
+// coefficient of an unknown signal in a linear constraint, `None` if it is not
+//   constant but known to be non-zero
+type Term = (SignalId, Option<FS>);
+
+struct Analysis {
+    constraints: Vec<QEQ>,
+
+    // constraints where each signal appears
+    occurrences: Vec<Vec<usize>>,
+
+    // signals constrained to be 0 or 1
+    boolean: Vec<bool>,
+
+    // constraints by the normalized `a` and `b` factors
+    factors: HashMap<String, Vec<usize>>,
+
+    known: Vec<bool>,
+
+    // signals set as known, to undo the search branches
+    trail: Vec<SignalId>,
+
+    // factors assumed to be zero (true) or non-zero (false)
+    assumptions: HashMap<String, bool>,
+}
+
+impl Analysis {
+    fn new(signal_count: usize, constraints: &Constraints) -> Self {
+        let constraints = (0..constraints.len())
+            .map(|n| constraints.get(n))
+            .collect::<Vec<_>>();
+
+        let mut occurrences = vec![Vec::new(); signal_count];
+        let mut boolean = vec![false; signal_count];
+        let mut factors: HashMap<String, Vec<usize>> = HashMap::new();
+        for (n, qeq) in constraints.iter().enumerate() {
+            let signals = qeq
+                .a
+                .iter()
+                .chain(&qeq.b)
+                .chain(&qeq.c)
+                .filter(|(signal, value)| *signal != SIGNAL_ONE && !value.is_zero())
+                .map(|(signal, _)| *signal)
+                .collect::<HashSet<_>>();
+            for signal in &signals {
+                occurrences[*signal].push(n);
+            }
+            if let Some(signal) = boolean_signal(qeq, &signals) {
+                boolean[signal] = true;
+            }
+            for factor in &[&qeq.a, &qeq.b] {
+                if constant(factor).is_none() && !factor.is_zero() {
+                    factors.entry(factor_key(factor)).or_default().push(n);
+                }
+            }
+        }
+
+        Analysis {
+            constraints,
+            occurrences,
+            boolean,
+            factors,
+            known: vec![false; signal_count],
+            trail: Vec::new(),
+            assumptions: HashMap::new(),
+        }
+    }
+
+    fn set_known(&mut self, signal: SignalId, queue: &mut Vec<usize>) {
+        if !self.known[signal] {
+            self.known[signal] = true;
+            self.trail.push(signal);
+            queue.extend(&self.occurrences[signal]);
+        }
+    }
+
+    // solves the queued constraints until no more signals are determined
+    fn propagate(&mut self, mut queue: Vec<usize>) {
+        while let Some(n) = queue.pop() {
+            for signal in self.solve(n) {
+                self.set_known(signal, &mut queue);
+            }
+        }
+    }
+
+    // splits the search in the zero and non-zero cases of the known factors
+    //   that block a constraint, the signals determined in both cases are known
+    fn search(&mut self) {
+        let mut tried = HashSet::new();
+        loop {
+            let mut progress = false;
+            for n in 0..self.constraints.len() {
+                let key = match self.split_factor(n) {
+                    Some(key) => key,
+                    None => continue,
+                };
+                if !tried.insert(key.clone()) {
+                    continue;
+                }
+                let queue = self.factors[&key].clone();
+                let when_zero = self.branch(&key, true, queue.clone());
+                let when_nonzero = self.branch(&key, false, queue);
+
+                let mut queue = Vec::new();
+                for signal in when_zero.intersection(&when_nonzero) {
+                    self.set_known(*signal, &mut queue);
+                }
+                if !queue.is_empty() {
+                    progress = true;
+                    self.propagate(queue);
+                }
+            }
+            if !progress {
+                break;
+            }
+            tried.clear();
+        }
+    }
+
+    fn branch(&mut self, key: &str, zero: bool, queue: Vec<usize>) -> HashSet<SignalId> {
+        self.assumptions.insert(key.to_string(), zero);
+        let mark = self.trail.len();
+        self.propagate(queue);
+        let determined = self.trail.drain(mark..).collect::<HashSet<_>>();
+        for signal in &determined {
+            self.known[*signal] = false;
+        }
+        self.assumptions.remove(key);
+        determined
+    }
+
+    // the signals determined by a constraint
+    fn solve(&self, n: usize) -> Vec<SignalId> {
+        let terms = match self.linearize(&self.constraints[n]) {
+            Some(terms) => terms,
+            None => return Vec::new(),
+        };
+        if terms.len() == 1 || self.is_bit_decomposition(&terms) {
+            terms.into_iter().map(|(signal, _)| signal).collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    // the unknown signals of a constraint that is linear in them
+    fn linearize(&self, qeq: &QEQ) -> Option<Vec<Term>> {
+        let unknown_c = self.unknowns(&qeq.c);
+        let constant_terms = |terms: Vec<(SignalId, FS)>| {
+            terms
+                .into_iter()
+                .map(|(signal, value)| (signal, Some(value)))
+                .collect::<Vec<_>>()
+        };
+
+        if self.is_zero(&qeq.a) || self.is_zero(&qeq.b) {
+            return Some(constant_terms(unknown_c));
+        }
+        let (unknown_a, unknown_b) = (self.unknowns(&qeq.a), self.unknowns(&qeq.b));
+        let (factor, unknown_other) = match (unknown_a.is_empty(), unknown_b.is_empty()) {
+            (true, true) => return Some(constant_terms(unknown_c)),
+            (true, false) => (&qeq.a, unknown_b),
+            (false, true) => (&qeq.b, unknown_a),
+            (false, false) => return None,
+        };
+
+        if let Some(k) = constant(factor) {
+            let mut terms = unknown_c.into_iter().collect::<LC>();
+            for (signal, value) in unknown_other {
+                let value = &value * &k;
+                terms.set(signal, |v| v.map_or(value.clone(), |v| v + &value));
+            }
+            let terms = terms
+                .iter()
+                .filter(|(_, v)| !v.is_zero())
+                .cloned()
+                .collect();
+            Some(constant_terms(terms))
+        } else if self.is_nonzero(factor) {
+            if unknown_other
+                .iter()
+                .any(|(signal, _)| unknown_c.iter().any(|(s, _)| s == signal))
+            {
+                return None;
+            }
+            let mut terms = unknown_other
+                .into_iter()
+                .map(|(signal, _)| (signal, None))
+                .collect::<Vec<_>>();
+            terms.extend(constant_terms(unknown_c));
+            Some(terms)
+        } else {
+            None
+        }
+    }
+
+    // boolean signals with constant coefficients such that different values
+    //   of the bits always give different sums
+    fn is_bit_decomposition(&self, terms: &[Term]) -> bool {
+        let mut coefs = Vec::new();
+        for (signal, value) in terms {
+            match value {
+                Some(value) if self.boolean[*signal] => coefs.push(if value.is_neg() {
+                    (-value).into_repr()
+                } else {
+                    value.clone().into_repr()
+                }),
+                _ => return false,
+            }
+        }
+        // superincreasing coefficients, whose sum is smaller than p so the
+        //   sums do not wrap around the field
+        coefs.sort();
+        let mut sum = BigUint::zero();
+        for coef in coefs {
+            if coef <= sum {
+                return false;
+            }
+            sum += coef;
+        }
+        &sum < FS::field()
+    }
+
+    // the known factor of a blocked constraint, not yet assumed
+    fn split_factor(&self, n: usize) -> Option<String> {
+        let qeq = &self.constraints[n];
+        if self.unknowns(&qeq.c).is_empty()
+            && self.unknowns(&qeq.a).is_empty()
+            && self.unknowns(&qeq.b).is_empty()
+        {
+            return None;
+        }
+        for (factor, other) in &[(&qeq.a, &qeq.b), (&qeq.b, &qeq.a)] {
+            if self.unknowns(factor).is_empty()
+                && !self.unknowns(other).is_empty()
+                && constant(factor).is_none()
+                && !factor.is_zero()
+            {
+                let key = factor_key(factor);
+                if !self.assumptions.contains_key(&key) {
+                    return Some(key);
+                }
+            }
+        }
+        None
+    }
+
+    fn unknowns(&self, lc: &LC) -> Vec<(SignalId, FS)> {
+        lc.iter()
+            .filter(|(signal, value)| !self.known[*signal] && !value.is_zero())
+            .cloned()
+            .collect()
+    }
+
+    fn is_zero(&self, lc: &LC) -> bool {
+        lc.is_zero() || self.assumptions.get(&factor_key(lc)) == Some(&true)
+    }
+
+    fn is_nonzero(&self, lc: &LC) -> bool {
+        constant(lc).is_some() || self.assumptions.get(&factor_key(lc)) == Some(&false)
+    }
+}
+
+// the value of a non-zero constant linear combination
+fn constant(lc: &LC) -> Option<FS> {
+    let mut value = FS::zero();
+    for (signal, coef) in lc {
+        if *signal == SIGNAL_ONE {
+            value += coef;
+        } else if !coef.is_zero() {
+            return None;
+        }
+    }
+    if value.is_zero() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+// linear combinations equal up to a constant factor have the same key
+fn factor_key(lc: &LC) -> String {
+    let mut terms = lc
+        .iter()
+        .filter(|(_, value)| !value.is_zero())
+        .collect::<Vec<_>>();
+    terms.sort_by_key(|(signal, _)| *signal);
+    match terms.first() {
+        Some((_, first)) => {
+            let scale = (&FS::one() / first).unwrap();
+            terms
+                .iter()
+                .map(|(signal, value)| format!("{}:{:?}", signal, &scale * value))
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+        None => String::new(),
+    }
+}
+
+// the signal `x` of a `(αx+a0)*(βx+b0)+γ = 0` constraint with roots 0 and 1
+fn boolean_signal(qeq: &QEQ, signals: &HashSet<SignalId>) -> Option<SignalId> {
+    if signals.len() != 1 {
+        return None;
+    }
+    let x = *signals.iter().next().unwrap();
+    let coef = |lc: &LC, signal| lc.get(signal).cloned().unwrap_or_else(FS::zero);
+
+    if !coef(&qeq.c, x).is_zero() {
+        return None;
+    }
+    let (alpha, a0) = (coef(&qeq.a, x), coef(&qeq.a, SIGNAL_ONE));
+    let (beta, b0) = (coef(&qeq.b, x), coef(&qeq.b, SIGNAL_ONE));
+    let gamma = coef(&qeq.c, SIGNAL_ONE);
+    if alpha.is_zero() || beta.is_zero() {
+        return None;
+    }
+
+    let at_zero = &(&a0 * &b0) + &gamma;
+    let at_one = &(&(&alpha + &a0) * &(&beta + &b0)) + &gamma;
+    if at_zero.is_zero() && at_one.is_zero() {
+        Some(x)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::{Evaluator, Mode, Scope};
+    use crate::optimizer;
+
+    fn undetermined(code: &str, optimize: bool) -> Vec<String> {
+        let mut eval = Evaluator::new(
+            Mode::GenConstraints,
+            Signals::default(),
+            Constraints::default(),
+        );
+        let mut scope = Scope::new(true, None, "root".to_string());
+        eval.eval_inline(&mut scope, code).unwrap();
+
+        let ids = if optimize {
+            let irreductible = eval.signals.main_input_ids();
+            let (constraints, substitutions, _) =
+                optimizer::optimize(&eval.constraints, &irreductible, optimizer::Level::O2);
+            undetermined_signals(&eval.signals, &constraints, &substitutions)
+        } else {
+            undetermined_signals(&eval.signals, &eval.constraints, &Substitutions::default())
+        };
+        ids.into_iter()
+            .map(|id| eval.signals.get_by_id(id).unwrap().full_name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_uniqueness() {
+        let is_zero = "
+            template IsZero() {
+                signal input in;
+                signal output out;
+                signal inv;
+                #[w] if (in!=0) { inv <-- 1/in; } else { inv <-- 0; }
+                out <== -in*inv +1;
+                in*out === 0;
+            }
+            template Num2Bits(n) {
+                signal input in;
+                signal output out[n];
+                var lc1=0;
+                for (var i = 0; i<n; i++) {
+                    #[w] out[i] <-- (in >> i) & 1;
+                    out[i] * (out[i] -1 ) === 0;
+                    lc1 += out[i] * 2**i;
+                }
+                lc1 === in;
+            }
+        ";
+        let code = format!(
+            "{}
+            template Main() {{
+                signal private input a;
+                signal output z;
+                signal output bits[4];
+                signal output sq;
+                component iz = IsZero();
+                iz.in <== a;
+                z <== iz.out;
+                component n2b = Num2Bits(4);
+                n2b.in <== a;
+                for (var i = 0; i < 4; i++) {{
+                    bits[i] <== n2b.out[i];
+                }}
+                sq <== a * a;
+            }}
+            component main = Main();",
+            is_zero
+        );
+        assert_eq!(vec!["main.iz.inv"], undetermined(&code, false));
+        assert_eq!(vec!["main.iz.inv"], undetermined(&code, true));
+
+        let code = "
+            template Main() {
+                signal private input a;
+                signal t;
+                signal output b;
+                signal output c;
+                #[w] t <-- a * 2;
+                b <== t * t;
+                c * c === a;
+            }
+            component main = Main();";
+        assert_eq!(
+            vec!["main.b", "main.c", "main.t"],
+            undetermined(code, false)
+        );
+
+        // 2^254-1 > p, so the bits of a value have an alias
+        let code = format!(
+            "{}
+            template Main() {{
+                signal private input a;
+                component n2b = Num2Bits(254);
+                n2b.in <== a;
+            }}
+            component main = Main();",
+            is_zero
+        );
+        assert_eq!(254, undetermined(&code, false).len());
+        // the bit removed by the optimizer depends on the others
+        let names = undetermined(&code, true);
+        assert_eq!(254, names.len());
+        assert!(names.iter().all(|name| name.starts_with("main.n2b.out[")));
+    }
+
+    #[test]
+    fn test_uniqueness_removed_signals() {
+        let code = "
+            template Main() {
+                signal private input a;
+                signal u;
+                signal output s;
+                #[w] u <-- 3;
+                u * u === a;
+                s <== u + a;
+            }
+            component main = Main();";
+        assert_eq!(vec!["main.s", "main.u"], undetermined(code, false));
+        // the linear constraint is removed with one of `u` and `s`
+        assert_eq!(vec!["main.s", "main.u"], undetermined(code, true));
+    }
+}
@@ -1,186 +1,253 @@
-use std::collections::HashMap;
+mod report;
+mod substitutions;
+
+use std::collections::{HashMap, HashSet};
 
 use crate::algebra::AlgZero;
 use crate::algebra::SignalId;
 use crate::algebra::{FS, LC, QEQ, SIGNAL_ONE};
 use crate::types::Constraints;
 
-#[derive(Clone)]
-struct Change {
-    replace_s: SignalId,
-    replace_f: FS,
-}
+pub use self::report::{ComponentCount, KindCount, Level, PassCount, Report, Stats};
+pub use self::substitutions::Substitutions;
 
 pub fn optimize(
     constraints: &Constraints,
     irreductible_signals: &[SignalId],
-) -> (Constraints, Vec<SignalId>) {
-    let (constraints, r1) = optimize_n(&constraints, irreductible_signals);
-    println!("Optimize L1 {} {}", constraints.len(), r1.len());
+    level: Level,
+) -> (Constraints, Substitutions, Stats) {
+    let (constraints, substitutions, stats) = optimize_n(constraints, irreductible_signals, level);
+    info!(
+        "Optimize {}: passes={} folded={} trivial={} duplicated={} substituted={}",
+        level, stats.passes, stats.folded, stats.trivial, stats.duplicated, stats.substituted
+    );
 
-    (constraints, r1)
+    (constraints, substitutions, stats)
 }
 
+/// Removes the linear constraints by substituting one of their signals that
+///   is not irreductible in all the other constraints, folds the constant
+///   factors and removes the trivially satisfied and the duplicated
+///   constraints, until there is nothing left to optimize. Returns the
+///   optimized constraints and how to compute the removed signals.
+///
+/// With `Level::O1` only the linear constraints with up to two signals are
+///   removed, and with `Level::O0` the constraints are returned as they are.
 pub fn optimize_n(
     constraints: &Constraints,
     irreductible_signals: &[SignalId],
-) -> (Constraints, Vec<SignalId>) {
-    let mut replaces = HashMap::<SignalId, Change>::new();
-    let mut rmconstraints = Vec::new();
-
-    let mut type1 = 0;
-    let mut type2 = 0;
-    let mut type3 = 0;
-
-    // optimize constraints
-    for n_c in 0..constraints.len() {
-        let mut cnstr = constraints.get(n_c);
-
-        // Rewrite to only-C if possible
-        //   rewrite [a][c2SOne]+[c3s3] :> [][]+[ c2 a c3s3 ]
-        //   rewrite [c1SOne][b]+[c3s3] :> [][]+[ c1 b c3s3 ]
-
-        if cnstr.a.0.len() == 1 && cnstr.a.0[0].0 == SIGNAL_ONE {
-            cnstr = QEQ::new(
-                LC::zero(),
-                LC::zero(),
-                &cnstr.c + &(&cnstr.b * &cnstr.a.0[0].1),
-            );
-            type1 += 1;
-        } else if cnstr.b.0.len() == 1 && cnstr.b.0[0].0 == SIGNAL_ONE {
-            cnstr = QEQ::new(
-                LC::zero(),
-                LC::zero(),
-                &cnstr.c + &(&cnstr.a * &cnstr.b.0[0].1),
-            );
-            type1 += 1;
+    level: Level,
+) -> (Constraints, Substitutions, Stats) {
+    let mut optimizer = Optimizer::new(constraints, irreductible_signals);
+
+    // first the constraints with up to two signals, that only rename a
+    //   signal or set it to a constant, so the substitutions do not add
+    //   terms to the other constraints
+    let max_signals: &[usize] = match level {
+        Level::O0 => &[],
+        Level::O1 => &[2],
+        Level::O2 => &[2, usize::MAX],
+    };
+    while !max_signals.is_empty() {
+        optimizer.start_pass();
+        let mut changed = false;
+        for max_signals in max_signals {
+            for n in 0..optimizer.constraints.len() {
+                changed |= optimizer.optimize_constraint(n, *max_signals);
+            }
+        }
+        changed |= optimizer.remove_duplicates();
+        optimizer.end_pass();
+        if !changed {
+            break;
+        }
+    }
+
+    let mut opt_cons = Constraints::default();
+    for (n, qeq) in optimizer.constraints.into_iter().enumerate() {
+        if let Some(qeq) = qeq {
+            opt_cons.push_from(qeq, constraints, n);
         }
+    }
+    (
+        opt_cons,
+        Substitutions(optimizer.substitutions),
+        optimizer.stats,
+    )
+}
+
+struct Optimizer {
+    // the removed constraints are None, the others keep their index
+    constraints: Vec<Option<QEQ>>,
+    irreductible_signals: HashSet<SignalId>,
+    // constraints where each signal may appear
+    uses: HashMap<SignalId, Vec<usize>>,
+    // the removed signals with their value, in terms of the signals that are
+    //   left when they are removed
+    substitutions: Vec<(SignalId, LC)>,
+    // counters of the current pass, added to `stats` when it ends
+    pass: PassCount,
+    stats: Stats,
+}
 
-        // Remove constrain
-        //   a) [][]+[c1S1+c2S2] :>  search: S1 replace: c2/c1 S1 iff S1 is not irreductuble
-        //   b) [][]+[c1S1+c2S2] :>  search: S2 replace: c1/c2 S2 iff S2 is not irreductuble
-
-        if cnstr.a.0.is_empty() && cnstr.b.0.is_empty() && cnstr.c.0.len() == 2 {
-            let first = &cnstr.c.0[0];
-            let second = &cnstr.c.0[1];
-
-            let first_is_irreductible = irreductible_signals.iter().any(|&x| x == first.0);
-            let second_is_irreductible = irreductible_signals.iter().any(|&x| x == second.0);
-
-            let (search, replace) = match (first_is_irreductible, second_is_irreductible) {
-                (false, true) => (first, second),
-                (true, false) => (second, first),
-                (false, false) => {
-                    if first.0 > second.0 {
-                        (first, second)
-                    } else {
-                        (second, first)
+impl Optimizer {
+    fn new(constraints: &Constraints, irreductible_signals: &[SignalId]) -> Self {
+        let mut uses = HashMap::<SignalId, Vec<usize>>::new();
+        let constraints = (0..constraints.len())
+            .map(|n| {
+                let qeq = constraints.get(n);
+                for (signal, _) in qeq.a.iter().chain(&qeq.b).chain(&qeq.c) {
+                    let indexes = uses.entry(*signal).or_default();
+                    if indexes.last() != Some(&n) {
+                        indexes.push(n);
                     }
                 }
-                _ => continue,
-            };
+                Some(qeq)
+            })
+            .collect();
+
+        Optimizer {
+            constraints,
+            irreductible_signals: irreductible_signals.iter().copied().collect(),
+            uses,
+            substitutions: Vec::new(),
+            pass: PassCount::default(),
+            stats: Stats::default(),
+        }
+    }
 
-            let (search_s, mut replace_s, mut replace_f) =
-                (search.0, replace.0, -&(&replace.1 / &search.1).unwrap());
+    fn remaining(&self) -> usize {
+        self.constraints.iter().filter(|qeq| qeq.is_some()).count()
+    }
 
-            if replaces.get(&search_s).is_none() {
-                while let Some(v) = replaces.get(&replace_s.clone()) {
-                    replace_s = v.replace_s;
-                    replace_f = &replace_f * &v.replace_f;
-                    type3 += 1;
-                }
+    fn start_pass(&mut self) {
+        self.pass = PassCount {
+            constraints_before: self.remaining(),
+            ..PassCount::default()
+        };
+    }
 
-                replaces.insert(
-                    search_s,
-                    Change {
-                        replace_s,
-                        replace_f,
-                    },
-                );
+    fn end_pass(&mut self) {
+        let mut pass = std::mem::take(&mut self.pass);
+        pass.constraints_after = self.remaining();
+        self.stats.passes += 1;
+        self.stats.folded += pass.folded;
+        self.stats.trivial += pass.trivial;
+        self.stats.duplicated += pass.duplicated;
+        self.stats.substituted += pass.substituted;
+        self.stats.pass_counts.push(pass);
+    }
 
-                rmconstraints.push(n_c);
+    // returns if the constraint has been modified or removed, only the linear
+    //   constraints with up to `max_signals` signals are removed
+    fn optimize_constraint(&mut self, n: usize, max_signals: usize) -> bool {
+        let qeq = match &mut self.constraints[n] {
+            Some(qeq) => qeq,
+            None => return false,
+        };
+
+        // rewrite to only-C if possible
+        //   [c1SOne][b]+[c] :> [][]+[c + c1 b]
+        //   [a][c2SOne]+[c] :> [][]+[c + c2 a]
+        let mut changed = false;
+        if !qeq.a.is_empty() || !qeq.b.is_empty() {
+            let product = if let Some(k) = constant(&qeq.a) {
+                Some(&qeq.b * &k)
+            } else if let Some(k) = constant(&qeq.b) {
+                Some(&qeq.a * &k)
+            } else {
+                None
+            };
+            if let Some(product) = product {
+                *qeq = QEQ::new(LC::zero(), LC::zero(), &qeq.c + &product);
+                self.pass.folded += 1;
+                changed = true;
             }
         }
+
+        if qeq.is_zero() {
+            self.constraints[n] = None;
+            self.pass.trivial += 1;
+            return true;
+        }
+
+        // remove [][]+[c1S1+...+cnSn] by replacing the greatest signal that is not
+        //   irreductible with -(c1S1+...+cnSn - ciSi)/ci
+        let signals = qeq.c.iter().filter(|(s, _)| *s != SIGNAL_ONE).count();
+        if !qeq.a.is_empty() || !qeq.b.is_empty() || signals > max_signals {
+            return changed;
+        }
+        let irreductible_signals = &self.irreductible_signals;
+        let search = qeq
+            .c
+            .iter()
+            .rev()
+            .find(|(s, _)| *s != SIGNAL_ONE && !irreductible_signals.contains(s));
+
+        if let Some((signal, coef)) = search.cloned() {
+            let mut value = qeq.c.clone();
+            value.rm(signal);
+            let value = &value * &-&(&FS::one() / &coef).unwrap();
+
+            self.constraints[n] = None;
+            self.substitute(signal, &value);
+            self.substitutions.push((signal, value));
+            self.pass.substituted += 1;
+            return true;
+        }
+
+        changed
     }
 
-    // fix replaces
-    //
-    // if can happen this case:
-    //    [9868] :> [287] (1)
-    //    [287] :> [30]   (2)
-    //
-    // this means that signal 287 will be removed in (2) but is still used in (1)
-    // even more, this should be optimized into
-    //
-    //    [9868] :> [30]
-    //
-    // so, now let's reduce the graph
-    // TODO: optimize
-
-    let keys: Vec<SignalId> = replaces.keys().copied().collect();
-    let mut any_processed = true;
-    while any_processed {
-        any_processed = false;
-        keys.iter().for_each(|s| {
-            let mut remove = None;
-            if let Some(r) = replaces.get(s) {
-                if let Some(r2) = replaces.get(&r.replace_s) {
-                    // now we are in the case [s] :> f1*[r], [r] :> f2*[r2]
-                    // update [s] :> f1*[r] to [s] :> f1*f2*[r2]
-                    remove = Some(r2.clone());
+    // replaces the signal in all the constraints where it appears
+    fn substitute(&mut self, signal: SignalId, value: &LC) {
+        for n in self.uses.remove(&signal).unwrap_or_default() {
+            let qeq = match &mut self.constraints[n] {
+                Some(qeq) => qeq,
+                None => continue,
+            };
+            let mut found = false;
+            for lc in [&mut qeq.a, &mut qeq.b, &mut qeq.c].iter_mut() {
+                if let Some(k) = lc.get(signal).cloned() {
+                    lc.rm(signal);
+                    **lc = &**lc + &(value * &k);
+                    found = true;
                 }
             }
-            if let Some(remove_r) = remove {
-                if let Some(r) = replaces.get_mut(s) {
-                    type2 += 1;
-                    *r = Change {
-                        replace_s: remove_r.replace_s,
-                        replace_f: &r.replace_f * &remove_r.replace_f,
-                    };
+            if found {
+                for (s, _) in value {
+                    let indexes = self.uses.entry(*s).or_default();
+                    if indexes.last() != Some(&n) {
+                        indexes.push(n);
+                    }
                 }
-                any_processed = true;
             }
-        });
+        }
     }
 
-    // now update constraints
-    let mut opt_cons = Constraints::default();
-    let mut rm_index = 0;
-
-    for n_c in 0..constraints.len() {
-        if rm_index < rmconstraints.len() && rmconstraints[rm_index] == n_c {
-            rm_index += 1;
-            continue;
-        }
-        let mut con = constraints.get(n_c);
-        for lcelem in con.a.0.iter_mut() {
-            if let Some(v) = replaces.get(&lcelem.0) {
-                *lcelem = (v.replace_s, &lcelem.1 * &v.replace_f);
-            }
-        }
-        for lcelem in con.b.0.iter_mut() {
-            if let Some(v) = replaces.get(&lcelem.0) {
-                *lcelem = (v.replace_s, &lcelem.1 * &v.replace_f);
-            }
-        }
-        for lcelem in con.c.0.iter_mut() {
-            if let Some(v) = replaces.get(&lcelem.0) {
-                *lcelem = (v.replace_s, &lcelem.1 * &v.replace_f);
+    fn remove_duplicates(&mut self) -> bool {
+        let mut seen = HashSet::new();
+        let mut changed = false;
+        for constraint in self.constraints.iter_mut() {
+            if let Some(qeq) = constraint {
+                if !seen.insert(qeq.clone()) {
+                    *constraint = None;
+                    self.pass.duplicated += 1;
+                    changed = true;
+                }
             }
         }
-        opt_cons.push(con, None);
+        changed
     }
+}
 
-    let mut removed_signals = Vec::with_capacity(replaces.len());
-    replaces
-        .into_iter()
-        .for_each(|(k, _)| removed_signals.push(k));
-
-    removed_signals.sort();
-
-    info!("type1={} type2={} type3={}", type1, type2, type3);
-
-    (opt_cons, removed_signals)
+// the value of a linear combination without signals
+fn constant(lc: &LC) -> Option<FS> {
+    match lc.terms() {
+        [] => Some(FS::zero()),
+        [(SIGNAL_ONE, k)] => Some(k.clone()),
+        _ => None,
+    }
 }
 
 #[test]
@@ -216,7 +283,7 @@ fn test_optimize_eq() {
     cons.push(qeq1, None);
     cons.push(qeq2, None);
     cons.push(qeq3, None);
-    let (opt_cons, removed_signals) = optimize_n(&cons, &[sin, sout]);
+    let (opt_cons, substitutions, _) = optimize_n(&cons, &[sin, sout], Level::O2);
 
     let qeq_optimized = QEQ::new(
         LC::zero(),
@@ -224,10 +291,141 @@ fn test_optimize_eq() {
         &LC::from_signal(sout, FS::one()) + &LC::from_signal(sin, -&FS::from(4)),
     );
 
-    assert_eq!([st, sk].to_vec(), removed_signals);
+    assert_eq!([st, sk].to_vec(), substitutions.removed_signals());
     assert_eq!(1, opt_cons.len());
     assert_eq!(
         format!("{:?}", qeq_optimized),
         format!("{:?}", opt_cons.get(0))
     );
 }
+
+#[test]
+fn test_optimize_linear() {
+    use crate::algebra::Value;
+    use crate::evaluator::{Evaluator, Mode, Scope};
+    use crate::types::Signals;
+
+    let code = "
+        template T() {
+            signal private input a;
+            signal input b;
+            signal output c;
+            signal x;
+            signal y;
+            signal z;
+            x <== a + b;
+            y <== 3*x + 2*a - b + 7;
+            z <== x * y;
+            c <== z + y;
+        }
+        component main = T();";
+
+    let eval = |mode| {
+        let witness = mode == Mode::GenWitness;
+        let mut eval = Evaluator::new(mode, Signals::default(), Constraints::default());
+        if witness {
+            eval.set_deferred_value("main.a".to_string(), Value::from(2));
+            eval.set_deferred_value("main.b".to_string(), Value::from(5));
+        }
+        let mut scope = Scope::new(true, None, "root".to_string());
+        eval.eval_inline(&mut scope, code).unwrap();
+        eval
+    };
+    let (constraints, witness) = (eval(Mode::GenConstraints), eval(Mode::GenWitness));
+
+    // a duplicated and a trivially satisfied constraint
+    let mut cons = Constraints::default();
+    for n in 0..constraints.constraints.len() {
+        cons.push(constraints.constraints.get(n), None);
+    }
+    let quadratic = (0..cons.len()).find(|n| !cons.get(*n).a.is_empty());
+    cons.push(cons.get(quadratic.unwrap()), None);
+    cons.push(
+        QEQ::new(LC::zero(), LC::from_signal(1, FS::one()), LC::zero()),
+        None,
+    );
+
+    let irreductible = constraints.signals.main_input_ids();
+    let (opt_cons, substitutions, stats) = optimize_n(&cons, &irreductible, Level::O2);
+
+    let name = |id| {
+        constraints
+            .signals
+            .get_by_id(id)
+            .unwrap()
+            .full_name
+            .to_string()
+    };
+    assert_eq!(
+        vec!["main.x", "main.y", "main.z"],
+        substitutions
+            .removed_signals()
+            .into_iter()
+            .map(name)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(1, opt_cons.len());
+    opt_cons.satisfies_with_signals(&witness.signals).unwrap();
+
+    let report = Report::new(
+        Level::O2,
+        &stats,
+        cons.len(),
+        opt_cons.len(),
+        &constraints.signals,
+        &substitutions,
+        std::time::Duration::from_millis(2),
+    );
+    assert_eq!(
+        (6, 1),
+        (report.constraints_before, report.constraints_after)
+    );
+    assert_eq!(
+        vec![("substituted", 3), ("trivial", 1), ("duplicated", 1)],
+        report.kinds[..3]
+            .iter()
+            .map(|k| (k.kind.as_str(), k.count))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![(6, 1, 3, 1, 1), (1, 1, 0, 0, 0)],
+        report
+            .pass_counts
+            .iter()
+            .map(|p| (
+                p.constraints_before,
+                p.constraints_after,
+                p.substituted,
+                p.trivial,
+                p.duplicated
+            ))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!("main", report.components[0].component);
+    assert_eq!(3, report.components[0].removed_signals);
+    assert!(report
+        .to_json()
+        .starts_with(r#"{"level":"O2","passes":2,"constraints_before":6,"constraints_after":1,"#));
+
+    // only the duplicated and trivial constraints are removed, or none
+    assert_eq!(4, optimize_n(&cons, &irreductible, Level::O1).0.len());
+    assert_eq!(6, optimize_n(&cons, &irreductible, Level::O0).0.len());
+
+    // the full witness from the values of the signals that are left
+    let reduced = substitutions.reduce_witness(&witness.signals).unwrap();
+    assert_eq!(witness.signals.len() - 3, reduced.len());
+    let mut signals = constraints.signals;
+    substitutions
+        .expand_witness(&mut signals, &reduced)
+        .unwrap();
+    for id in 1..signals.len() {
+        assert_eq!(
+            format!("{:?}", witness.signals.get_by_id(id).unwrap().value),
+            format!("{:?}", signals.get_by_id(id).unwrap().value)
+        );
+    }
+    cons.satisfies_with_signals(&signals).unwrap();
+    assert!(substitutions
+        .expand_witness(&mut signals, &reduced[1..])
+        .is_err());
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use super::Substitutions;
+use crate::types::Signals;
+
+/// How much the constraints are optimized
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Level {
+    // the constraints as generated
+    O0,
+    // only the linear constraints that rename a signal or set it to a constant
+    O1,
+    // all the linear constraints
+    O2,
+}
+
+// the constraints that the optimizer removed before the levels were added
+impl Default for Level {
+    fn default() -> Self {
+        Level::O1
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "0" | "O0" => Ok(Level::O0),
+            "1" | "O1" => Ok(Level::O1),
+            "2" | "O2" => Ok(Level::O2),
+            _ => Err(format!("unknown optimization level '{}', use 0, 1 or 2", s)),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Counters of the optimizer
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub passes: usize,
+    // constraints rewritten to only-C by folding a constant factor
+    pub folded: usize,
+    // constraints removed because they always hold
+    pub trivial: usize,
+    // constraints removed because they are repeated
+    pub duplicated: usize,
+    // linear constraints removed by substituting one of their signals
+    pub substituted: usize,
+    pub pass_counts: Vec<PassCount>,
+}
+
+/// Counters of an optimizer pass, with the constraints before and after it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PassCount {
+    pub constraints_before: usize,
+    pub constraints_after: usize,
+    pub substituted: usize,
+    pub trivial: usize,
+    pub duplicated: usize,
+    pub folded: usize,
+}
+
+/// What the optimizer did, to track the circuit size
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub level: Level,
+    pub passes: usize,
+    pub constraints_before: usize,
+    pub constraints_after: usize,
+    // removed constraints by kind, and the folded ones that are rewritten
+    pub kinds: Vec<KindCount>,
+    // the constraints before and after each pass, with the kinds applied
+    pub pass_counts: Vec<PassCount>,
+    // removed signals by component path, the ones with more first
+    pub components: Vec<ComponentCount>,
+    pub time_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KindCount {
+    pub kind: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentCount {
+    pub component: String,
+    pub removed_signals: usize,
+}
+
+impl Report {
+    pub fn new(
+        level: Level,
+        stats: &Stats,
+        constraints_before: usize,
+        constraints_after: usize,
+        signals: &Signals,
+        substitutions: &Substitutions,
+        time: Duration,
+    ) -> Self {
+        let kinds = [
+            ("substituted", stats.substituted),
+            ("trivial", stats.trivial),
+            ("duplicated", stats.duplicated),
+            ("folded", stats.folded),
+        ]
+        .iter()
+        .map(|(kind, count)| KindCount {
+            kind: kind.to_string(),
+            count: *count,
+        })
+        .collect();
+
+        let mut by_component = HashMap::<String, usize>::new();
+        for (signal, _) in &substitutions.0 {
+            let name = signals.get_by_id(*signal).unwrap().full_name.to_string();
+            let component = name.rfind('.').map_or("", |p| &name[..p]);
+            *by_component.entry(component.to_string()).or_default() += 1;
+        }
+        let mut components = by_component
+            .into_iter()
+            .map(|(component, removed_signals)| ComponentCount {
+                component,
+                removed_signals,
+            })
+            .collect::<Vec<_>>();
+        components.sort_by(|a, b| {
+            b.removed_signals
+                .cmp(&a.removed_signals)
+                .then_with(|| a.component.cmp(&b.component))
+        });
+
+        Report {
+            level,
+            passes: stats.passes,
+            constraints_before,
+            constraints_after,
+            kinds,
+            pass_counts: stats.pass_counts.clone(),
+            components,
+            time_ms: time.as_secs_f64() * 1000.0,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "optimization {}: {} -> {} constraints, {} passes in {:.3} ms",
+            self.level, self.constraints_before, self.constraints_after, self.passes, self.time_ms
+        )?;
+        writeln!(f, "  {:<40} {:>10}", "kind", "constraints")?;
+        for kind in &self.kinds {
+            writeln!(f, "  {:<40} {:>10}", kind.kind, kind.count)?;
+        }
+        writeln!(
+            f,
+            "  {:<6} {:>10} {:>10} {:>12} {:>10} {:>10} {:>10}",
+            "pass", "before", "after", "substituted", "trivial", "duplicated", "folded"
+        )?;
+        for (n, pass) in self.pass_counts.iter().enumerate() {
+            writeln!(
+                f,
+                "  {:<6} {:>10} {:>10} {:>12} {:>10} {:>10} {:>10}",
+                n + 1,
+                pass.constraints_before,
+                pass.constraints_after,
+                pass.substituted,
+                pass.trivial,
+                pass.duplicated,
+                pass.folded
+            )?;
+        }
+        if !self.components.is_empty() {
+            writeln!(f, "  {:<40} {:>10}", "component", "removed signals")?;
+            for component in &self.components {
+                writeln!(
+                    f,
+                    "  {:<40} {:>10}",
+                    component.component, component.removed_signals
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
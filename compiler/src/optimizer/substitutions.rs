@@ -0,0 +1,86 @@
+use crate::algebra::{SignalId, Value, FS, LC};
+use crate::types::Signals;
+
+/// The signals removed by the optimizer, each one with its value as a linear
+///   combination of the signals that were left when it was removed, so they
+///   are evaluated in reverse order
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Substitutions(pub Vec<(SignalId, LC)>);
+
+impl Substitutions {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Sorted removed signals, the `ignore_signals` of the prover
+    pub fn removed_signals(&self) -> Vec<SignalId> {
+        let mut removed: Vec<SignalId> = self.0.iter().map(|(s, _)| *s).collect();
+        removed.sort();
+        removed
+    }
+
+    /// Sets the value of the removed signals from the values of the others
+    pub fn reconstruct(&self, signals: &mut Signals) -> Result<(), String> {
+        for (signal, value) in self.0.iter().rev() {
+            let value = signals.eval_lc(value)?;
+            signals.update(*signal, Value::FieldScalar(value));
+        }
+        Ok(())
+    }
+
+    /// The values of the signals that are not removed, in order and starting
+    ///   with `one`
+    pub fn reduce_witness(&self, signals: &Signals) -> Result<Vec<FS>, String> {
+        let removed = self.removed_signals();
+        let mut removed = removed.iter().peekable();
+        let mut witness = Vec::with_capacity(signals.len() - removed.len());
+        witness.push(FS::one());
+        for id in 1..signals.len() {
+            if removed.peek() == Some(&&id) {
+                removed.next();
+                continue;
+            }
+            let signal = signals.get_by_id(id).unwrap();
+            match &signal.value {
+                Some(Value::FieldScalar(fs)) => witness.push(fs.clone()),
+                _ => {
+                    return Err(format!(
+                        "signal '{}' has no value",
+                        signal.full_name.to_string()
+                    ))
+                }
+            }
+        }
+        Ok(witness)
+    }
+
+    /// Sets the signal values from the values returned by `reduce_witness`
+    ///   and reconstructs the removed ones
+    pub fn expand_witness(&self, signals: &mut Signals, witness: &[FS]) -> Result<(), String> {
+        let removed = self.removed_signals();
+        if witness.len() + removed.len() != signals.len() {
+            return Err(format!(
+                "expected a witness of {} values, found {}",
+                signals.len() - removed.len(),
+                witness.len()
+            ));
+        }
+        if witness.first().map(FS::is_one) != Some(true) {
+            return Err("the first witness value should be 1".to_string());
+        }
+        let mut removed = removed.iter().peekable();
+        let mut values = witness.iter().skip(1);
+        for id in 1..signals.len() {
+            if removed.peek() == Some(&&id) {
+                removed.next();
+            } else {
+                signals.update(id, Value::FieldScalar(values.next().unwrap().clone()));
+            }
+        }
+        self.reconstruct(signals)
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::evaluator::{Evaluator, Mode};
+use crate::optimizer::{self, Level};
+use crate::project::{Project, Result};
+use crate::types::{Constraints, Signals};
+
+/// Generated and optimized amounts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Count {
+    pub before: usize,
+    pub after: usize,
+}
+
+impl Count {
+    fn add(&mut self, other: Count) {
+        self.before += other.before;
+        self.after += other.after;
+    }
+}
+
+/// Constraints and signals of a component and its subcomponents
+#[derive(Debug, Clone, Serialize)]
+pub struct Node {
+    // component full name, empty for the root of the circuit
+    pub component: String,
+    pub template: Option<String>,
+    // generated in the component itself
+    pub constraints: Count,
+    pub signals: Count,
+    // generated in the component and its subcomponents
+    pub total_constraints: Count,
+    pub total_signals: Count,
+    // the ones with more optimized constraints first
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    fn name(&self) -> String {
+        match &self.template {
+            Some(template) => format!("{} ({})", self.component, template),
+            None => self.component.clone(),
+        }
+    }
+
+    fn fmt_tree(&self, f: &mut fmt::Formatter<'_>, depth: usize, width: usize) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<width$} {:>8} {:>8} {:>8} {:>8}",
+            format!("{}{}", "  ".repeat(depth), self.name()),
+            self.total_constraints.before,
+            self.total_constraints.after,
+            self.total_signals.before,
+            self.total_signals.after,
+            width = width
+        )?;
+        for child in &self.children {
+            child.fmt_tree(f, depth + 1, width)?;
+        }
+        Ok(())
+    }
+
+    fn width(&self, depth: usize) -> usize {
+        self.children
+            .iter()
+            .map(|child| child.width(depth + 1))
+            .fold(2 * depth + self.name().len(), usize::max)
+    }
+
+    fn fold(&self, stack: &str, optimized: bool, out: &mut String) {
+        let segment = self.component.rsplit('.').next().unwrap();
+        let frame = match &self.template {
+            Some(template) => format!("{} ({})", segment, template),
+            None => segment.to_string(),
+        };
+        let stack = if stack.is_empty() {
+            frame
+        } else {
+            format!("{};{}", stack, frame)
+        };
+        let count = if optimized {
+            self.constraints.after
+        } else {
+            self.constraints.before
+        };
+        if count > 0 {
+            out.push_str(&format!("{} {}\n", stack, count));
+        }
+        for child in &self.children {
+            child.fold(&stack, optimized, out);
+        }
+    }
+}
+
+/// The constraints and signals of the circuit attributed to the component
+///   that generates them, before and after the optimization
+#[derive(Debug, Clone, Serialize)]
+pub struct Profile {
+    pub level: Level,
+    pub root: Node,
+}
+
+impl Profile {
+    /// Flamegraph folded stacks, one line per component with its own
+    ///   constraints, e.g. `main (Main);n (Num2Bits) 4`
+    pub fn folded(&self, optimized: bool) -> String {
+        let mut out = String::new();
+        for child in &self.root.children {
+            child.fold("", optimized, &mut out);
+        }
+        // constraints generated outside of any component
+        let root = if optimized {
+            self.root.constraints.after
+        } else {
+            self.root.constraints.before
+        };
+        if root > 0 {
+            out.push_str(&format!("<root> {}\n", root));
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.root.width(0).max("component".len());
+        let level = self.level.to_string();
+        writeln!(
+            f,
+            "{:<width$} {:>17} {:>17}",
+            "",
+            "constraints",
+            "signals",
+            width = width
+        )?;
+        writeln!(
+            f,
+            "{:<width$} {:>8} {:>8} {:>8} {:>8}",
+            "component",
+            "before",
+            level,
+            "before",
+            level,
+            width = width
+        )?;
+        writeln!(
+            f,
+            "{:<width$} {:>8} {:>8} {:>8} {:>8}",
+            "total",
+            self.root.total_constraints.before,
+            self.root.total_constraints.after,
+            self.root.total_signals.before,
+            self.root.total_signals.after,
+            width = width
+        )?;
+        for child in &self.root.children {
+            child.fmt_tree(f, 0, width)?;
+        }
+        Ok(())
+    }
+}
+
+// the component that contains a component or a signal
+fn parent(full_name: &str) -> &str {
+    full_name.rfind('.').map_or("", |p| &full_name[..p])
+}
+
+/// Optimizes the constraints generated by an evaluator and attributes them
+///   and the signals to their components
+pub fn profile(eval: &Evaluator, level: Level) -> Profile {
+    let irreductible_signals = eval.signals.main_input_ids();
+    let (optimized, substitutions, _) =
+        optimizer::optimize(&eval.constraints, &irreductible_signals, level);
+    let removed: HashSet<_> = substitutions.removed_signals().into_iter().collect();
+
+    // own constraints and signals of each component
+    let mut counts = BTreeMap::<String, (Count, Count)>::new();
+    for component in eval.component_templates.keys() {
+        counts.entry(component.clone()).or_default();
+    }
+    let component = |constraints: &Constraints, i: usize| {
+        constraints.get_component(i).unwrap_or_default().to_string()
+    };
+    for i in 0..eval.constraints.len() {
+        let entry = counts.entry(component(&eval.constraints, i)).or_default();
+        entry.0.before += 1;
+    }
+    for i in 0..optimized.len() {
+        let entry = counts.entry(component(&optimized, i)).or_default();
+        entry.0.after += 1;
+    }
+    // the `one` signal is not generated by the circuit
+    for id in 1..eval.signals.len() {
+        let signal = eval.signals.get_by_id(id).unwrap();
+        let entry = counts
+            .entry(parent(&signal.full_name.to_string()).to_string())
+            .or_default();
+        entry.1.before += 1;
+        if !removed.contains(&id) {
+            entry.1.after += 1;
+        }
+    }
+
+    // components without signals and constraints still link their children
+    let mut pending: Vec<String> = counts.keys().cloned().collect();
+    while let Some(component) = pending.pop() {
+        if !component.is_empty() {
+            let parent = parent(&component).to_string();
+            if !counts.contains_key(&parent) {
+                counts.insert(parent.clone(), Default::default());
+                pending.push(parent);
+            }
+        }
+    }
+
+    let mut children = BTreeMap::<String, Vec<String>>::new();
+    for component in counts.keys().filter(|c| !c.is_empty()) {
+        children
+            .entry(parent(component).to_string())
+            .or_default()
+            .push(component.clone());
+    }
+
+    Profile {
+        level,
+        root: build_node("", eval, &counts, &children),
+    }
+}
+
+fn build_node(
+    component: &str,
+    eval: &Evaluator,
+    counts: &BTreeMap<String, (Count, Count)>,
+    children: &BTreeMap<String, Vec<String>>,
+) -> Node {
+    let (constraints, signals) = counts[component];
+    let mut node = Node {
+        component: component.to_string(),
+        template: eval.component_templates.get(component).cloned(),
+        constraints,
+        signals,
+        total_constraints: constraints,
+        total_signals: signals,
+        children: Vec::new(),
+    };
+    for child in children.get(component).into_iter().flatten() {
+        let child = build_node(child, eval, counts, children);
+        node.total_constraints.add(child.total_constraints);
+        node.total_signals.add(child.total_signals);
+        node.children.push(child);
+    }
+    node.children.sort_by(|a, b| {
+        b.total_constraints
+            .after
+            .cmp(&a.total_constraints.after)
+            .then_with(|| b.total_constraints.before.cmp(&a.total_constraints.before))
+            .then_with(|| a.component.cmp(&b.component))
+    });
+    node
+}
+
+/// Generates the constraints of the project circuit and profiles them with
+///   the project optimization level
+pub fn profile_project(project: &Project) -> Result<Profile> {
+    let mut eval = Evaluator::new(
+        Mode::GenConstraints,
+        Signals::default(),
+        Constraints::default(),
+    );
+    if let Err(err) = project.eval_file(&mut eval) {
+        project.report_error(&eval, &err);
+        return Err(err.into());
+    }
+    Ok(profile(&eval, project.optimization))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::Scope;
+
+    #[test]
+    fn test_profile() {
+        let code = "
+            template Bit() {
+                signal input in;
+                signal output out;
+                out <== in;
+                in * (in - 1) === 0;
+            }
+            template Main() {
+                signal input a;
+                signal input b;
+                signal output c;
+                component bits[2];
+                for (var i = 0; i < 2; i += 1) {
+                    bits[i] = Bit();
+                }
+                bits[0].in <== a;
+                bits[1].in <== b;
+                c <== bits[0].out * bits[1].out;
+            }
+            component main = Main();
+        ";
+
+        let mut eval = Evaluator::new(
+            Mode::GenConstraints,
+            Signals::default(),
+            Constraints::default(),
+        );
+        let mut scope = Scope::new(true, None, "root".to_string());
+        eval.eval_inline(&mut scope, code).unwrap();
+        let profile = profile(&eval, Level::O2);
+
+        let root = &profile.root;
+        assert_eq!(
+            Count {
+                before: 7,
+                after: 3
+            },
+            root.total_constraints
+        );
+        assert_eq!(
+            Count {
+                before: 7,
+                after: 3
+            },
+            root.total_signals
+        );
+        assert_eq!(1, root.children.len());
+
+        let main = &root.children[0];
+        assert_eq!(Some("Main".to_string()), main.template);
+        assert_eq!(
+            Count {
+                before: 3,
+                after: 1
+            },
+            main.constraints
+        );
+        assert_eq!(
+            Count {
+                before: 3,
+                after: 3
+            },
+            main.signals
+        );
+        assert_eq!(
+            vec!["main.bits[0]", "main.bits[1]"],
+            main.children
+                .iter()
+                .map(|c| c.component.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some("Bit".to_string()), main.children[0].template);
+        assert_eq!(
+            Count {
+                before: 2,
+                after: 1
+            },
+            main.children[0].constraints
+        );
+        assert_eq!(
+            Count {
+                before: 2,
+                after: 0
+            },
+            main.children[0].signals
+        );
+
+        assert_eq!(
+            "main (Main) 3\nmain (Main);bits[0] (Bit) 2\nmain (Main);bits[1] (Bit) 2\n",
+            profile.folded(false)
+        );
+        assert_eq!(
+            "main (Main) 1\nmain (Main);bits[0] (Bit) 1\nmain (Main);bits[1] (Bit) 1\n",
+            profile.folded(true)
+        );
+    }
+}
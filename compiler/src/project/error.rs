@@ -0,0 +1,28 @@
+#[derive(Debug)]
+pub enum Error {
+    Io(String, String),
+    Toml(String),
+    InvalidManifest(String),
+    LockMismatch(Vec<String>),
+    Evaluator(crate::evaluator::Error),
+}
+
+impl From<crate::evaluator::Error> for Error {
+    fn from(err: crate::evaluator::Error) -> Self {
+        Error::Evaluator(err)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Self {
+        Error::Toml(err.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(err: toml::ser::Error) -> Self {
+        Error::Toml(err.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,155 @@
+use std::path::{Component, Path, PathBuf};
+
+use super::error::Result;
+
+const LOCK_HEADER: &str = "# This file is generated by za, do not edit it by hand.\n\n";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LockedFile {
+    pub path: String,
+    pub blake2b: String,
+}
+
+/// Contents of a `za.lock` file, the hashes of all the files included by the
+///   circuit, sorted by path
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(rename = "file", default)]
+    pub files: Vec<LockedFile>,
+}
+
+impl Lockfile {
+    pub fn from_processed_files(root: &Path, processed_files: &[(String, String)]) -> Self {
+        let mut files = processed_files
+            .iter()
+            .map(|(path, hash)| LockedFile {
+                path: relative_path(root, Path::new(path)),
+                blake2b: hash.clone(),
+            })
+            .collect::<Vec<_>>();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Lockfile { files }
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(format!("{}{}", LOCK_HEADER, toml::to_string(self)?))
+    }
+
+    /// Differences found in `current` respect the locked files
+    pub fn diff(&self, current: &Lockfile) -> Vec<String> {
+        let mut diffs = Vec::new();
+        for locked in &self.files {
+            match current.files.iter().find(|f| f.path == locked.path) {
+                None => diffs.push(format!("{} is no longer included", locked.path)),
+                Some(f) if f.blake2b != locked.blake2b => {
+                    diffs.push(format!("{} has been modified", locked.path))
+                }
+                _ => {}
+            }
+        }
+        for file in &current.files {
+            if !self.files.iter().any(|f| f.path == file.path) {
+                diffs.push(format!("{} is not in the lockfile", file.path));
+            }
+        }
+        diffs
+    }
+}
+
+// lexically normalize `path` and make it relative to `root` if possible
+fn relative_path(root: &Path, path: &Path) -> String {
+    let root = normalize(root);
+    let path = normalize(path);
+    path.strip_prefix(&root)
+        .unwrap_or(&path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match out.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    out.pop();
+                }
+                _ => out.push(".."),
+            },
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn locked(path: &str, blake2b: &str) -> LockedFile {
+        LockedFile {
+            path: path.to_string(),
+            blake2b: blake2b.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_lock_relative_paths() {
+        let lock = Lockfile::from_processed_files(
+            Path::new("."),
+            &[
+                ("./lib/../circuit.za".to_string(), "01".to_string()),
+                ("./../circomlib/mux1.circom".to_string(), "02".to_string()),
+            ],
+        );
+        assert_eq!(
+            vec![
+                locked("../circomlib/mux1.circom", "02"),
+                locked("circuit.za", "01")
+            ],
+            lock.files
+        );
+
+        let lock = Lockfile::from_processed_files(
+            Path::new("project"),
+            &[("project/src/circuit.za".to_string(), "01".to_string())],
+        );
+        assert_eq!(vec![locked("src/circuit.za", "01")], lock.files);
+    }
+
+    #[test]
+    fn test_lock_roundtrip() -> Result<()> {
+        let lock = Lockfile {
+            files: vec![locked("a.za", "01"), locked("b.za", "02")],
+        };
+        assert_eq!(lock, Lockfile::parse(&lock.to_toml()?)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lock_diff() {
+        let lock = Lockfile {
+            files: vec![locked("a.za", "01"), locked("b.za", "02")],
+        };
+        assert!(lock.diff(&lock.clone()).is_empty());
+
+        let current = Lockfile {
+            files: vec![locked("a.za", "03"), locked("c.za", "04")],
+        };
+        assert_eq!(
+            vec![
+                "a.za has been modified".to_string(),
+                "b.za is no longer included".to_string(),
+                "c.za is not in the lockfile".to_string(),
+            ],
+            lock.diff(&current)
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+
+use super::error::{Error, Result};
+
+/// Contents of a `za.toml` project manifest
+///
+/// ```toml
+/// [circuit]
+/// file = "circuit.za"
+/// main = "Multiplier(3)"
+/// include = ["lib"]
+///
+/// [dependencies]
+/// circomlib = { path = "../circomlib/circuits" }
+///
+/// [paths]
+/// pk = "proving.key"
+/// verifier = "verifier.sol"
+/// input = "input.json"
+/// proof = "proof.json"
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub circuit: Circuit,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+    #[serde(default)]
+    pub paths: Paths,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Circuit {
+    // main circuit file, relative to the project root
+    pub file: String,
+    // main template invocation, e.g. `Multiplier(3)`, or just the template
+    //   name if `params` are set
+    pub main: Option<String>,
+    // main template arguments by name, e.g. `{ n = "3" }`
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+    // additional include search paths, relative to the project root
+    #[serde(default)]
+    pub include: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dependency {
+    // local path of the library, relative to the project root
+    pub path: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Paths {
+    pub pk: Option<String>,
+    pub verifier: Option<String>,
+    pub input: Option<String>,
+    pub proof: Option<String>,
+    // circom symbols file written by `compile` and `setup`, if set
+    pub sym: Option<String>,
+}
+
+impl Manifest {
+    pub fn new(circuit_file: &str) -> Self {
+        Manifest {
+            circuit: Circuit {
+                file: circuit_file.to_string(),
+                main: None,
+                params: BTreeMap::new(),
+                include: Vec::new(),
+            },
+            dependencies: BTreeMap::new(),
+            paths: Paths::default(),
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let manifest: Manifest = toml::from_str(content)?;
+        if manifest.circuit.file.is_empty() {
+            return Err(Error::InvalidManifest(
+                "circuit.file cannot be empty".to_string(),
+            ));
+        }
+        if let Some(name) = manifest.dependencies.keys().find(|k| k.contains('/')) {
+            return Err(Error::InvalidManifest(format!(
+                "dependency name '{}' cannot contain '/'",
+                name
+            )));
+        }
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_manifest_parse() -> Result<()> {
+        let manifest = Manifest::parse(
+            r#"
+            [circuit]
+            file = "circuit.za"
+            main = "T"
+            params = { n = "3" }
+            include = ["lib"]
+
+            [dependencies]
+            circomlib = { path = "../circomlib/circuits" }
+
+            [paths]
+            pk = "keys/proving.key"
+            sym = "circuit.sym"
+            "#,
+        )?;
+
+        assert_eq!("circuit.za", manifest.circuit.file);
+        assert_eq!(Some("T".to_string()), manifest.circuit.main);
+        assert_eq!("3", manifest.circuit.params["n"]);
+        assert_eq!(vec!["lib".to_string()], manifest.circuit.include);
+        assert_eq!(
+            "../circomlib/circuits",
+            manifest.dependencies["circomlib"].path
+        );
+        assert_eq!(Some("keys/proving.key".to_string()), manifest.paths.pk);
+        assert_eq!(None, manifest.paths.proof);
+        assert_eq!(Some("circuit.sym".to_string()), manifest.paths.sym);
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifest_minimal() -> Result<()> {
+        let manifest = Manifest::parse("[circuit]\nfile = \"c.za\"\n")?;
+        assert_eq!(None, manifest.circuit.main);
+        assert!(manifest.circuit.params.is_empty());
+        assert!(manifest.dependencies.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifest_invalid() {
+        assert!(Manifest::parse("[circuit]\n").is_err());
+        assert!(Manifest::parse("[circuit]\nfile = \"\"\n").is_err());
+        assert!(Manifest::parse(
+            "[circuit]\nfile = \"c.za\"\n[dependencies]\n\"a/b\" = { path = \"x\" }\n"
+        )
+        .is_err());
+    }
+}
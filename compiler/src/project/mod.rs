@@ -0,0 +1,567 @@
+mod error;
+mod lock;
+mod manifest;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::cache::{self, Cache, CompiledCircuit};
+use crate::diagnostics::{emit, Diagnostic, MessageFormat};
+use crate::evaluator;
+use crate::evaluator::{Evaluator, Location, Mode, Scope};
+use crate::optimizer;
+use crate::tester::dump_error;
+use crate::types::{print_info, Constraints, Signals};
+
+pub use self::error::{Error, Result};
+pub use self::lock::{LockedFile, Lockfile};
+pub use self::manifest::{Circuit, Dependency, Manifest, Paths};
+
+pub const MANIFEST_FILE: &str = "za.toml";
+pub const LOCK_FILE: &str = "za.lock";
+
+/// A circuit project, defined by a `za.toml` manifest in its root folder or
+///   by a single circuit file
+pub struct Project {
+    pub root: PathBuf,
+    pub manifest: Manifest,
+    pub cache: Option<Cache>,
+
+    // how the evaluation errors are printed
+    pub message_format: MessageFormat,
+
+    pub optimization: optimizer::Level,
+    // how the optimization report is printed after compiling, if set
+    pub optimization_report: Option<MessageFormat>,
+}
+
+impl Project {
+    pub fn new(root: &str, manifest: Manifest) -> Self {
+        Project {
+            root: PathBuf::from(root),
+            manifest,
+            cache: None,
+            message_format: MessageFormat::default(),
+            optimization: optimizer::Level::default(),
+            optimization_report: None,
+        }
+    }
+
+    /// Enables the `.za-cache` folder in the project root
+    pub fn with_cache(mut self) -> Self {
+        self.cache = Some(Cache::new(self.root.join(cache::CACHE_DIR)));
+        self
+    }
+
+    pub fn with_message_format(mut self, message_format: MessageFormat) -> Self {
+        self.message_format = message_format;
+        self
+    }
+
+    pub fn with_optimization(
+        mut self,
+        level: optimizer::Level,
+        report: Option<MessageFormat>,
+    ) -> Self {
+        self.optimization = level;
+        self.optimization_report = report;
+        self
+    }
+
+    pub fn from_circuit(root: &str, circuit_file: &str) -> Self {
+        Project::new(root, Manifest::new(circuit_file))
+    }
+
+    pub fn load(root: &str) -> Result<Self> {
+        let manifest_path = Path::new(root).join(MANIFEST_FILE);
+        let manifest = Manifest::parse(&read_file(&manifest_path)?)?;
+        Ok(Project::new(root, manifest))
+    }
+
+    /// Loads the project in `root` if it contains a manifest
+    pub fn discover(root: &str) -> Result<Option<Self>> {
+        if Path::new(root).join(MANIFEST_FILE).exists() {
+            Ok(Some(Project::load(root)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn has_manifest(&self) -> bool {
+        self.root.join(MANIFEST_FILE).exists()
+    }
+
+    pub fn circuit_file(&self) -> &str {
+        &self.manifest.circuit.file
+    }
+
+    /// Sets the evaluator include paths, libraries and main template
+    pub fn configure(&self, eval: &mut Evaluator) {
+        eval.include_paths = self
+            .manifest
+            .circuit
+            .include
+            .iter()
+            .map(|include| self.root.join(include))
+            .collect();
+        eval.libraries = self
+            .manifest
+            .dependencies
+            .iter()
+            .map(|(name, dep)| (name.clone(), self.root.join(&dep.path)))
+            .collect();
+        eval.main = self.manifest.circuit.main.clone();
+        eval.main_params = self
+            .manifest
+            .circuit
+            .params
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        eval.cache = self.cache.clone();
+    }
+
+    /// Configures the evaluator and evaluates the main circuit file
+    pub fn eval_file<'a>(&self, eval: &'a mut Evaluator) -> evaluator::Result<Scope<'a>> {
+        self.configure(eval);
+        eval.eval_file(&self.root.to_string_lossy(), self.circuit_file())
+    }
+
+    /// Evaluates the circuit sources in `Mode::Collect` and returns all the
+    ///   syntax errors, missing includes and calls to undefined templates or
+    ///   functions, instead of stopping at the first one
+    pub fn check(&self) -> Vec<(evaluator::Error, Location)> {
+        let mut eval = Evaluator::new(Mode::Collect, Signals::default(), Constraints::default());
+        eval.collected_errors = Some(Vec::new());
+        let res = self.eval_file(&mut eval).map(|_| ());
+        let mut errors = eval.collected_errors.take().unwrap_or_default();
+        if let (Err(err), Some(ctx)) = (res, &eval.last_error) {
+            let location = Location {
+                file: ctx.file.clone(),
+                meta: ctx.meta.clone(),
+            };
+            errors.push((err, location));
+        }
+        errors
+    }
+
+    /// Prints an evaluation error, if the sources have errors that can be
+    ///   collected all of them are printed instead, since they are the cause
+    pub fn report_error(&self, eval: &Evaluator, err: &evaluator::Error) {
+        let errors = self.check();
+        if errors.is_empty() {
+            dump_error(eval, err, self.message_format);
+        } else {
+            let diagnostics = errors
+                .iter()
+                .map(|(err, location)| Diagnostic::from_collected(err, location))
+                .collect::<Vec<_>>();
+            emit(&diagnostics, self.message_format);
+        }
+    }
+
+    /// Compiles and optimizes the circuit, reusing the cached constraint system
+    ///   if none of the sources has changed
+    pub fn compile(&self, update_lock: bool, print_all: bool) -> Result<CompiledCircuit> {
+        let key = self.cache_key();
+        if let Some(circuit) = self.cache.as_ref().and_then(|c| c.load_circuit(&key)) {
+            info!("Using cached constraint system");
+            self.check_lock(&circuit.processed_files, update_lock)?;
+            print_info(
+                "optimized",
+                &circuit.constraints,
+                &circuit.signals,
+                &circuit.removed_signals,
+                print_all,
+            );
+            self.print_report(&circuit.report);
+            info!("Circuit hash: {}", circuit.circuit_hash);
+            return Ok(circuit);
+        }
+
+        let mut start = SystemTime::now();
+        let mut eval = Evaluator::new(
+            Mode::GenConstraints,
+            Signals::default(),
+            Constraints::default(),
+        );
+        if let Err(err) = self.eval_file(&mut eval) {
+            self.report_error(&eval, &err);
+            return Err(Error::Evaluator(err));
+        }
+        self.check_lock(&eval.processed_files, update_lock)?;
+
+        info!(
+            "Compile time: {:?}",
+            SystemTime::now().duration_since(start).unwrap()
+        );
+        start = SystemTime::now();
+
+        let Evaluator {
+            constraints,
+            signals,
+            processed_files,
+            skipped_includes,
+            collected_asts,
+            main,
+            ..
+        } = eval;
+        print_info("compile", &constraints, &signals, &[], print_all);
+
+        let irreductible_signals = signals.main_input_ids();
+        let constraints_before = constraints.len();
+        let (constraints, substitutions, stats) =
+            optimizer::optimize(&constraints, &irreductible_signals, self.optimization);
+        let removed_signals = substitutions.removed_signals();
+
+        let time = SystemTime::now().duration_since(start).unwrap();
+        info!("Optimization time: {:?}", time);
+        let report = optimizer::Report::new(
+            self.optimization,
+            &stats,
+            constraints_before,
+            constraints.len(),
+            &signals,
+            &substitutions,
+            time,
+        );
+        self.print_report(&report);
+        print_info(
+            "optimized",
+            &constraints,
+            &signals,
+            &removed_signals,
+            print_all,
+        );
+
+        let circuit_hash = constraints.circuit_hash(&signals);
+        info!("Circuit hash: {}", circuit_hash);
+
+        let circuit = CompiledCircuit {
+            main,
+            processed_files,
+            skipped_includes,
+            asts: collected_asts,
+            signals,
+            constraints,
+            removed_signals,
+            substitutions,
+            report,
+            circuit_hash,
+        };
+        if let Some(cache) = &self.cache {
+            if let Err(err) = cache.store_circuit(&key, &circuit) {
+                warn!("Cannot store constraint system in cache: {:?}", err);
+            }
+        }
+        Ok(circuit)
+    }
+
+    fn print_report(&self, report: &optimizer::Report) {
+        match self.optimization_report {
+            Some(MessageFormat::Human) => print!("{}", report),
+            Some(MessageFormat::Json) => println!("{}", report.to_json()),
+            None => {}
+        }
+    }
+
+    // the compiled circuit depends on the sources, on how they are resolved
+    //   and on the optimization level
+    fn cache_key(&self) -> String {
+        let config = format!(
+            "{:?}{:?}{:?}{:?}",
+            self.root, self.manifest.circuit, self.manifest.dependencies, self.optimization
+        );
+        cache::hash(config.as_bytes())
+    }
+
+    /// Checks the hashes of the processed files against the lockfile, the
+    ///   lockfile is created if does not exist or `update` is set. Projects
+    ///   without manifest are not locked.
+    pub fn check_lock(&self, processed_files: &[(String, String)], update: bool) -> Result<()> {
+        if !self.has_manifest() {
+            return Ok(());
+        }
+
+        let lock_path = self.root.join(LOCK_FILE);
+        let current = Lockfile::from_processed_files(&self.root, processed_files);
+
+        if lock_path.exists() && !update {
+            let diffs = Lockfile::parse(&read_file(&lock_path)?)?.diff(&current);
+            if !diffs.is_empty() {
+                return Err(Error::LockMismatch(diffs));
+            }
+        } else {
+            let content = current.to_toml()?;
+            File::create(&lock_path)
+                .and_then(|mut file| file.write_all(content.as_bytes()))
+                .map_err(|err| Error::Io(format!("{:?}", lock_path), err.to_string()))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    let mut content = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut content))
+        .map_err(|err| Error::Io(format!("{:?}", path), err.to_string()))?;
+    Ok(content)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_provenance() {
+        let root = std::env::temp_dir().join("za-project-test-provenance");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let code = "
+            template Mul() {
+                signal input a;
+                signal input b;
+                signal output c;
+                signal t;
+                t <== a;
+                c <== t * b;
+            }
+            component main = Mul();";
+        fs::write(root.join("circuit.za"), code).unwrap();
+
+        // the constraint kept by the optimizer and the cached one are located
+        let project = Project::from_circuit(&root.to_string_lossy(), "circuit.za").with_cache();
+        for _ in 0..2 {
+            let circuit = project.compile(false, false).unwrap();
+            assert_eq!(1, circuit.constraints.len());
+            let provenance = circuit.constraints.get_provenance(0).unwrap();
+            assert_eq!(code.find("c <==").unwrap(), provenance.start);
+            assert_eq!("main", provenance.component);
+            assert_eq!(
+                format!("{}:8:17 in main", root.join("circuit.za").display()),
+                provenance.position()
+            );
+            assert!(circuit
+                .constraints
+                .provenance_json()
+                .contains("\"line\": 8"));
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let root = std::env::temp_dir().join("za-project-test-check");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let file = |name: &str| root.join(name).to_string_lossy().to_string();
+        let check = |main: &str| {
+            fs::write(root.join("main.za"), main).unwrap();
+            Project::from_circuit(&root.to_string_lossy(), "main.za")
+                .check()
+                .into_iter()
+                .map(|(err, location)| (err.code(), location.file, location.meta.start))
+                .collect::<Vec<_>>()
+        };
+
+        // the calls to undefined templates and functions of all the files
+        let lib = "template A() { component d = D(); }";
+        fs::write(root.join("lib.za"), lib).unwrap();
+        let main = "
+            include \"lib.za\";
+            include \"missing.za\";
+            template C() { component a = A(); var v = f(1); }
+            component main = C();";
+        assert_eq!(
+            vec![
+                ("E0016", file("main.za"), main.find("include \"m").unwrap()),
+                ("E0001", file("lib.za"), lib.find("D()").unwrap()),
+                ("E0001", file("main.za"), main.find("f(1)").unwrap()),
+            ],
+            check(main)
+        );
+
+        // the syntax errors of all the files
+        let bad = "
+            template A() { signal input a b; }
+            template B() { var x = ; }";
+        fs::write(root.join("bad.za"), bad).unwrap();
+        let main = "
+            include \"bad.za\";
+            template C() { signal c d; }
+            component main = C();";
+        assert_eq!(
+            vec![
+                ("E0003", file("main.za"), main.find("d;").unwrap()),
+                ("E0003", file("bad.za"), bad.find("b;").unwrap()),
+                ("E0003", file("bad.za"), bad.find("= ;").unwrap() + 2),
+            ],
+            check(main)
+        );
+    }
+
+    #[test]
+    fn test_check_lock() {
+        let root = std::env::temp_dir().join("za-project-test-lock");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join(MANIFEST_FILE),
+            "[circuit]\nfile = \"circuit.za\"\n",
+        )
+        .unwrap();
+        let project = Project::load(&root.to_string_lossy()).unwrap();
+        let file = |name: &str, hash: &str| {
+            (
+                root.join(name).to_string_lossy().to_string(),
+                hash.to_string(),
+            )
+        };
+        let diffs = |files: &[(String, String)]| match project.check_lock(files, false) {
+            Ok(()) => Vec::new(),
+            Err(Error::LockMismatch(diffs)) => diffs,
+            Err(err) => panic!("unexpected error {:?}", err),
+        };
+
+        // the first check creates the lockfile
+        let locked = vec![file("circuit.za", "00"), file("a.za", "01")];
+        assert!(diffs(&locked).is_empty());
+        assert!(root.join(LOCK_FILE).exists());
+        assert!(diffs(&locked).is_empty());
+
+        assert_eq!(
+            vec!["a.za has been modified"],
+            diffs(&[file("circuit.za", "00"), file("a.za", "02")])
+        );
+        assert_eq!(
+            vec!["b.za is not in the lockfile"],
+            diffs(&[
+                file("circuit.za", "00"),
+                file("a.za", "01"),
+                file("b.za", "03")
+            ])
+        );
+        assert_eq!(
+            vec!["a.za is no longer included"],
+            diffs(&[file("circuit.za", "00")])
+        );
+
+        // updating accepts the changes
+        let updated = vec![file("circuit.za", "00"), file("b.za", "03")];
+        project.check_lock(&updated, true).unwrap();
+        assert!(diffs(&updated).is_empty());
+    }
+
+    #[test]
+    fn test_configure() {
+        let root = std::env::temp_dir().join("za-project-test-configure");
+        let _ = fs::remove_dir_all(&root);
+        for dir in &["inc1", "inc2", "libs/lib", "src"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(
+            root.join(MANIFEST_FILE),
+            "
+            [circuit]
+            file = \"src/circuit.za\"
+            main = \"T\"
+            params = { n = \"2\" }
+            include = [\"inc1\", \"inc2\"]
+
+            [dependencies]
+            lib = { path = \"libs/lib\" }",
+        )
+        .unwrap();
+        let project = Project::load(&root.to_string_lossy()).unwrap();
+
+        let mut eval = Evaluator::new(
+            Mode::GenConstraints,
+            Signals::default(),
+            Constraints::default(),
+        );
+        project.configure(&mut eval);
+        assert_eq!(
+            vec![root.join("inc1"), root.join("inc2")],
+            eval.include_paths
+        );
+        assert_eq!(Some(&root.join("libs/lib")), eval.libraries.get("lib"));
+        assert_eq!(Some("T".to_string()), eval.main);
+        assert_eq!(vec![("n".to_string(), "2".to_string())], eval.main_params);
+
+        // the includes are searched relative to the file, then in the
+        //   libraries and then in the include paths, in order
+        let file = |name: &str, code: &str| fs::write(root.join(name), code).unwrap();
+        file(
+            "src/circuit.za",
+            "
+            include \"local.za\";
+            include \"lib/dep.za\";
+            include \"shared.za\";
+            include \"second.za\";
+            template T(n) { signal input a[n]; }",
+        );
+        file("src/local.za", "function local() { return 1; }");
+        file("inc1/local.za", "function local_inc() { return 1; }");
+        file("libs/lib/dep.za", "function dep() { return 1; }");
+        file("inc1/shared.za", "function shared() { return 1; }");
+        file("inc2/shared.za", "function shared_inc2() { return 1; }");
+        file("inc2/second.za", "function second() { return 1; }");
+
+        let mut eval = Evaluator::new(
+            Mode::GenConstraints,
+            Signals::default(),
+            Constraints::default(),
+        );
+        project.eval_file(&mut eval).unwrap();
+        let processed = eval
+            .processed_files
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        let path = |name: &str| root.join(name).to_string_lossy().to_string();
+        assert_eq!(
+            vec![
+                path("src/circuit.za"),
+                path("src/local.za"),
+                path("libs/lib/dep.za"),
+                path("inc1/shared.za"),
+                path("inc2/second.za"),
+            ],
+            processed
+        );
+        assert!(eval.signals.get_by_name("main.a[1]").is_some());
+    }
+
+    #[test]
+    fn test_cache_include_resolution() {
+        let root = std::env::temp_dir().join("za-project-test-cache-include");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("inc")).unwrap();
+        fs::write(
+            root.join("circuit.za"),
+            "include \"t.za\"; component main = T();",
+        )
+        .unwrap();
+        fs::write(
+            root.join("inc/t.za"),
+            "template T() { signal input a; signal output b; b <== a * a; }",
+        )
+        .unwrap();
+        let mut manifest = Manifest::new("circuit.za");
+        manifest.circuit.include = vec!["inc".to_string()];
+        let project = Project::new(&root.to_string_lossy(), manifest).with_cache();
+        assert_eq!(1, project.compile(false, false).unwrap().constraints.len());
+
+        // a file next to the circuit is included instead of the cached one
+        fs::write(
+            root.join("t.za"),
+            "template T() { signal input a; signal output b; b <== a * a; a * b === 1; }",
+        )
+        .unwrap();
+        assert_eq!(2, project.compile(false, false).unwrap().constraints.len());
+    }
+}
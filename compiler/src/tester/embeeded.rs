@@ -4,7 +4,8 @@ use super::error::{Error, Result};
 use super::report::dump_error;
 
 use crate::algebra::FS;
-use crate::evaluator::{Evaluator, Mode, ScopeValue};
+use crate::evaluator::{Error as EvalError, Evaluator, Mode, ScopeValue};
+use crate::project::Project;
 use crate::types::{Constraints, Signals};
 
 pub fn run_embeeded_tests(
@@ -14,17 +15,33 @@ pub fn run_embeeded_tests(
     skip_compile: bool,
     output_witness: bool,
     test_prefix: &str,
-) -> Result<Option<(Evaluator, String)>> {
+) -> Result<Option<(Evaluator, EvalError)>> {
+    run_project_tests(
+        &Project::from_circuit(path, filename),
+        debug,
+        skip_compile,
+        output_witness,
+        test_prefix,
+    )
+}
+
+pub fn run_project_tests(
+    project: &Project,
+    debug: bool,
+    skip_compile: bool,
+    output_witness: bool,
+    test_prefix: &str,
+) -> Result<Option<(Evaluator, EvalError)>> {
     let mut eval = Evaluator::new(Mode::Collect, Signals::default(), Constraints::default());
 
-    match eval.eval_file(&path, &filename) {
+    match project.eval_file(&mut eval) {
         Ok(scan_scope) => {
             let tests = scan_scope
                 .vars
                 .borrow()
                 .iter()
                 .filter_map(|(k, v)| match v {
-                    ScopeValue::Template { attrs, .. } if attrs.has_tag_test() => Some(k),
+                    ScopeValue::Template { meta, .. } if meta.attrs.has_tag_test() => Some(k),
                     _ => None,
                 })
                 .map(|template_name| template_name.to_string())
@@ -40,7 +57,7 @@ pub fn run_embeeded_tests(
                     Evaluator::new(Mode::GenWitness, Signals::default(), Constraints::default());
                 ev_witness.debug = debug;
                 if let Err(err) = ev_witness.eval_template(&mut scan_scope.clone(), &test_name) {
-                    dump_error(&ev_witness, &format!("{:?}", &err));
+                    dump_error(&ev_witness, &err, project.message_format);
                     return Err(Error::Evaluator(err));
                 }
 
@@ -68,7 +85,7 @@ pub fn run_embeeded_tests(
                     if let Err(err) =
                         ev_constraints.eval_template(&mut scan_scope.clone(), &test_name)
                     {
-                        dump_error(&ev_constraints, &format!("{:?}", &err));
+                        dump_error(&ev_constraints, &err, project.message_format);
                         return Err(Error::Evaluator(err));
                     }
 
@@ -107,13 +124,13 @@ pub fn run_embeeded_tests(
                     ev_constraints
                         .constraints
                         .satisfies_with_signals(&ev_witness.signals)
-                        .map_err(Error::Unexpected)?;
+                        .map_err(|report| Error::Unexpected(report.to_string()))?;
                 }
             }
         }
 
         Err(err) => {
-            dump_error(&eval, &format!("{:?}", &err));
+            project.report_error(&eval, &err);
         }
     }
 
@@ -1,68 +1,408 @@
+use std::collections::HashMap;
+use std::fs;
+
+use super::satisfaction::{Satisfaction, SignalValue, Unsatisfied};
 use super::signal::Signals;
-use crate::algebra::{AlgZero, Value, FS, LC, QEQ};
+use crate::algebra::{AlgZero, Value, QEQ};
+use crate::cache;
+use crate::diagnostics::line_column;
+
+// changes if the hashed content or its encoding changes
+const CIRCUIT_HASH_VERSION: &str = "za-circuit-1";
 
-pub struct Constraints(Vec<(QEQ, Option<String>)>);
+/// Where a constraint has been generated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub file: String,
+    // byte span of the statement
+    pub start: usize,
+    pub end: usize,
+    // full name of the component, empty at the root
+    pub component: String,
+}
 
-impl Default for Constraints {
-    fn default() -> Self {
-        Constraints(Vec::new())
+impl Provenance {
+    /// `file:line:column in component`, with the byte offset if the file
+    ///   cannot be read
+    pub fn position(&self) -> String {
+        let position = match fs::read_to_string(&self.file)
+            .ok()
+            .and_then(|code| line_column(&code, self.start))
+        {
+            Some((line, column)) => format!("{}:{}:{}", self.file, line, column),
+            None => format!("{}:{}", self.file, self.start),
+        };
+        if self.component.is_empty() {
+            position
+        } else {
+            format!("{} in {}", position, self.component)
+        }
     }
 }
 
+// file names and component full names, stored once for all the constraints
+#[derive(Default)]
+struct Names {
+    names: Vec<String>,
+    ids: HashMap<String, u32>,
+}
+
+impl Names {
+    fn intern(&mut self, name: &str) -> u32 {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        let id = self.names.len() as u32;
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        id
+    }
+    fn get(&self, id: u32) -> &str {
+        &self.names[id as usize]
+    }
+}
+
+// a provenance with the file and component interned
+#[derive(Clone, Copy)]
+struct Origin {
+    file: u32,
+    start: usize,
+    end: usize,
+    component: u32,
+}
+
+#[derive(Default)]
+pub struct Constraints {
+    qeqs: Vec<(QEQ, Option<Origin>)>,
+    files: Names,
+    components: Names,
+}
+
 impl Constraints {
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.qeqs.is_empty()
     }
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.qeqs.len()
     }
     pub fn get(&self, i: usize) -> QEQ {
-        self.0[i].0.clone()
-    }
-    pub fn get_debug(&self, i: usize) -> Option<String> {
-        self.0[i].1.clone()
-    }
-    pub fn push(&mut self, qeq: QEQ, debug: Option<String>) -> usize {
-        self.0.push((qeq, debug));
-        self.0.len() - 1
-    }
-    pub fn satisfies_with_signals(&self, signals: &Signals) -> Result<(), String> {
-        let eval_lc = |lc: &LC| {
-            lc.0.iter().fold(Ok(FS::zero()), |acc, (s, v)| {
-                let s_val = if *s == 0 {
-                    FS::one()
-                } else {
-                    let s_val = &*signals.get_by_id(*s).unwrap();
-                    match &s_val.value {
-                        Some(Value::FieldScalar(fs)) => fs.clone(),
-                        _ => return Err(format!("signal bad value {:?}", s_val)),
+        self.qeqs[i].0.clone()
+    }
+    pub fn get_provenance(&self, i: usize) -> Option<Provenance> {
+        self.qeqs[i].1.map(|origin| Provenance {
+            file: self.files.get(origin.file).to_string(),
+            start: origin.start,
+            end: origin.end,
+            component: self.components.get(origin.component).to_string(),
+        })
+    }
+    /// Full name of the component that generated the constraint
+    pub fn get_component(&self, i: usize) -> Option<&str> {
+        self.qeqs[i]
+            .1
+            .map(|origin| self.components.get(origin.component))
+    }
+    pub fn push(&mut self, qeq: QEQ, provenance: Option<Provenance>) -> usize {
+        match provenance {
+            Some(p) => self.push_located(qeq, &p.file, p.start, p.end, &p.component),
+            None => {
+                self.qeqs.push((qeq, None));
+                self.qeqs.len() - 1
+            }
+        }
+    }
+    /// Pushes a constraint generated in the `start..end` span of `file`, the
+    ///   names are only copied the first time
+    pub fn push_located(
+        &mut self,
+        qeq: QEQ,
+        file: &str,
+        start: usize,
+        end: usize,
+        component: &str,
+    ) -> usize {
+        let origin = Origin {
+            file: self.files.intern(file),
+            start,
+            end,
+            component: self.components.intern(component),
+        };
+        self.qeqs.push((qeq, Some(origin)));
+        self.qeqs.len() - 1
+    }
+    /// Pushes a constraint with the provenance of the constraint `i` of
+    ///   `constraints`
+    pub fn push_from(&mut self, qeq: QEQ, constraints: &Constraints, i: usize) -> usize {
+        match constraints.qeqs[i].1 {
+            Some(origin) => self.push_located(
+                qeq,
+                constraints.files.get(origin.file),
+                origin.start,
+                origin.end,
+                constraints.components.get(origin.component),
+            ),
+            None => self.push(qeq, None),
+        }
+    }
+    /// Checks all the constraints with the signal values
+    pub fn satisfaction(&self, signals: &Signals) -> Satisfaction {
+        let mut unsatisfied = Vec::new();
+        for (index, (qeq, _)) in self.qeqs.iter().enumerate() {
+            let (a, b, c) = (
+                signals.eval_lc(&qeq.a).ok(),
+                signals.eval_lc(&qeq.b).ok(),
+                signals.eval_lc(&qeq.c).ok(),
+            );
+            let value = match (&a, &b, &c) {
+                (Some(a), Some(b), Some(c)) => Some(&(a * b) + c),
+                _ => None,
+            };
+            if matches!(&value, Some(value) if value.is_zero()) {
+                continue;
+            }
+
+            let mut ids = qeq
+                .a
+                .iter()
+                .chain(&qeq.b)
+                .chain(&qeq.c)
+                .map(|(s, _)| *s)
+                .filter(|s| *s != 0)
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids.dedup();
+            let signals_values = ids
+                .into_iter()
+                .map(|id| {
+                    let signal = signals.get_by_id(id).unwrap();
+                    let value = match &signal.value {
+                        Some(Value::FieldScalar(fs)) => Some(fs.clone()),
+                        _ => None,
+                    };
+                    SignalValue {
+                        id,
+                        name: signal.full_name.to_string(),
+                        value,
                     }
-                };
-                Ok(&acc? + &(v * &s_val))
+                })
+                .collect();
+
+            unsatisfied.push(Unsatisfied {
+                index,
+                constraint: signals.format(&Value::QuadraticEquation(qeq.clone())),
+                a,
+                b,
+                c,
+                value,
+                signals: signals_values,
+                provenance: self.get_provenance(index),
+            });
+        }
+        Satisfaction {
+            constraints: self.len(),
+            unsatisfied,
+        }
+    }
+
+    /// Checks all the constraints, the ones that are not satisfied are returned
+    pub fn satisfies_with_signals(&self, signals: &Signals) -> Result<(), Satisfaction> {
+        let satisfaction = self.satisfaction(signals);
+        if satisfaction.is_satisfied() {
+            Ok(())
+        } else {
+            Err(satisfaction)
+        }
+    }
+
+    /// The canonical form of each constraint, sorted and without the ones
+    ///   that always hold or are repeated, the provenance is not kept
+    pub fn canonical(&self) -> Constraints {
+        let mut qeqs = self
+            .qeqs
+            .iter()
+            .map(|(qeq, _)| qeq.canonical())
+            .filter(|qeq| !qeq.is_zero())
+            .collect::<Vec<_>>();
+        qeqs.sort();
+        qeqs.dedup();
+        let mut canonical = Constraints::default();
+        for qeq in qeqs {
+            canonical.push(qeq, None);
+        }
+        canonical
+    }
+
+    /// Hash of the main public inputs and the canonical constraints, it does
+    ///   not change with the order of the constraints or how they are scaled
+    pub fn circuit_hash(&self, signals: &Signals) -> String {
+        fn put(bytes: &mut Vec<u8>, n: usize) {
+            bytes.extend_from_slice(&(n as u64).to_be_bytes());
+        }
+        let mut bytes = CIRCUIT_HASH_VERSION.as_bytes().to_vec();
+
+        let inputs = (1..signals.len())
+            .map(|id| signals.get_by_id(id).unwrap())
+            .filter(|signal| signal.is_main_public_input())
+            .collect::<Vec<_>>();
+        put(&mut bytes, inputs.len());
+        for signal in inputs {
+            let name = signal.full_name.to_string();
+            put(&mut bytes, signal.id);
+            put(&mut bytes, name.len());
+            bytes.extend_from_slice(name.as_bytes());
+        }
+
+        let canonical = self.canonical();
+        put(&mut bytes, canonical.len());
+        for (qeq, _) in &canonical.qeqs {
+            for lc in &[&qeq.a, &qeq.b, &qeq.c] {
+                put(&mut bytes, lc.len());
+                for (signal, value) in lc.iter() {
+                    put(&mut bytes, *signal);
+                    let mut value = value.to_bytes_le();
+                    value.resize(32, 0);
+                    bytes.extend_from_slice(&value);
+                }
+            }
+        }
+        cache::hash(&bytes)
+    }
+
+    /// JSON map from each constraint index to where it has been generated,
+    ///   with the byte span, 1-based line and column and component
+    pub fn provenance_json(&self) -> String {
+        let mut sources = HashMap::new();
+        let entries = self
+            .qeqs
+            .iter()
+            .enumerate()
+            .filter_map(|(n, (_, origin))| origin.map(|origin| (n, origin)))
+            .map(|(n, origin)| {
+                let file = self.files.get(origin.file);
+                let position = sources
+                    .entry(origin.file)
+                    .or_insert_with(|| fs::read_to_string(file).ok())
+                    .as_ref()
+                    .and_then(|code| line_column(code, origin.start));
+                ProvenanceEntry {
+                    constraint: n,
+                    file,
+                    start: origin.start,
+                    end: origin.end,
+                    line: position.map(|(line, _)| line),
+                    column: position.map(|(_, column)| column),
+                    component: self.components.get(origin.component),
+                }
             })
-        };
+            .collect::<Vec<_>>();
+        serde_json::to_string_pretty(&entries).unwrap()
+    }
+}
+
+#[derive(Serialize)]
+struct ProvenanceEntry<'a> {
+    constraint: usize,
+    file: &'a str,
+    start: usize,
+    end: usize,
+    line: Option<usize>,
+    column: Option<usize>,
+    component: &'a str,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::algebra::{SignalId, Value, FS, LC};
+    use za_parser::ast::SignalType;
 
-        for n in 0..self.len() {
-            let qeq = self.get(n);
-            let a = eval_lc(&qeq.a)?;
-            let b = eval_lc(&qeq.b)?;
-            let c = eval_lc(&qeq.c)?;
-
-            let zero = &(&a * &b) + &c;
-
-            if !zero.is_zero() {
-                let nonzero_value = Value::QuadraticEquation(qeq);
-                let debug = self.get_debug(n).unwrap_or_else(|| "".to_string());
-                let msg = format!(
-                    "constrain '{}' ({}) evals to non-zero ({:?})",
-                    signals.format(&nonzero_value),
-                    debug,
-                    zero
-                );
-                return Err(msg);
+    #[test]
+    fn test_circuit_hash() {
+        let mut signals = Signals::default();
+        let a = signals.insert("main.a".to_string(), SignalType::PublicInput, None);
+        let b = signals.insert("main.b".to_string(), SignalType::PrivateInput, None);
+        let c = signals.insert("main.c".to_string(), SignalType::Output, None);
+        let lc = |terms: &[(SignalId, u64)]| {
+            terms
+                .iter()
+                .map(|(s, k)| (*s, FS::from(*k)))
+                .collect::<LC>()
+        };
+        let constraints = |qeqs: Vec<QEQ>| {
+            let mut constraints = Constraints::default();
+            for qeq in qeqs {
+                constraints.push(qeq, None);
             }
+            constraints
+        };
+
+        // a*b = c and a + b = c
+        let mul = &(&lc(&[(a, 1)]) * &lc(&[(b, 1)])) + &-&lc(&[(c, 1)]);
+        let add = QEQ::from(lc(&[(a, 1), (b, 1), (c, 1)]));
+        let hash = constraints(vec![mul.clone(), add.clone()]).circuit_hash(&signals);
+
+        // reordered, scaled, swapped and repeated constraints
+        let two = FS::from(2);
+        let swapped = &(&lc(&[(b, 3)]) * &lc(&[(a, 1)])) + &-&lc(&[(c, 3)]);
+        let reordered = constraints(vec![&add * &two, swapped, -&mul, QEQ::from(LC::new())]);
+        assert_eq!(2, reordered.canonical().len());
+        assert_eq!(hash, reordered.circuit_hash(&signals));
+
+        let changed = QEQ::from(lc(&[(a, 1), (b, 2), (c, 1)]));
+        assert_ne!(hash, constraints(vec![mul, changed]).circuit_hash(&signals));
+    }
+
+    #[test]
+    fn test_satisfaction() {
+        let mut signals = Signals::default();
+        let a = signals.insert("main.a".to_string(), SignalType::PublicInput, None);
+        let b = signals.insert("main.b".to_string(), SignalType::PrivateInput, None);
+        let c = signals.insert("main.c".to_string(), SignalType::Output, None);
+        let d = signals.insert("main.d".to_string(), SignalType::Internal, None);
+        for (signal, value) in &[(a, 3), (b, 7), (c, 20)] {
+            signals.update(*signal, Value::from(*value));
         }
+        let one = |s: SignalId| LC::from_signal(s, FS::one());
+
+        let mut constraints = Constraints::default();
+        let provenance = Provenance {
+            file: "circuit.za".to_string(),
+            start: 10,
+            end: 20,
+            component: "main".to_string(),
+        };
+        // a*b = c, a + b = 10, c = 20 and d = a
+        constraints.push(&(&one(a) * &one(b)) + &-&one(c), Some(provenance));
+        constraints.push(QEQ::from(&(&one(a) + &one(b)) + &-&FS::from(10)), None);
+        constraints.push(QEQ::from(&one(c) + &-&FS::from(20)), None);
+        constraints.push(QEQ::from(&one(d) + &-&one(a)), None);
+
+        let satisfaction = constraints.satisfaction(&signals);
+        assert!(!satisfaction.is_satisfied());
+        assert_eq!(4, satisfaction.constraints);
+        assert_eq!(2, satisfaction.unsatisfied.len());
+
+        let mul = &satisfaction.unsatisfied[0];
+        assert_eq!(0, mul.index);
+        assert_eq!("[1main.a]*[1main.b]+[-1main.c]", mul.constraint);
+        assert_eq!(Some(FS::from(1)), mul.value);
+        assert_eq!(Some(FS::from(20)), mul.c.as_ref().map(|c| -c));
+        assert_eq!(
+            vec!["main.a", "main.b", "main.c"],
+            mul.signals
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("main", mul.provenance.as_ref().unwrap().component);
+        assert!(!mul.has_missing_values());
+
+        let missing = &satisfaction.unsatisfied[1];
+        assert_eq!(3, missing.index);
+        assert!(missing.has_missing_values());
+        assert_eq!((None, None), (missing.c.clone(), missing.value.clone()));
+        assert!(satisfaction.to_string().contains("  main.d has no value\n"));
 
-        Ok(())
+        assert!(constraints.satisfies_with_signals(&signals).is_err());
     }
 }
@@ -1,7 +1,9 @@
 mod constraint;
+mod satisfaction;
 mod signal;
 mod utils;
 
-pub use self::constraint::Constraints;
+pub use self::constraint::{Constraints, Provenance};
+pub use self::satisfaction::{Satisfaction, SignalValue, Unsatisfied};
 pub use self::signal::{Signal, SignalName, Signals};
 pub use self::utils::print_info;
@@ -0,0 +1,94 @@
+use std::fmt;
+
+use super::constraint::Provenance;
+use crate::algebra::{SignalId, FS};
+
+/// A signal of a constraint with its value, if it has one
+#[derive(Debug, Clone, Serialize)]
+pub struct SignalValue {
+    pub id: SignalId,
+    pub name: String,
+    pub value: Option<FS>,
+}
+
+/// A constraint that does not hold, or that cannot be checked because some
+///   of its signals have no value
+#[derive(Debug, Clone, Serialize)]
+pub struct Unsatisfied {
+    pub index: usize,
+    // the constraint with the signal names
+    pub constraint: String,
+    // the values of a, b, c and a*b+c, if all their signals have a value
+    pub a: Option<FS>,
+    pub b: Option<FS>,
+    pub c: Option<FS>,
+    pub value: Option<FS>,
+    // the signals of the constraint, without `one`
+    pub signals: Vec<SignalValue>,
+    pub provenance: Option<Provenance>,
+}
+
+impl Unsatisfied {
+    pub fn has_missing_values(&self) -> bool {
+        self.signals.iter().any(|signal| signal.value.is_none())
+    }
+}
+
+impl fmt::Display for Unsatisfied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "constraint #{} {}", self.index, self.constraint)?;
+        if let Some(provenance) = &self.provenance {
+            write!(f, " generated at {}", provenance.position())?;
+        }
+        writeln!(f)?;
+        let value = |v: &Option<FS>| v.as_ref().map_or("?".to_string(), FS::to_string);
+        writeln!(
+            f,
+            "  a = {}, b = {}, c = {}, a*b+c = {}",
+            value(&self.a),
+            value(&self.b),
+            value(&self.c),
+            value(&self.value)
+        )?;
+        for signal in &self.signals {
+            match &signal.value {
+                Some(v) => writeln!(f, "  {} = {}", signal.name, v)?,
+                None => writeln!(f, "  {} has no value", signal.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The constraints that are not satisfied by the signal values
+#[derive(Debug, Clone, Serialize)]
+pub struct Satisfaction {
+    // number of checked constraints
+    pub constraints: usize,
+    pub unsatisfied: Vec<Unsatisfied>,
+}
+
+impl Satisfaction {
+    pub fn is_satisfied(&self) -> bool {
+        self.unsatisfied.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl fmt::Display for Satisfaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} of {} constraints are not satisfied",
+            self.unsatisfied.len(),
+            self.constraints
+        )?;
+        for unsatisfied in &self.unsatisfied {
+            write!(f, "{}", unsatisfied)?;
+        }
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@ use std::fmt::{Debug, Formatter};
 use za_parser::ast::SignalType;
 
 use crate::algebra;
-use crate::algebra::SignalId;
+use crate::algebra::{SignalId, FS, LC};
 
 #[derive(Clone)]
 pub struct SignalName(pub Rc<String>); // see E0210
@@ -162,6 +162,48 @@ impl Signals {
         inputs
     }
 
+    /// circom `.sym` file, a `signal id,witness index,component index,full name`
+    ///   line for each signal but `one`. The witness index skips the signals
+    ///   removed by the optimizer, that have -1, and the components are
+    ///   numbered in the order of their first signal.
+    pub fn sym(&self, removed_signals: &[SignalId]) -> String {
+        let mut removed = removed_signals.iter().peekable();
+        let mut components = HashMap::new();
+        let mut witness = 0;
+        let mut sym = String::new();
+        for id in 1..self.len() {
+            let name = self.get_by_id(id).unwrap().full_name.to_string();
+            let component = name.rfind('.').map_or("", |p| &name[..p]).to_string();
+            let next = components.len();
+            let component = *components.entry(component).or_insert(next);
+            let witness = if removed.peek() == Some(&&id) {
+                removed.next();
+                -1
+            } else {
+                witness += 1;
+                witness
+            };
+            sym.push_str(&format!("{},{},{},{}\n", id, witness, component, name));
+        }
+        sym
+    }
+
+    /// Value of a linear combination with the signal values
+    pub fn eval_lc(&self, lc: &LC) -> Result<FS, String> {
+        lc.iter().fold(Ok(FS::zero()), |acc, (s, v)| {
+            let s_val = if *s == 0 {
+                FS::one()
+            } else {
+                let s_val = &*self.get_by_id(*s).unwrap();
+                match &s_val.value {
+                    Some(algebra::Value::FieldScalar(fs)) => fs.clone(),
+                    _ => return Err(format!("signal bad value {:?}", s_val)),
+                }
+            };
+            Ok(&acc? + &(v * &s_val))
+        })
+    }
+
     pub fn to_string(&self, id: SignalId) -> String {
         let s = &self.ids[id as usize];
         format!("{:?}:{:?}:{:?}", s.full_name, s.xtype, s.value)
@@ -189,3 +231,25 @@ impl Debug for Signals {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sym() {
+        let mut signals = Signals::default();
+        for (name, xtype) in &[
+            ("main.a", SignalType::PublicInput),
+            ("main.n.in", SignalType::PrivateInput),
+            ("main.n.out", SignalType::Output),
+            ("main.b", SignalType::Output),
+        ] {
+            signals.insert(name.to_string(), *xtype, None);
+        }
+        assert_eq!(
+            "1,1,0,main.a\n2,-1,1,main.n.in\n3,2,1,main.n.out\n4,3,0,main.b\n",
+            signals.sym(&[2])
+        );
+    }
+}
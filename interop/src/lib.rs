@@ -2,8 +2,9 @@
 mod test {
     use std::fs::{read_dir, File};
     use std::io::prelude::*;
+    use std::path::Path;
     use za_compiler::tester;
-    use za_parser::parse;
+    use za_parser::{comments, format_source, parse};
 
     #[test]
     fn circomlib_parse() {
@@ -23,6 +24,37 @@ mod test {
         }
     }
 
+    fn format_tree(dir: &Path) {
+        for path in read_dir(dir).unwrap() {
+            let path = path.unwrap().path();
+            if path.is_dir() {
+                format_tree(&path);
+            } else if path.to_string_lossy().ends_with(".circom") {
+                println!("+++ format testing {} +++", path.display());
+                let mut contents = String::new();
+                File::open(&path)
+                    .expect("Unable to open the file")
+                    .read_to_string(&mut contents)
+                    .expect("Unable to read the file");
+                let formatted = format_source(&contents).unwrap();
+                assert_eq!(formatted, format_source(&formatted).unwrap());
+                assert_eq!(
+                    format!("{:?}", parse(&contents).unwrap()),
+                    format!("{:?}", parse(&formatted).unwrap())
+                );
+                assert_eq!(
+                    comments(&contents).unwrap().len(),
+                    comments(&formatted).unwrap().len()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn circomlib_format() {
+        format_tree(Path::new("./circuits/circomlib/circuits"));
+    }
+
     #[test]
     fn circomlib_tests() {
         match tester::run_embeeded_tests(
@@ -0,0 +1,438 @@
+use std::path::Path;
+
+use za_compiler::evaluator::{Evaluator, Mode, Scope, ScopeValue};
+use za_compiler::project::Project;
+use za_compiler::types::{Constraints, Signals};
+use za_parser::ast::{BodyElementP, ExpressionP, Meta, SignalType, StatementP, VariableType};
+
+use super::text;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DefinitionKind {
+    Template,
+    Function,
+}
+
+/// A signal declared in the body of a template
+#[derive(Debug, Clone)]
+pub struct SignalDecl {
+    pub name: String,
+    pub xtype: SignalType,
+    pub meta: Meta,
+    pub decl: String,
+}
+
+impl SignalDecl {
+    /// Inputs and outputs are accessible as component pins
+    pub fn is_pin(&self) -> bool {
+        self.xtype != SignalType::Internal
+    }
+}
+
+/// A template or function, defined in the file or in its includes
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub kind: DefinitionKind,
+    pub name: String,
+    pub args: Vec<String>,
+    pub path: String,
+    pub meta: Meta,
+    pub signals: Vec<SignalDecl>,
+}
+
+impl Definition {
+    pub fn signature(&self) -> String {
+        let kind = match self.kind {
+            DefinitionKind::Template => "template",
+            DefinitionKind::Function => "function",
+        };
+        format!("{} {}({})", kind, self.name, self.args.join(","))
+    }
+
+    pub fn hover(&self) -> String {
+        let mut hover = self.signature();
+        for signal in &self.signals {
+            hover.push_str("\n    ");
+            hover.push_str(&signal.decl);
+        }
+        hover
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub path: String,
+    pub meta: Meta,
+    pub message: String,
+}
+
+/// Where the symbol under the cursor is defined
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub path: String,
+    pub meta: Meta,
+}
+
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub label: String,
+    pub detail: String,
+    pub kind: Option<DefinitionKind>,
+}
+
+/// Parse and `Mode::Collect` evaluation results of an open file
+pub struct Analysis {
+    pub path: String,
+    pub text: String,
+    pub parsed: bool,
+    pub elements: Vec<BodyElementP>,
+    pub definitions: Vec<Definition>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Analysis {
+    pub fn new(path: &str, text: &str) -> Self {
+        let mut diagnostics = Vec::new();
+        let (parsed, elements) = match za_parser::parse(text) {
+            Ok(elements) => (true, elements),
+            Err(err) => {
+                for (message, meta) in err.errors() {
+                    diagnostics.push(Diagnostic {
+                        path: path.to_string(),
+                        meta,
+                        message,
+                    });
+                }
+                (false, Vec::new())
+            }
+        };
+        let definitions = if parsed {
+            collect_definitions(path, text, &mut diagnostics)
+        } else {
+            Vec::new()
+        };
+        Analysis {
+            path: path.to_string(),
+            text: text.to_string(),
+            parsed,
+            elements,
+            definitions,
+            diagnostics,
+        }
+    }
+
+    /// If the file cannot be parsed, for example while typing, keep using the
+    ///   definitions of its last valid version
+    pub fn with_fallback(mut self, previous: &Analysis) -> Self {
+        if !self.parsed {
+            self.elements = previous.elements.clone();
+            self.definitions = previous.definitions.clone();
+        }
+        self
+    }
+
+    pub fn definition(&self, name: &str) -> Option<&Definition> {
+        self.definitions.iter().find(|d| d.name == name)
+    }
+
+    /// The location of the template, function or pin under the cursor
+    pub fn location_at(&self, offset: usize) -> Option<Location> {
+        let (start, end) = text::identifier_at(&self.text, offset);
+        let name = &self.text[start..end];
+        if let Some(template) = self.pin_template(start) {
+            let signal = template.signals.iter().find(|s| s.name == name)?;
+            return Some(Location {
+                path: template.path.clone(),
+                meta: signal.meta.clone(),
+            });
+        }
+        self.definition(name).map(|d| Location {
+            path: d.path.clone(),
+            meta: d.meta.clone(),
+        })
+    }
+
+    /// The description of the template, function or pin under the cursor
+    pub fn hover_at(&self, offset: usize) -> Option<String> {
+        let (start, end) = text::identifier_at(&self.text, offset);
+        let name = &self.text[start..end];
+        if let Some(template) = self.pin_template(start) {
+            let signal = template.signals.iter().find(|s| s.name == name)?;
+            return Some(format!("{}\n    {}", template.signature(), signal.decl));
+        }
+        self.definition(name).map(Definition::hover)
+    }
+
+    /// The component pins after a `.`, otherwise the templates and functions
+    pub fn completions_at(&self, offset: usize) -> Vec<Completion> {
+        let (start, _) = text::identifier_at(&self.text, offset);
+        if let Some(template) = self.pin_template(start) {
+            return template
+                .signals
+                .iter()
+                .filter(|s| s.is_pin())
+                .map(|s| Completion {
+                    label: s.name.clone(),
+                    detail: s.decl.clone(),
+                    kind: None,
+                })
+                .collect();
+        }
+        self.definitions
+            .iter()
+            .map(|d| Completion {
+                label: d.name.clone(),
+                detail: d.signature(),
+                kind: Some(d.kind),
+            })
+            .collect()
+    }
+
+    // the template of the component if the identifier starting at `start` is a pin
+    fn pin_template(&self, start: usize) -> Option<&Definition> {
+        let component = text::pin_component(&self.text, start)?;
+
+        // look first in the template being edited
+        let mut templates = self
+            .elements
+            .iter()
+            .filter_map(|element| match element {
+                BodyElementP::TemplateDef { meta, stmt, .. } => Some((meta, stmt)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        templates.sort_by_key(|(meta, _)| !(meta.start <= start && start <= meta.end));
+
+        templates
+            .into_iter()
+            .filter_map(|(_, stmt)| component_template(stmt, component))
+            .filter_map(|name| self.definition(&name))
+            .find(|d| d.kind == DefinitionKind::Template)
+    }
+}
+
+// loads the project manifest of the file, if any
+fn find_project(dir: &Path) -> Option<Project> {
+    dir.ancestors()
+        .filter_map(|root| Project::discover(&root.to_string_lossy()).ok())
+        .find_map(|project| project)
+}
+
+fn collect_definitions(
+    path: &str,
+    text: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<Definition> {
+    let dir = Path::new(path).parent().unwrap_or_else(|| Path::new("."));
+
+    let mut eval = Evaluator::new(Mode::Collect, Signals::default(), Constraints::default());
+    if let Some(project) = find_project(dir) {
+        project.configure(&mut eval);
+    }
+    eval.path = dir.to_path_buf();
+    eval.current_file = path.to_string();
+
+    let mut scope = Scope::new(true, None, "root".to_string());
+    eval.collected_errors = Some(Vec::new());
+    if let Err(err) = eval.eval_inline(&mut scope, text) {
+        if let Some(ctx) = &eval.last_error {
+            diagnostics.push(Diagnostic {
+                path: ctx.file.clone(),
+                meta: ctx.meta.clone(),
+                message: format!("{:?}", err),
+            });
+        }
+    }
+    eval.check_references(&scope);
+    for (err, location) in eval.collected_errors.take().unwrap_or_default() {
+        diagnostics.push(Diagnostic {
+            path: location.file,
+            meta: location.meta,
+            message: err.to_string(),
+        });
+    }
+
+    let mut definitions = scope
+        .vars
+        .borrow()
+        .iter()
+        .filter_map(|(name, value)| match value {
+            ScopeValue::Template {
+                meta,
+                args,
+                stmt,
+                path,
+            } => Some(Definition {
+                kind: DefinitionKind::Template,
+                name: name.clone(),
+                args: args.clone(),
+                path: path.clone(),
+                meta: meta.clone(),
+                signals: signal_decls(stmt),
+            }),
+            ScopeValue::Function {
+                meta, args, path, ..
+            } => Some(Definition {
+                kind: DefinitionKind::Function,
+                name: name.clone(),
+                args: args.clone(),
+                path: path.clone(),
+                meta: meta.clone(),
+                signals: Vec::new(),
+            }),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    definitions.sort_by(|a, b| a.name.cmp(&b.name));
+    definitions
+}
+
+// signals declared in the template body
+fn signal_decls(stmt: &StatementP) -> Vec<SignalDecl> {
+    match stmt {
+        StatementP::Block { stmts, .. } => stmts
+            .iter()
+            .filter_map(|stmt| match &**stmt {
+                StatementP::Declaration {
+                    meta,
+                    xtype: VariableType::Signal(xtype),
+                    name,
+                    ..
+                } => Some(SignalDecl {
+                    name: name.name.clone(),
+                    xtype: *xtype,
+                    meta: meta.clone(),
+                    decl: format!("{:?}", stmt).trim_end_matches(';').to_string(),
+                }),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// the template used to instantiate the component in the statement
+fn component_template(stmt: &StatementP, component: &str) -> Option<String> {
+    use StatementP::*;
+    let template = |value: &ExpressionP| match value {
+        ExpressionP::FunctionCall { name, .. } => Some(name.clone()),
+        _ => None,
+    };
+    match stmt {
+        Declaration {
+            xtype: VariableType::Component,
+            name,
+            init: Some((_, value)),
+            ..
+        } if name.name == component => template(value),
+        Substitution { name, value, .. } if name.name == component => template(value),
+        Block { stmts, .. } => stmts
+            .iter()
+            .find_map(|stmt| component_template(stmt, component)),
+        IfThenElse { xthen, xelse, .. } => component_template(xthen, component).or_else(|| {
+            xelse
+                .as_ref()
+                .and_then(|xelse| component_template(xelse, component))
+        }),
+        For { stmt, .. } | While { stmt, .. } => component_template(stmt, component),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    fn write_file(dir: &Path, name: &str, content: &str) -> String {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_analysis() {
+        let dir = std::env::temp_dir().join("za_lsp_test_analysis");
+        fs::create_dir_all(&dir).unwrap();
+
+        let lib = write_file(
+            &dir,
+            "lib.circom",
+            "
+            template Mul(n) {
+                signal input a;
+                signal input b[n];
+                signal tmp;
+                signal output c;
+                c <== a * b[0];
+            }
+            function square(x) {
+                return x*x;
+            }",
+        );
+        let text = "
+            include \"lib.circom\";
+            template T() {
+                signal input x;
+                component m[2];
+                m[0] = Mul(2);
+                m[0].a <== x;
+            }";
+        let path = write_file(&dir, "circuit.circom", text);
+        let analysis = Analysis::new(&path, text);
+        assert!(analysis.diagnostics.is_empty());
+
+        // go to definition of templates and pins across includes
+        let location = analysis.location_at(text.find("Mul").unwrap()).unwrap();
+        assert_eq!(lib, location.path);
+        let lib_text = fs::read_to_string(&lib).unwrap();
+        assert!(lib_text[location.meta.start..].starts_with("template Mul"));
+
+        let location = analysis.location_at(text.find(".a").unwrap() + 1).unwrap();
+        assert!(lib_text[location.meta.start..].starts_with("signal input a"));
+
+        // hover
+        assert_eq!(
+            "template Mul(n)\n    signal input a\n    signal input b[n]\n    signal tmp\n    signal output c",
+            analysis.hover_at(text.find("Mul").unwrap() + 1).unwrap()
+        );
+        assert_eq!(
+            "template Mul(n)\n    signal input a",
+            analysis.hover_at(text.find(".a").unwrap() + 1).unwrap()
+        );
+
+        // completion
+        let labels = |completions: Vec<Completion>| {
+            completions.into_iter().map(|c| c.label).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec!["Mul", "T", "square"],
+            labels(analysis.completions_at(0))
+        );
+        let edited = text.replace("m[0].a <== x;", "m[0].");
+        let analysis = Analysis::new(&path, &edited).with_fallback(&analysis);
+        assert!(!analysis.parsed);
+        assert_eq!(
+            vec!["a", "b", "c"],
+            labels(analysis.completions_at(edited.rfind('.').unwrap() + 1))
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let dir = std::env::temp_dir().join("za_lsp_test_diagnostics");
+        fs::create_dir_all(&dir).unwrap();
+
+        let text = "template T() { signal input a }";
+        let path = write_file(&dir, "circuit.za", text);
+        let analysis = Analysis::new(&path, text);
+        assert_eq!(1, analysis.diagnostics.len());
+        assert_eq!(text.find('}').unwrap(), analysis.diagnostics[0].meta.start);
+
+        let lib = write_file(&dir, "lib.za", "template A() {\n a = ; }");
+        let text = "include \"lib.za\";";
+        let analysis = Analysis::new(&path, text);
+        assert_eq!(1, analysis.diagnostics.len());
+        assert_eq!(lib, analysis.diagnostics[0].path);
+    }
+}
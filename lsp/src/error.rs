@@ -0,0 +1,27 @@
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Protocol(lsp_server::ProtocolError),
+    Json(serde_json::error::Error),
+    Disconnected(String),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<lsp_server::ProtocolError> for Error {
+    fn from(err: lsp_server::ProtocolError) -> Self {
+        Error::Protocol(err)
+    }
+}
+
+impl From<serde_json::error::Error> for Error {
+    fn from(err: serde_json::error::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
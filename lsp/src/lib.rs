@@ -0,0 +1,13 @@
+extern crate lsp_server;
+extern crate lsp_types;
+extern crate serde_json;
+extern crate za_compiler;
+extern crate za_parser;
+
+#[macro_use]
+extern crate log;
+
+pub mod analysis;
+pub mod error;
+pub mod server;
+pub mod text;
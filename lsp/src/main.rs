@@ -0,0 +1,33 @@
+extern crate lsp_server;
+extern crate stderrlog;
+extern crate za_lsp;
+
+#[macro_use]
+extern crate log;
+
+use lsp_server::Connection;
+
+use za_lsp::error::Result;
+use za_lsp::server::{self, Server};
+
+fn run() -> Result<()> {
+    // stdout is used by the protocol, log goes to stderr
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = serde_json::to_value(server::capabilities())?;
+    connection.initialize(capabilities)?;
+    info!("Initialized");
+
+    Server::new(connection).run()?;
+    io_threads.join()?;
+    info!("Shutdown");
+    Ok(())
+}
+
+fn main() {
+    stderrlog::new().verbosity(2).init().unwrap();
+
+    if let Err(err) = run() {
+        error!("{:?}", err);
+        std::process::exit(1);
+    }
+}
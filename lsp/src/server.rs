@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::fs;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{Completion, GotoDefinition, HoverRequest, Request as _};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DidSaveTextDocumentParams, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability,
+    MarkupContent, MarkupKind, OneOf, Position, PublishDiagnosticsParams, Range,
+    ServerCapabilities, TextDocumentPositionParams, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use za_parser::ast::Meta;
+
+use super::analysis::{Analysis, DefinitionKind};
+use super::error::{Error, Result};
+use super::text;
+
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![".".to_string()]),
+            ..CompletionOptions::default()
+        }),
+        ..ServerCapabilities::default()
+    }
+}
+
+pub struct Server {
+    connection: Connection,
+
+    // analysis of the open documents
+    documents: HashMap<Url, Analysis>,
+
+    // files with published diagnostics for each open document, they are
+    //   cleared when the errors are fixed
+    published: HashMap<Url, Vec<Url>>,
+}
+
+impl Server {
+    pub fn new(connection: Connection) -> Self {
+        Server {
+            connection,
+            documents: HashMap::new(),
+            published: HashMap::new(),
+        }
+    }
+
+    /// Processes the client messages until shutdown
+    pub fn run(&mut self) -> Result<()> {
+        let receiver = self.connection.receiver.clone();
+        for msg in &receiver {
+            match msg {
+                Message::Request(req) => {
+                    if self.connection.handle_shutdown(&req)? {
+                        return Ok(());
+                    }
+                    self.on_request(req)?;
+                }
+                // a notification cannot be answered, the malformed ones are skipped
+                Message::Notification(not) => match self.on_notification(not) {
+                    Err(Error::Json(err)) => warn!("Invalid notification params: {}", err),
+                    res => res?,
+                },
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn send(&self, msg: Message) -> Result<()> {
+        self.connection
+            .sender
+            .send(msg)
+            .map_err(|err| Error::Disconnected(err.to_string()))
+    }
+
+    fn on_request(&mut self, req: Request) -> Result<()> {
+        let id = req.id.clone();
+        let result = match req.method.as_str() {
+            GotoDefinition::METHOD => self.handle(req.params, Self::definition),
+            HoverRequest::METHOD => self.handle(req.params, Self::hover),
+            Completion::METHOD => self.handle(req.params, Self::completion),
+            _ => {
+                let message = format!("unsupported request {}", req.method);
+                let code = ErrorCode::MethodNotFound as i32;
+                return self.send(Response::new_err(id, code, message).into());
+            }
+        };
+        let response = match result {
+            Ok(result) => Response::new_ok(id, result),
+            // the malformed requests are answered without stopping the server
+            Err(err) => Response::new_err(id, ErrorCode::InvalidParams as i32, err.to_string()),
+        };
+        self.send(response.into())
+    }
+
+    // parses the request params and serializes the result of `handler`
+    fn handle<P, R>(
+        &self,
+        params: serde_json::Value,
+        handler: fn(&Self, P) -> R,
+    ) -> serde_json::Result<serde_json::Value>
+    where
+        P: DeserializeOwned,
+        R: Serialize,
+    {
+        serde_json::to_value(handler(self, serde_json::from_value(params)?))
+    }
+
+    fn on_notification(&mut self, not: Notification) -> Result<()> {
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams = serde_json::from_value(not.params)?;
+                info!("Opened {}", params.text_document.uri);
+                self.analyze(params.text_document.uri, &params.text_document.text)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams = serde_json::from_value(not.params)?;
+                match params.content_changes.into_iter().last() {
+                    Some(change) => self.analyze(params.text_document.uri, &change.text),
+                    None => Ok(()),
+                }
+            }
+            DidSaveTextDocument::METHOD => {
+                let _: DidSaveTextDocumentParams = serde_json::from_value(not.params)?;
+                // the saved file can be included by other open documents
+                let documents = self
+                    .documents
+                    .iter()
+                    .map(|(uri, analysis)| (uri.clone(), analysis.text.clone()))
+                    .collect::<Vec<_>>();
+                for (uri, text) in documents {
+                    self.analyze(uri, &text)?;
+                }
+                Ok(())
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams = serde_json::from_value(not.params)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                let mut files = self.published.remove(&uri).unwrap_or_default();
+                files.push(uri);
+                for file in files {
+                    self.publish(file, Vec::new())?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn analyze(&mut self, uri: Url, text: &str) -> Result<()> {
+        let path = uri_to_path(&uri);
+        let mut analysis = Analysis::new(&path, text);
+        if let Some(previous) = self.documents.get(&uri) {
+            analysis = analysis.with_fallback(previous);
+        }
+
+        // diagnostics can be located in the included files
+        let mut files: HashMap<Url, Vec<lsp_types::Diagnostic>> = HashMap::new();
+        for file in self.published.remove(&uri).unwrap_or_default() {
+            files.insert(file, Vec::new());
+        }
+        files.insert(uri.clone(), Vec::new());
+        for diagnostic in &analysis.diagnostics {
+            let (file, range) = if diagnostic.path == path {
+                (uri.clone(), range(&analysis.text, &diagnostic.meta))
+            } else {
+                let file = Url::from_file_path(&diagnostic.path).unwrap_or_else(|_| uri.clone());
+                (file, self.range(&diagnostic.path, &diagnostic.meta))
+            };
+            files.entry(file).or_default().push(lsp_types::Diagnostic {
+                range,
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("za".to_string()),
+                message: diagnostic.message.clone(),
+                ..lsp_types::Diagnostic::default()
+            });
+        }
+
+        self.published.insert(
+            uri.clone(),
+            files
+                .iter()
+                .filter(|(file, diagnostics)| **file != uri && !diagnostics.is_empty())
+                .map(|(file, _)| file.clone())
+                .collect(),
+        );
+        self.documents.insert(uri, analysis);
+
+        for (file, diagnostics) in files {
+            self.publish(file, diagnostics)?;
+        }
+        Ok(())
+    }
+
+    fn publish(&self, uri: Url, diagnostics: Vec<lsp_types::Diagnostic>) -> Result<()> {
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        };
+        self.send(Notification::new(PublishDiagnostics::METHOD.to_string(), params).into())
+    }
+
+    // the range of the span in a file, which can be open or not
+    fn range(&self, path: &str, meta: &Meta) -> Range {
+        match self.documents.values().find(|d| d.path == path) {
+            Some(analysis) => range(&analysis.text, meta),
+            None => range(&fs::read_to_string(path).unwrap_or_default(), meta),
+        }
+    }
+
+    // the document and the offset of a position
+    fn document_at(&self, params: &TextDocumentPositionParams) -> Option<(&Analysis, usize)> {
+        let analysis = self.documents.get(&params.text_document.uri)?;
+        let offset = text::offset(&analysis.text, params.position);
+        Some((analysis, offset))
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let (analysis, offset) = self.document_at(&params.text_document_position_params)?;
+        let location = analysis.location_at(offset)?;
+        Some(GotoDefinitionResponse::Scalar(lsp_types::Location {
+            uri: Url::from_file_path(&location.path).ok()?,
+            range: self.range(&location.path, &location.meta),
+        }))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let (analysis, offset) = self.document_at(&params.text_document_position_params)?;
+        let hover = analysis.hover_at(offset)?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```circom\n{}\n```", hover),
+            }),
+            range: None,
+        })
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let (analysis, offset) = self.document_at(&params.text_document_position)?;
+        let items = analysis
+            .completions_at(offset)
+            .into_iter()
+            .map(|completion| CompletionItem {
+                label: completion.label,
+                detail: Some(completion.detail),
+                kind: Some(match completion.kind {
+                    Some(DefinitionKind::Template) => CompletionItemKind::CLASS,
+                    Some(DefinitionKind::Function) => CompletionItemKind::FUNCTION,
+                    None => CompletionItemKind::FIELD,
+                }),
+                ..CompletionItem::default()
+            })
+            .collect();
+        Some(CompletionResponse::Array(items))
+    }
+}
+
+fn uri_to_path(uri: &Url) -> String {
+    match uri.to_file_path() {
+        Ok(path) => path.to_string_lossy().to_string(),
+        Err(_) => uri.path().to_string(),
+    }
+}
+
+fn range(text: &str, meta: &Meta) -> Range {
+    let start: Position = text::position(text, meta.start);
+    let end: Position = text::position(text, meta.end);
+    Range::new(start, end)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lsp_types::notification::Exit;
+    use lsp_types::request::Shutdown;
+    use lsp_types::{TextDocumentIdentifier, TextDocumentItem};
+
+    #[test]
+    fn test_server() {
+        let dir = std::env::temp_dir().join("za_lsp_test_server");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("circuit.za");
+        let uri = Url::from_file_path(&path).unwrap();
+
+        let (server, client) = Connection::memory();
+        let handle = std::thread::spawn(move || Server::new(server).run());
+
+        let open = |text: &str| {
+            let params = DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(uri.clone(), "circom".into(), 0, text.into()),
+            };
+            let not = Notification::new(DidOpenTextDocument::METHOD.to_string(), params);
+            client.sender.send(not.into()).unwrap();
+            match client.receiver.recv().unwrap() {
+                Message::Notification(not) => {
+                    serde_json::from_value::<PublishDiagnosticsParams>(not.params).unwrap()
+                }
+                msg => panic!("unexpected {:?}", msg),
+            }
+        };
+
+        let diagnostics = open("template T() {\n signal input a\n}");
+        assert_eq!(uri, diagnostics.uri);
+        assert_eq!(1, diagnostics.diagnostics.len());
+        assert_eq!(2, diagnostics.diagnostics[0].range.start.line);
+
+        let diagnostics = open("template T(n) {\n signal input a;\n}");
+        assert!(diagnostics.diagnostics.is_empty());
+
+        let params = HoverParams {
+            text_document_position_params: TextDocumentPositionParams::new(
+                TextDocumentIdentifier::new(uri.clone()),
+                Position::new(0, 9),
+            ),
+            work_done_progress_params: Default::default(),
+        };
+        let req = Request::new(1.into(), HoverRequest::METHOD.to_string(), params);
+        client.sender.send(req.into()).unwrap();
+        match client.receiver.recv().unwrap() {
+            Message::Response(resp) => {
+                let hover: Hover = serde_json::from_value(resp.result.unwrap()).unwrap();
+                match hover.contents {
+                    HoverContents::Markup(content) => assert_eq!(
+                        "```circom\ntemplate T(n)\n    signal input a\n```",
+                        content.value
+                    ),
+                    _ => panic!("unexpected hover"),
+                }
+            }
+            msg => panic!("unexpected {:?}", msg),
+        }
+
+        // malformed messages do not stop the server
+        let not = Notification::new(DidOpenTextDocument::METHOD.to_string(), ());
+        client.sender.send(not.into()).unwrap();
+        let req = Request::new(2.into(), HoverRequest::METHOD.to_string(), ());
+        client.sender.send(req.into()).unwrap();
+        match client.receiver.recv().unwrap() {
+            Message::Response(resp) => {
+                assert_eq!(ErrorCode::InvalidParams as i32, resp.error.unwrap().code)
+            }
+            msg => panic!("unexpected {:?}", msg),
+        }
+        let diagnostics = open("template T() {\n signal input a\n}");
+        assert_eq!(1, diagnostics.diagnostics.len());
+
+        let req = Request::new(3.into(), Shutdown::METHOD.to_string(), ());
+        client.sender.send(req.into()).unwrap();
+        client.receiver.recv().unwrap();
+        let not = Notification::new(Exit::METHOD.to_string(), ());
+        client.sender.send(not.into()).unwrap();
+        handle.join().unwrap().unwrap();
+    }
+}
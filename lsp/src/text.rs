@@ -0,0 +1,111 @@
+use lsp_types::Position;
+
+/// Converts a byte offset into a LSP position, where characters are counted
+///   in UTF-16 code units
+pub fn position(text: &str, offset: usize) -> Position {
+    let mut line = 0;
+    let mut character = 0;
+    for (pos, ch) in text.char_indices() {
+        if pos >= offset {
+            break;
+        }
+        if ch == '\n' {
+            line += 1;
+            character = 0;
+        } else {
+            character += ch.len_utf16() as u32;
+        }
+    }
+    Position::new(line, character)
+}
+
+/// Converts a LSP position into a byte offset
+pub fn offset(text: &str, position: Position) -> usize {
+    let mut line = 0;
+    let mut character = 0;
+    for (pos, ch) in text.char_indices() {
+        if line == position.line && character >= position.character {
+            return pos;
+        }
+        if ch == '\n' {
+            if line == position.line {
+                return pos;
+            }
+            line += 1;
+            character = 0;
+        } else if line == position.line {
+            character += ch.len_utf16() as u32;
+        }
+    }
+    text.len()
+}
+
+fn is_identifier(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_' || ch == '$'
+}
+
+/// The identifier around the offset, as its (start, end) byte offsets
+pub fn identifier_at(text: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(text.len());
+    let start = text[..offset]
+        .rfind(|ch| !is_identifier(ch))
+        .map(|pos| pos + 1)
+        .unwrap_or(0);
+    let end = text[offset..]
+        .find(|ch| !is_identifier(ch))
+        .map(|pos| offset + pos)
+        .unwrap_or_else(|| text.len());
+    (start, end)
+}
+
+/// If the identifier starting at `start` is a pin, `a.b[1].pin`, returns
+///   the name of the component variable, `b`
+pub fn pin_component(text: &str, start: usize) -> Option<&str> {
+    let before = text[..start].trim_end();
+    if !before.ends_with('.') {
+        return None;
+    }
+    // skip the component indexes
+    let mut end = before.len() - 1;
+    let mut depth = 0;
+    for (pos, ch) in before[..end].char_indices().rev() {
+        match ch {
+            ']' => depth += 1,
+            '[' => depth -= 1,
+            _ if depth == 0 => {
+                end = pos + ch.len_utf8();
+                break;
+            }
+            _ => {}
+        }
+    }
+    let (start, end) = identifier_at(text, end);
+    if start < end {
+        Some(&text[start..end])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_positions() {
+        let text = "template A() {\n  signal ñ;\n  a.b[i[1]].c <== 1;\n}";
+
+        assert_eq!(Position::new(0, 0), position(text, 0));
+        assert_eq!(Position::new(1, 2), position(text, 17));
+        assert_eq!(Position::new(1, 10), position(text, 26));
+        assert_eq!(Position::new(2, 0), position(text, 28));
+        for offset in &[0, 17, 26, 28] {
+            assert_eq!(*offset, super::offset(text, position(text, *offset)));
+        }
+
+        let c = text.find(".c").unwrap() + 1;
+        assert_eq!((c, c + 1), identifier_at(text, c));
+        assert_eq!(Some("b"), pin_component(text, c));
+        assert_eq!(None, pin_component(text, text.find("a.").unwrap()));
+    }
+}
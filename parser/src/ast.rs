@@ -16,6 +16,9 @@ impl Attributes {
     pub fn has_tag_test(&self) -> bool {
         self.has_tag("test")
     }
+    pub fn tags(&self) -> &[String] {
+        &self.0
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -87,6 +90,44 @@ pub enum ExpressionP {
         meta: Meta,
         values: Vec<Box<ExpressionP>>,
     },
+    Ternary {
+        meta: Meta,
+        cond: Box<ExpressionP>,
+        if_true: Box<ExpressionP>,
+        if_false: Box<ExpressionP>,
+    },
+    StringLiteral {
+        meta: Meta,
+        value: String,
+    },
+    Tuple {
+        meta: Meta,
+        values: Vec<Box<ExpressionP>>,
+    },
+    AnonymousComponent {
+        meta: Meta,
+        template: String,
+        params: Vec<Box<ExpressionP>>,
+        args: Vec<Box<ExpressionP>>,
+    },
+}
+
+impl ExpressionP {
+    pub fn meta(&self) -> &Meta {
+        use self::ExpressionP::*;
+        match self {
+            FunctionCall { meta, .. }
+            | Variable { meta, .. }
+            | Number { meta, .. }
+            | PrefixOp { meta, .. }
+            | InfixOp { meta, .. }
+            | Array { meta, .. }
+            | Ternary { meta, .. }
+            | StringLiteral { meta, .. }
+            | Tuple { meta, .. }
+            | AnonymousComponent { meta, .. } => meta,
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -152,6 +193,33 @@ pub enum StatementP {
         name: String,
         args: Vec<Box<ExpressionP>>,
     },
+    Tuple {
+        meta: Meta,
+        xtype: VariableType,
+        names: Vec<Box<VariableP>>,
+        op: Opcode,
+        value: Box<ExpressionP>,
+    },
+}
+
+impl StatementP {
+    pub fn meta(&self) -> &Meta {
+        use self::StatementP::*;
+        match self {
+            IfThenElse { meta, .. }
+            | For { meta, .. }
+            | While { meta, .. }
+            | Return { meta, .. }
+            | Declaration { meta, .. }
+            | Substitution { meta, .. }
+            | Block { meta, .. }
+            | SignalLeft { meta, .. }
+            | SignalRight { meta, .. }
+            | SignalEq { meta, .. }
+            | InternalCall { meta, .. }
+            | Tuple { meta, .. } => meta,
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -176,6 +244,11 @@ pub enum BodyElementP {
         meta: Meta,
         decl: Box<StatementP>,
     },
+    MainComponent {
+        meta: Meta,
+        public: Vec<String>,
+        decl: Box<StatementP>,
+    },
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -240,17 +313,23 @@ mod test {
     use super::super::lang;
 
     fn test_expression(expr: &str, expected: &str) {
-        let expr = lang::ExpressionParser::new().parse(expr).unwrap();
+        let expr = lang::ExpressionParser::new()
+            .parse(&mut Vec::new(), expr)
+            .unwrap();
         assert_eq!(&format!("{:?}", expr), expected);
     }
 
     fn test_statement(expr: &str) {
-        let parsed = lang::StatementParser::new().parse(expr).unwrap();
+        let parsed = lang::StatementParser::new()
+            .parse(&mut Vec::new(), expr)
+            .unwrap();
         assert_eq!(&format!("{:?}", parsed), expr);
     }
 
     fn test_bodyelement(expr: &str) {
-        let parsed = lang::BodyElementParser::new().parse(expr).unwrap();
+        let parsed = lang::BodyElementParser::new()
+            .parse(&mut Vec::new(), expr)
+            .unwrap();
         assert_eq!(&format!("{:?}", parsed), expr);
     }
 
@@ -316,6 +395,15 @@ mod test {
         test_expression("f(a*1,b(),c(1*2))", "f((a * 1),b(),c((1 * 2)))");
     }
 
+    #[test]
+    fn expression_circom2() {
+        test_expression("a ? b : c ? d : e", "(a ? b : (c ? d : e))");
+        test_expression("a == 1 ? b + 1 : c", "((a == 1) ? (b + 1) : c)");
+        test_expression("T(1)(a,b[1])", "T(1)(a,b[1])");
+        test_expression("(a,b)", "(a,b)");
+        test_expression("\"hola\"", "\"hola\"");
+    }
+
     #[test]
     fn statement_declaration() {
         test_statement("var a;");
@@ -374,6 +462,30 @@ mod test {
         test_statement("a === b;");
     }
 
+    #[test]
+    fn statement_circom2() {
+        test_statement("var a[2] = [b,c];");
+        test_statement("var (a,b) = (c,d);");
+        test_statement("(a,b) = (b,a);");
+        test_statement("(a,b[1]) <== T(1)(c);");
+        test_statement("log!(\"a\",a);");
+
+        let parse = |expr| {
+            format!(
+                "{:?}",
+                lang::StatementParser::new()
+                    .parse(&mut Vec::new(), expr)
+                    .unwrap()
+            )
+        };
+        assert_eq!("a += 1;", parse("a++;"));
+        assert_eq!("a[1] -= 1;", parse("a[1]--;"));
+        assert_eq!("assert!((a > 1));", parse("assert(a > 1);"));
+        assert!(lang::StatementParser::new()
+            .parse(&mut Vec::new(), "(a,1) = (b,c);")
+            .is_err());
+    }
+
     #[test]
     fn statement_block() {
         test_statement("if (a) {b = c; b = c;}");
@@ -386,5 +498,7 @@ mod test {
         test_bodyelement("function f1(a,b,c) {a += b;}");
         test_bodyelement("template f1(a,b,c) {a += b;}");
         test_bodyelement("var a;");
+        test_bodyelement("component main {public [a,b]} = T(1);");
+        test_bodyelement("component main {public []} = T();");
     }
 }
@@ -16,6 +16,21 @@ impl Debug for BodyElementP {
                 name, args, stmt, ..
             } => write!(fmt, "template {}({}) {:?}", name, args.join(","), stmt),
             Declaration { decl, .. } => write!(fmt, "{:?}", decl),
+            MainComponent { public, decl, .. } => match &**decl {
+                StatementP::Declaration {
+                    name,
+                    init: Some((op, value)),
+                    ..
+                } => write!(
+                    fmt,
+                    "component {:?} {{public [{}]}} {:?} {:?};",
+                    name,
+                    public.join(","),
+                    op,
+                    value
+                ),
+                _ => unreachable!(),
+            },
         }
     }
 }
@@ -126,6 +141,23 @@ impl Debug for StatementP {
             } => write!(fmt, "{:?} {:?} {:?};", value, op, name),
             SignalEq { lhe, op, rhe, .. } => write!(fmt, "{:?} {:?} {:?};", lhe, op, rhe),
             InternalCall { name, args, .. } => write!(fmt, "{}!({});", name, comma_concat(args)),
+            Tuple {
+                xtype,
+                names,
+                op,
+                value,
+                ..
+            } => {
+                let names = names
+                    .iter()
+                    .map(|name| format!("{:?}", name))
+                    .collect::<Vec<String>>()
+                    .join(",");
+                match xtype {
+                    VariableType::Empty => write!(fmt, "({}) {:?} {:?};", names, op, value),
+                    _ => write!(fmt, "{:?} ({}) {:?} {:?};", xtype, names, op, value),
+                }
+            }
         }
     }
 }
@@ -170,6 +202,26 @@ impl Debug for ExpressionP {
             InfixOp { lhe, op, rhe, .. } => write!(fmt, "({:?} {:?} {:?})", lhe, op, rhe),
             Array { values, .. } => write!(fmt, "[{}]", comma_concat(values)),
             FunctionCall { name, args, .. } => write!(fmt, "{}({})", name, comma_concat(args)),
+            Ternary {
+                cond,
+                if_true,
+                if_false,
+                ..
+            } => write!(fmt, "({:?} ? {:?} : {:?})", cond, if_true, if_false),
+            StringLiteral { value, .. } => write!(fmt, "\"{}\"", value),
+            Tuple { values, .. } => write!(fmt, "({})", comma_concat(values)),
+            AnonymousComponent {
+                template,
+                params,
+                args,
+                ..
+            } => write!(
+                fmt,
+                "{}({})({})",
+                template,
+                comma_concat(params),
+                comma_concat(args)
+            ),
         }
     }
 }
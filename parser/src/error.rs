@@ -1,8 +1,29 @@
-use super::ast::Meta;
+use super::ast::{BodyElementP, Meta};
 
 #[derive(Debug)]
 pub enum Error {
     ParseError(String, Meta),
+    // all the errors when the parser has recovered from them, with the
+    //   elements that could be parsed
+    ParseErrors(Vec<(String, Meta)>, Vec<BodyElementP>),
+}
+
+impl Error {
+    /// The message and location of each error
+    pub fn errors(&self) -> Vec<(String, Meta)> {
+        match self {
+            Error::ParseError(message, meta) => vec![(message.clone(), meta.clone())],
+            Error::ParseErrors(errors, _) => errors.clone(),
+        }
+    }
+
+    /// The elements parsed by recovering from the errors
+    pub fn recovered(&self) -> &[BodyElementP] {
+        match self {
+            Error::ParseError(_, _) => &[],
+            Error::ParseErrors(_, elements) => elements,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
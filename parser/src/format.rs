@@ -0,0 +1,746 @@
+use std::cell::RefCell;
+
+use super::ast::*;
+use super::error::{Error, Result};
+use super::parse::{self, Lang};
+
+const INDENT: &str = "    ";
+const MAX_WIDTH: usize = 100;
+
+/// Formats a circuit source, normalizing the indentation and the spacing
+///   of the operators, the comments are kept in place
+pub fn format_source(code: &str) -> Result<String> {
+    let pp = parse::preprocess(code)?;
+    let lang = Lang::detect(&pp);
+    let elements = parse::parse_ast(&pp, lang)?;
+    format_elements(code, &pp, lang, &elements)
+}
+
+// prints the parsed elements of `code`, the elements that cannot be printed
+//   are returned as an error
+fn format_elements(code: &str, pp: &str, lang: Lang, elements: &[BodyElementP]) -> Result<String> {
+    let mut fragments = parse::comments(code)?;
+    if lang == Lang::Circom2 {
+        fragments.extend(parse::pragmas(pp));
+        fragments.sort();
+    }
+
+    let mut formatter = Formatter {
+        code,
+        pp,
+        fragments,
+        next: 0,
+        last: 0,
+        out: String::new(),
+        indent: 0,
+        escape_attrs: code.contains("/*#["),
+        unsupported: RefCell::new(None),
+    };
+    formatter.body(elements);
+    if let Some((message, meta)) = formatter.unsupported.into_inner() {
+        return Err(Error::ParseError(message, meta));
+    }
+
+    let out = formatter.out.trim_end();
+    if out.is_empty() {
+        Ok(String::new())
+    } else {
+        Ok(format!("{}\n", out))
+    }
+}
+
+struct Formatter<'a> {
+    code: &'a str,
+    pp: &'a str,
+
+    // comments and pragmas, printed verbatim
+    fragments: Vec<(usize, usize)>,
+    next: usize,
+
+    // source location of the last printed element
+    last: usize,
+
+    out: String,
+    indent: usize,
+    escape_attrs: bool,
+
+    // the first element that cannot be printed, the source is not
+    //   formatted instead of dropping it from the output
+    unsupported: RefCell<Option<(String, Meta)>>,
+}
+
+impl<'a> Formatter<'a> {
+    // records an element that cannot be printed, the returned text is empty
+    fn unsupported(&self, what: &str, meta: &Meta) -> String {
+        let mut unsupported = self.unsupported.borrow_mut();
+        if unsupported.is_none() {
+            *unsupported = Some((format!("cannot format {}", what), meta.clone()));
+        }
+        String::new()
+    }
+
+    // indents a new line, keeping a single blank line if there is one
+    //   in the source before `pos`
+    fn newline(&mut self, pos: usize) {
+        let blank = pos > self.last && self.code[self.last..pos].matches('\n').count() > 1;
+        if blank && !self.out.is_empty() && !self.out.ends_with("{\n") {
+            self.blank_line();
+        }
+        self.out.push_str(&INDENT.repeat(self.indent));
+    }
+
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn push_fragment(&mut self, start: usize, end: usize) {
+        let text = self.code[start..end]
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.out.push_str(&text);
+        self.next += 1;
+        self.last = end;
+    }
+
+    // prints the fragments located before `pos` in its own lines, this
+    //   also moves the comments inside expressions before the statement
+    fn fragments_before(&mut self, pos: usize) {
+        while let Some(&(start, end)) = self.fragments.get(self.next) {
+            if start >= pos {
+                break;
+            }
+            self.newline(start);
+            self.push_fragment(start, end);
+            self.out.push('\n');
+        }
+    }
+
+    // appends to the current line the fragments after `pos` that are
+    //   in the same line of the source
+    fn trailing(&mut self, pos: usize) {
+        self.last = pos;
+        while let Some(&(start, end)) = self.fragments.get(self.next) {
+            if start < self.last || self.code[self.last..start].contains('\n') {
+                break;
+            }
+            self.out.pop();
+            self.out.push(' ');
+            self.push_fragment(start, end);
+            self.out.push('\n');
+        }
+    }
+
+    // starts the line of an element located at `start`, the elements
+    //   before `end` are printed before
+    fn start_line(&mut self, start: usize, end: usize) {
+        self.fragments_before(end);
+        self.newline(start);
+    }
+
+    // prints a single line element
+    fn line(&mut self, start: usize, end: usize, text: &str) {
+        self.start_line(start, end);
+        self.out.push_str(text);
+        self.out.push('\n');
+        self.trailing(end);
+    }
+
+    fn open(&mut self, brace: usize) {
+        self.out.push_str("{\n");
+        self.trailing(brace + 1);
+        self.indent += 1;
+    }
+
+    fn close(&mut self, end: usize) {
+        self.fragments_before(end - 1);
+        self.indent -= 1;
+        self.out.push_str(&INDENT.repeat(self.indent));
+        self.out.push('}');
+        self.last = end;
+    }
+
+    // prints a `{...}` block, the header is already printed
+    fn block(&mut self, block: &StatementP) {
+        if let StatementP::Block { meta, stmts } = block {
+            self.open(meta.start);
+            for stmt in stmts {
+                self.statement(stmt);
+            }
+            self.close(meta.end);
+        } else {
+            self.unsupported("a body that is not a block", block.meta());
+        }
+    }
+
+    fn end_block(&mut self, block: &StatementP) {
+        self.out.push('\n');
+        self.trailing(block_meta(block).end);
+    }
+
+    fn body(&mut self, elements: &[BodyElementP]) {
+        use self::BodyElementP::*;
+
+        let mut previous_def = None;
+        for element in elements {
+            let def = matches!(element, FunctionDef { .. } | TemplateDef { .. });
+            if previous_def.is_some_and(|previous| previous || def) {
+                self.blank_line();
+            }
+            previous_def = Some(def);
+
+            match element {
+                Include { meta, path } => {
+                    self.line(meta.start, meta.end, &format!("include \"{}\";", path))
+                }
+                FunctionDef {
+                    meta,
+                    name,
+                    args,
+                    stmt,
+                }
+                | TemplateDef {
+                    meta,
+                    name,
+                    args,
+                    stmt,
+                } => {
+                    let keyword = if def_is_template(element) {
+                        "template"
+                    } else {
+                        "function"
+                    };
+                    let attrs = self.attrs(meta);
+                    let header = if attrs.is_empty() {
+                        String::new()
+                    } else {
+                        format!("{}\n", attrs.trim_end())
+                    };
+                    self.start_line(meta.start, block_meta(stmt).start);
+                    self.out.push_str(&format!(
+                        "{}{} {}({}) ",
+                        header,
+                        keyword,
+                        name,
+                        args.join(", ")
+                    ));
+                    self.block(stmt);
+                    self.end_block(stmt);
+                }
+                Declaration { meta, decl } => {
+                    let text = format!("{}{};", self.attrs(meta), self.item(decl));
+                    self.line(meta.start, meta.end, &text);
+                }
+                MainComponent { meta, public, decl } => {
+                    if let StatementP::Declaration {
+                        name,
+                        init: Some((_, value)),
+                        ..
+                    } = &**decl
+                    {
+                        let text = format!(
+                            "{}component {} {{public [{}]}} = {};",
+                            self.attrs(meta),
+                            self.variable(name),
+                            public.join(", "),
+                            self.expr(value)
+                        );
+                        self.line(meta.start, meta.end, &text);
+                    } else {
+                        self.unsupported("a main component without initialization", meta);
+                    }
+                }
+            }
+        }
+        self.fragments_before(usize::MAX);
+    }
+
+    fn statement(&mut self, stmt: &StatementP) {
+        use self::StatementP::*;
+
+        match stmt {
+            IfThenElse { meta, xthen, .. } => {
+                self.start_line(meta.start, block_meta(xthen).start);
+                self.out.push_str(&self.attrs(meta));
+                self.if_then_else(stmt);
+            }
+            For {
+                meta,
+                init,
+                cond,
+                step,
+                stmt,
+            } => {
+                self.start_line(meta.start, block_meta(stmt).start);
+                let header = format!(
+                    "{}for ({}; {}; {}) ",
+                    self.attrs(meta),
+                    self.item(init),
+                    self.expr(cond),
+                    self.item(step)
+                );
+                self.out.push_str(&header);
+                self.block(stmt);
+                self.end_block(stmt);
+            }
+            While { meta, cond, stmt } => {
+                self.start_line(meta.start, block_meta(stmt).start);
+                let header = format!("{}while ({}) ", self.attrs(meta), self.expr(cond));
+                self.out.push_str(&header);
+                self.block(stmt);
+                self.end_block(stmt);
+            }
+            Block { meta, .. } => {
+                self.start_line(meta.start, meta.start);
+                self.out.push_str(&self.attrs(meta));
+                self.block(stmt);
+                self.end_block(stmt);
+            }
+            Return { meta, value } => {
+                let prefix = format!("{}return ", self.attrs(meta));
+                let text = format!("{}{};", prefix, self.value(value, &prefix));
+                self.line(meta.start, meta.end, &text);
+            }
+            Declaration { meta, .. } | Substitution { meta, .. } => {
+                let text = format!("{}{};", self.attrs(meta), self.item(stmt));
+                self.line(meta.start, meta.end, &text);
+            }
+            SignalLeft {
+                meta,
+                name,
+                op,
+                value,
+            } => {
+                let text = format!(
+                    "{}{} {:?} {};",
+                    self.attrs(meta),
+                    self.variable(name),
+                    op,
+                    self.expr(value)
+                );
+                self.line(meta.start, meta.end, &text);
+            }
+            SignalRight {
+                meta,
+                value,
+                op,
+                name,
+            } => {
+                let text = format!(
+                    "{}{} {:?} {};",
+                    self.attrs(meta),
+                    self.expr(value),
+                    op,
+                    self.variable(name)
+                );
+                self.line(meta.start, meta.end, &text);
+            }
+            SignalEq { meta, lhe, op, rhe } => {
+                let text = format!(
+                    "{}{} {:?} {};",
+                    self.attrs(meta),
+                    self.expr(lhe),
+                    op,
+                    self.expr(rhe)
+                );
+                self.line(meta.start, meta.end, &text);
+            }
+            InternalCall { meta, name, args } => {
+                // keep the `name!(...)` or `name(...)` form of the source
+                let source = &self.pp[meta.start..meta.end];
+                let bang = source
+                    .find(name.as_str())
+                    .is_some_and(|n| source[n + name.len()..].trim_start().starts_with('!'));
+                let text = format!(
+                    "{}{}{}({});",
+                    self.attrs(meta),
+                    name,
+                    if bang { "!" } else { "" },
+                    self.list(args)
+                );
+                self.line(meta.start, meta.end, &text);
+            }
+            Tuple {
+                meta,
+                xtype,
+                names,
+                op,
+                value,
+            } => {
+                let names = names
+                    .iter()
+                    .map(|name| self.variable(name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let xtype = match xtype {
+                    VariableType::Empty => String::new(),
+                    _ => format!("{:?} ", xtype),
+                };
+                let text = format!(
+                    "{}{}({}) {:?} {};",
+                    self.attrs(meta),
+                    xtype,
+                    names,
+                    op,
+                    self.expr(value)
+                );
+                self.line(meta.start, meta.end, &text);
+            }
+        }
+    }
+
+    // prints `if (...) {...} else ...`, the line is already started
+    fn if_then_else(&mut self, stmt: &StatementP) {
+        if let StatementP::IfThenElse {
+            xif, xthen, xelse, ..
+        } = stmt
+        {
+            self.out.push_str(&format!("if ({}) ", self.expr(xif)));
+            self.block(xthen);
+            match xelse.as_ref().map(|xelse| &**xelse) {
+                None => self.end_block(xthen),
+                Some(xelse @ StatementP::IfThenElse { .. }) => {
+                    self.out.push_str(" else ");
+                    self.if_then_else(xelse);
+                }
+                Some(xelse) => {
+                    self.out.push_str(" else ");
+                    self.block(xelse);
+                    self.end_block(xelse);
+                }
+            }
+        }
+    }
+
+    // declarations and substitutions, without the `;`
+    fn item(&self, stmt: &StatementP) -> String {
+        use self::StatementP::*;
+
+        match stmt {
+            Declaration {
+                xtype,
+                name,
+                init: Some((op, value)),
+                ..
+            } => {
+                let prefix = format!("{:?} {} {:?} ", xtype, self.variable(name), op);
+                format!("{}{}", prefix, self.value(value, &prefix))
+            }
+            Declaration {
+                xtype,
+                name,
+                init: None,
+                ..
+            } => format!("{:?} {}", xtype, self.variable(name)),
+            Substitution {
+                name, op, value, ..
+            } => match (&**value, op) {
+                // `i++` and `i--` are parsed as `i += 1` and `i -= 1` with an
+                //   empty location for the `1`
+                (ExpressionP::Number { meta, .. }, Opcode::AssigAdd) if meta.start == meta.end => {
+                    format!("{}++", self.variable(name))
+                }
+                (ExpressionP::Number { meta, .. }, Opcode::AssigSub) if meta.start == meta.end => {
+                    format!("{}--", self.variable(name))
+                }
+                _ => {
+                    let prefix = format!("{} {:?} ", self.variable(name), op);
+                    format!("{}{}", prefix, self.value(value, &prefix))
+                }
+            },
+            _ => self.unsupported("a statement as a declaration", stmt.meta()),
+        }
+    }
+
+    fn attrs(&self, meta: &Meta) -> String {
+        let tags = meta.attrs.tags();
+        if tags.is_empty() {
+            String::new()
+        } else if self.escape_attrs {
+            format!("/*#[{}]#*/ ", tags.join(", "))
+        } else {
+            format!("#[{}] ", tags.join(", "))
+        }
+    }
+
+    // an expression placed after `prefix` in the current line, arrays
+    //   that do not fit are splitted with an element per line
+    fn value(&self, expr: &ExpressionP, prefix: &str) -> String {
+        self.wrapped(expr, self.indent, self.indent * INDENT.len() + prefix.len())
+    }
+
+    fn wrapped(&self, expr: &ExpressionP, indent: usize, column: usize) -> String {
+        let line = self.expr(expr);
+        match expr {
+            ExpressionP::Array { values, .. }
+                if !values.is_empty() && column + line.len() + 1 > MAX_WIDTH =>
+            {
+                let inner = INDENT.repeat(indent + 1);
+                let values = values
+                    .iter()
+                    .map(|value| {
+                        format!("{}{}", inner, self.wrapped(value, indent + 1, inner.len()))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",\n");
+                format!("[\n{}\n{}]", values, INDENT.repeat(indent))
+            }
+            _ => line,
+        }
+    }
+
+    fn variable(&self, var: &VariableP) -> String {
+        let mut text = var.name.clone();
+        for sel in &var.sels {
+            match &**sel {
+                SelectorP::Pin { name, .. } => text.push_str(&format!(".{}", name)),
+                SelectorP::Index { pos, .. } => text.push_str(&format!("[{}]", self.expr(pos))),
+            }
+        }
+        text
+    }
+
+    fn list(&self, exprs: &[Box<ExpressionP>]) -> String {
+        exprs
+            .iter()
+            .map(|expr| self.expr(expr))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn expr(&self, expr: &ExpressionP) -> String {
+        use self::ExpressionP::*;
+
+        match expr {
+            FunctionCall { name, args, .. } => format!("{}({})", name, self.list(args)),
+            Variable { name, .. } => self.variable(name),
+            Number { meta, value } => {
+                // keep the hexadecimal literals
+                let source = &self.code[meta.start..meta.end];
+                if !source.is_empty() && source.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    source.to_string()
+                } else {
+                    value.to_string()
+                }
+            }
+            PrefixOp { op, rhe, .. } => {
+                let rhe_text = self.expr(rhe);
+                match &**rhe {
+                    PrefixOp { .. } | InfixOp { .. } | Ternary { .. } => {
+                        format!("{:?}({})", op, rhe_text)
+                    }
+                    _ => format!("{:?}{}", op, rhe_text),
+                }
+            }
+            InfixOp { lhe, op, rhe, .. } => format!(
+                "{} {:?} {}",
+                self.operand(*op, lhe, false),
+                op,
+                self.operand(*op, rhe, true)
+            ),
+            Array { values, .. } => format!("[{}]", self.list(values)),
+            Ternary {
+                cond,
+                if_true,
+                if_false,
+                ..
+            } => {
+                let nested = |expr: &ExpressionP| match expr {
+                    Ternary { .. } => format!("({})", self.expr(expr)),
+                    _ => self.expr(expr),
+                };
+                format!(
+                    "{} ? {} : {}",
+                    nested(cond),
+                    nested(if_true),
+                    self.expr(if_false)
+                )
+            }
+            StringLiteral { value, .. } => format!("\"{}\"", value),
+            Tuple { values, .. } => format!("({})", self.list(values)),
+            AnonymousComponent {
+                template,
+                params,
+                args,
+                ..
+            } => format!("{}({})({})", template, self.list(params), self.list(args)),
+        }
+    }
+
+    // an operand of an infix operator, with the parentheses required by the
+    //   precedence, or to make clear mixing bitwise operators with others
+    fn operand(&self, parent: Opcode, expr: &ExpressionP, right: bool) -> String {
+        let text = self.expr(expr);
+        let parens = match expr {
+            ExpressionP::InfixOp { op, .. } => {
+                let (parent_tier, tier) = match (tier(parent), tier(*op)) {
+                    (Some(parent_tier), Some(tier)) => (parent_tier, tier),
+                    _ => return self.unsupported("an operator", expr.meta()),
+                };
+                tier < parent_tier
+                    || (right && tier == parent_tier)
+                    || (tier != parent_tier && (is_bitwise(parent) || is_bitwise(*op)))
+            }
+            ExpressionP::Ternary { .. } => true,
+            _ => false,
+        };
+        if parens {
+            format!("({})", text)
+        } else {
+            text
+        }
+    }
+}
+
+fn def_is_template(element: &BodyElementP) -> bool {
+    matches!(element, BodyElementP::TemplateDef { .. })
+}
+
+// the location of a block, other statements are reported by `block`
+fn block_meta(stmt: &StatementP) -> &Meta {
+    match stmt {
+        StatementP::Block { meta, .. } => meta,
+        _ => stmt.meta(),
+    }
+}
+
+// precedence of the infix operators, higher binds tighter
+fn tier(op: Opcode) -> Option<usize> {
+    use self::Opcode::*;
+    match op {
+        BoolOr => Some(1),
+        BoolAnd => Some(2),
+        Eq | NotEq | Lesser | Greater | LesserEq | GreaterEq => Some(3),
+        BitOr => Some(4),
+        BitXor => Some(5),
+        BitAnd => Some(6),
+        ShiftL | ShiftR => Some(7),
+        Add | Sub => Some(8),
+        Mul | Div | IntDiv | Mod => Some(9),
+        Pow => Some(10),
+        _ => None,
+    }
+}
+
+fn is_bitwise(op: Opcode) -> bool {
+    use self::Opcode::*;
+    matches!(op, BitOr | BitXor | BitAnd | ShiftL | ShiftR)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_format(code: &str, expected: &str) {
+        let formatted = format_source(code).unwrap();
+        assert_eq!(expected, formatted);
+        assert_eq!(formatted, format_source(&formatted).unwrap());
+    }
+
+    #[test]
+    fn test_format_layout() {
+        assert_format(
+            "include \"a.circom\";\ntemplate T(a,b){signal input x;\n\n\n  signal output y;\nif(a>0){y<==x*x;}else if (b) {y<==x;} else {y<==1;}\nfor(var i=0;i<a;i++){x===i;}}\ncomponent main=T(1,2);",
+            "include \"a.circom\";
+
+template T(a, b) {
+    signal input x;
+
+    signal output y;
+    if (a > 0) {
+        y <== x * x;
+    } else if (b) {
+        y <== x;
+    } else {
+        y <== 1;
+    }
+    for (var i = 0; i < a; i++) {
+        x === i;
+    }
+}
+
+component main = T(1, 2);
+",
+        );
+    }
+
+    #[test]
+    fn test_format_comments() {
+        assert_format(
+            "/* license */\n// T\ntemplate T() { // header\n  signal input a; // input\n  /*#[w]#*/ a <-- 0x1F ; \n\n  // end\n}\n// eof",
+            "/* license */
+// T
+template T() { // header
+    signal input a; // input
+    /*#[w]#*/ a <-- 0x1F;
+
+    // end
+}
+// eof
+",
+        );
+        assert_format(
+            "// único\nfunction f() {\n  return [1, /* one */ 2];\n}\n",
+            "// único\nfunction f() {\n    /* one */\n    return [1, 2];\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_format_expressions() {
+        assert_format(
+            "var a = (b+c)*d - (e - f) + -(g*h) + !(!i);",
+            "var a = (b + c) * d - (e - f) + -(g * h) + !(!i);\n",
+        );
+        assert_format(
+            "var a = b & c == 1 || d << 1 + e;",
+            "var a = (b & c) == 1 || (d << (1 + e));\n",
+        );
+        assert_format(
+            "var a = (b ? c : d) ? e : f ? g : h;",
+            "var a = (b ? c : d) ? e : f ? g : h;\n",
+        );
+        assert_format(
+            "pragma circom 2.0.0;\ntemplate T() {\n var (a,b) = (1,2);\n (a, b) = (b, a);\n log(\"a\", a);\n assert!(a>1);\n}",
+            "pragma circom 2.0.0;\ntemplate T() {\n    var (a, b) = (1, 2);\n    (a, b) = (b, a);\n    log(\"a\", a);\n    assert!(a > 1);\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_format_unsupported() {
+        let code = "component main {public [a]} = T();";
+        let mut elements = parse::parse_ast(code, Lang::Za).unwrap();
+        if let BodyElementP::MainComponent { decl, .. } = &mut elements[0] {
+            if let StatementP::Declaration { init, .. } = &mut **decl {
+                *init = None;
+            }
+        }
+        match format_elements(code, code, Lang::Za, &elements) {
+            Err(Error::ParseError(message, meta)) => {
+                assert_eq!(
+                    "cannot format a main component without initialization",
+                    message
+                );
+                assert_eq!((0, code.len()), (meta.start, meta.end));
+            }
+            _ => panic!("the main component is not reported"),
+        }
+    }
+
+    #[test]
+    fn test_format_long_arrays() {
+        let values = (0..30).map(|n| n.to_string()).collect::<Vec<_>>();
+        let code = format!("function f() {{ return [[{}], [1]]; }}", values.join(","));
+        let expected = format!(
+            "function f() {{\n    return [\n        [\n{}\n        ],\n        [1]\n    ];\n}}\n",
+            values
+                .iter()
+                .map(|v| format!("            {}", v))
+                .collect::<Vec<_>>()
+                .join(",\n")
+        );
+        assert_format(&code, &expected);
+    }
+}
@@ -3,13 +3,17 @@ extern crate serde_derive;
 
 #[macro_use]
 extern crate lalrpop_util;
+#[macro_use]
+extern crate lazy_static;
 
 lalrpop_mod!(#[allow(clippy::all)] pub lang); // synthesized by LALRPOP
 
 pub mod ast;
 pub mod display;
 mod error;
+mod format;
 mod parse;
 
 pub use self::error::{Error, Result};
-pub use self::parse::parse;
+pub use self::format::format_source;
+pub use self::parse::{comments, parse, parse_expression, parse_lang, Lang};
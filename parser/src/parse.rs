@@ -1,95 +1,279 @@
+use regex::Regex;
+
 use super::ast;
-use super::ast::Meta;
+use super::ast::{Meta, SignalType, StatementP, VariableType};
 use super::error::*;
 use super::lang;
 
-fn preprocess(expr: &str) -> Result<String> {
-    let mut pp = String::new();
-    let mut state = 0;
-    let mut loc = 0;
-    let mut block_comment_start = 0;
-
-    let expr = expr.replace("/*#[", "  #[").replace("]#*/", "]   ");
-
-    let mut it = expr.chars();
-    while let Some(c0) = it.next() {
-        loc += 1;
-        match (state, c0) {
-            (0, '/') => {
-                loc += 1;
-                match it.next() {
-                    Some('/') => {
-                        state = 1;
-                        pp.push(' ');
-                        pp.push(' ');
-                    }
-                    Some('*') => {
-                        block_comment_start = loc;
-                        state = 2;
-                        pp.push(' ');
-                        pp.push(' ');
-                    }
-                    Some(c1) => {
-                        pp.push(c0);
-                        pp.push(c1);
-                    }
-                    None => {
-                        pp.push(c0);
-                        break;
-                    }
+lazy_static! {
+    static ref CIRCOM2_PRAGMA: Regex = Regex::new(r"pragma\s+circom\s+2").unwrap();
+    static ref PRAGMA: Regex = Regex::new(r"pragma[^;]*;").unwrap();
+}
+
+/// Language of a circuit source
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lang {
+    Za,
+    Circom2,
+}
+
+impl Lang {
+    /// Circom 2 sources are identified by its `pragma circom 2.x.x;`
+    pub fn detect(code: &str) -> Self {
+        if CIRCOM2_PRAGMA.is_match(code) {
+            Lang::Circom2
+        } else {
+            Lang::Za
+        }
+    }
+}
+
+/// Byte ranges of the `//` and `/* */` comments, `/*#[...]#*/` escaped attributes
+///   are not comments
+pub fn comments(code: &str) -> Result<Vec<(usize, usize)>> {
+    let bytes = code.as_bytes();
+    let find = |from: usize, pattern: &[u8]| {
+        bytes[from..]
+            .windows(pattern.len())
+            .position(|w| w == pattern)
+            .map(|n| from + n)
+    };
+
+    let mut comments = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let rest = &bytes[pos..];
+        if rest.starts_with(b"/*#[") {
+            pos += 4;
+        } else if rest.starts_with(b"//") {
+            let end = find(pos, b"\n").unwrap_or(bytes.len());
+            comments.push((pos, end));
+            pos = end;
+        } else if rest.starts_with(b"/*") {
+            match find(pos + 2, b"*/") {
+                Some(n) => {
+                    comments.push((pos, n + 2));
+                    pos = n + 2;
                 }
-            }
-            (0, _) => pp.push(c0),
-            (1, '\n') => {
-                pp.push(c0);
-                state = 0;
-            }
-            (2, '*') => {
-                loc += 1;
-                match it.next() {
-                    Some('/') => {
-                        pp.push(' ');
-                        pp.push(' ');
-                        state = 0;
-                    }
-                    Some(_) => {
-                        pp.push(' ');
-                        pp.push(' ');
-                    }
-                    None => {
-                        return Err(Error::ParseError(
-                            "unterminated /* */".to_string(),
-                            Meta::new(block_comment_start, block_comment_start, None),
-                        ));
-                    }
+                None => {
+                    return Err(Error::ParseError(
+                        "unterminated /* */".to_string(),
+                        Meta::new(pos, pos, None),
+                    ));
                 }
             }
-            _ => {
-                pp.push(' ');
+        } else if rest[0] == b'"' {
+            pos = find(pos + 1, b"\"").map_or(bytes.len(), |n| n + 1);
+        } else {
+            pos += 1;
+        }
+    }
+    Ok(comments)
+}
+
+// blank the comments and unescape the attributes, keeping the locations
+pub(crate) fn preprocess(expr: &str) -> Result<String> {
+    let mut pp = expr.as_bytes().to_vec();
+    for (start, end) in comments(expr)? {
+        pp[start..end].iter_mut().for_each(|byte| *byte = b' ');
+    }
+    let pp = String::from_utf8(pp).unwrap();
+    Ok(pp.replace("/*#[", "  #[").replace("]#*/", "]   "))
+}
+
+// byte ranges of the `pragma ...;` directives
+pub(crate) fn pragmas(code: &str) -> Vec<(usize, usize)> {
+    PRAGMA
+        .find_iter(code)
+        .map(|m| (m.start(), m.end()))
+        .collect()
+}
+
+// blank the pragmas, keeping the locations
+fn remove_pragmas(code: &str) -> String {
+    let ranges = pragmas(code);
+    let mut code = code.as_bytes().to_vec();
+    for (start, end) in ranges {
+        code[start..end]
+            .iter_mut()
+            .filter(|byte| **byte != b'\n')
+            .for_each(|byte| *byte = b' ');
+    }
+    String::from_utf8(code).unwrap()
+}
+
+// in circom 2 inputs are private unless declared public in the main component
+fn make_inputs_private(stmt: &mut StatementP) {
+    match stmt {
+        StatementP::Declaration {
+            xtype: xtype @ VariableType::Signal(SignalType::PublicInput),
+            ..
+        } => *xtype = VariableType::Signal(SignalType::PrivateInput),
+        StatementP::Block { stmts, .. } => {
+            stmts.iter_mut().for_each(|stmt| make_inputs_private(stmt))
+        }
+        StatementP::IfThenElse { xthen, xelse, .. } => {
+            make_inputs_private(xthen);
+            if let Some(xelse) = xelse {
+                make_inputs_private(xelse);
             }
         }
+        StatementP::For { stmt, .. } | StatementP::While { stmt, .. } => make_inputs_private(stmt),
+        _ => {}
     }
-    Ok(pp)
 }
 
-/// parse circom lang
+/// parse circom lang, circom 2 sources are detected by its pragma
 pub fn parse(expr: &str) -> Result<Vec<ast::BodyElementP>> {
+    let code = preprocess(expr)?;
+    let lang = Lang::detect(&code);
+    parse_preprocessed(&code, lang)
+}
+
+/// parse a single expression, e.g. the ones typed in the debugger
+pub fn parse_expression(expr: &str) -> Result<Box<ast::ExpressionP>> {
+    let code = preprocess(expr)?;
+    lang::ExpressionParser::new()
+        .parse(&mut Vec::new(), &code)
+        .map_err(|err| {
+            let (message, meta) = error_message(&code, err);
+            Error::ParseError(message, meta)
+        })
+}
+
+/// parse circom lang in the specified language mode
+pub fn parse_lang(expr: &str, lang: Lang) -> Result<Vec<ast::BodyElementP>> {
+    parse_preprocessed(&preprocess(expr)?, lang)
+}
+
+fn parse_preprocessed(code: &str, lang: Lang) -> Result<Vec<ast::BodyElementP>> {
+    let circom2 = |mut elements: Vec<ast::BodyElementP>| {
+        if lang == Lang::Circom2 {
+            for element in elements.iter_mut() {
+                if let ast::BodyElementP::TemplateDef { stmt, .. } = element {
+                    make_inputs_private(stmt);
+                }
+            }
+        }
+        elements
+    };
+    match parse_ast(code, lang) {
+        Ok(elements) => Ok(circom2(elements)),
+        Err(Error::ParseErrors(errors, elements)) => {
+            Err(Error::ParseErrors(errors, circom2(elements)))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+// parse the preprocessed code into the AST, as written in the source, the
+//   parser recovers from the syntax errors to report all of them
+pub(crate) fn parse_ast(code: &str, lang: Lang) -> Result<Vec<ast::BodyElementP>> {
+    let code = match lang {
+        Lang::Za => code.to_string(),
+        Lang::Circom2 => remove_pragmas(code),
+    };
+
+    let mut recovered = Vec::new();
+    let res = lang::BodyParser::new().parse(&mut recovered, &code);
+
+    let mut errors = recovered
+        .into_iter()
+        .map(|recovery| error_message(&code, recovery.error))
+        .collect::<Vec<_>>();
+    match res {
+        Ok(elements) if errors.is_empty() => Ok(elements),
+        Ok(elements) => Err(Error::ParseErrors(errors, elements)),
+        Err(err) if errors.is_empty() => {
+            let (message, meta) = error_message(&code, err);
+            Err(Error::ParseError(message, meta))
+        }
+        Err(err) => {
+            errors.push(error_message(&code, err));
+            Err(Error::ParseErrors(errors, Vec::new()))
+        }
+    }
+}
+
+type LalrpopError<'a> =
+    lalrpop_util::ParseError<usize, lang::Token<'a>, (usize, &'static str, usize)>;
+
+fn error_message(code: &str, err: LalrpopError) -> (String, Meta) {
     use lalrpop_util::ParseError::*;
-    lang::BodyParser::new()
-        .parse(&preprocess(expr)?)
-        .map_err(|err| match err {
-            InvalidToken { location } => {
-                Error::ParseError(format!("{:?}", err), Meta::new(location, location, None))
+
+    match err {
+        InvalidToken { location } => (
+            "invalid token".to_string(),
+            Meta::new(location, location, None),
+        ),
+        UnrecognizedToken {
+            token: Some((left, token, right)),
+            expected,
+        } => (
+            format!("unexpected `{}`, {}", token, expected_tokens(&expected)),
+            Meta::new(left, right, None),
+        ),
+        UnrecognizedToken {
+            token: None,
+            expected,
+        } => (
+            format!("unexpected end of file, {}", expected_tokens(&expected)),
+            Meta::new(code.len(), code.len(), None),
+        ),
+        ExtraToken {
+            token: (left, token, right),
+        } => (
+            format!("unexpected `{}`", token),
+            Meta::new(left, right, None),
+        ),
+        User {
+            error: (start, message, end),
+        } => (message.to_string(), Meta::new(start, end, None)),
+    }
+}
+
+// the expected terminals, with the regular expressions by its name
+fn expected_tokens(expected: &[String]) -> String {
+    let mut names = Vec::new();
+    for terminal in expected {
+        let name = if terminal.starts_with("r#") {
+            if terminal.contains("a-zA-Z") {
+                "identifier".to_string()
+            } else if terminal.contains("0-9") {
+                "number".to_string()
+            } else {
+                "string".to_string()
             }
-            UnrecognizedToken {
-                token: Some((left, _, right)),
-                ..
-            } => Error::ParseError(format!("{:?}", err), Meta::new(left, right, None)),
-            ExtraToken {
-                token: (left, _, right),
-            } => Error::ParseError(format!("{:?}", err), Meta::new(left, right, None)),
-            _ => Error::ParseError(format!("{:?}", err), Meta::new(0, 0, None)),
-        })
+        } else {
+            format!("`{}`", terminal.trim_matches('"'))
+        };
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    match names.len() {
+        0 => "expected nothing".to_string(),
+        1 => format!("expected {}", names[0]),
+        // after an expression almost any operator is expected, only the
+        //   tokens that close it are named
+        n if n > 20 => {
+            let closing = names
+                .iter()
+                .filter(|name| ["`;`", "`,`", "`)`", "`]`", "`}`"].contains(&name.as_str()))
+                .cloned()
+                .collect::<Vec<_>>();
+            if closing.is_empty() {
+                format!("expected one of {} possible tokens", n)
+            } else {
+                format!(
+                    "expected {} or one of {} other tokens",
+                    closing.join(", "),
+                    n - closing.len()
+                )
+            }
+        }
+        _ => format!("expected one of {}", names.join(", ")),
+    }
 }
 
 #[cfg(test)]
@@ -108,4 +292,76 @@ mod test {
         test_preprocess("helo /* // */foo", "helo         foo");
         test_preprocess("a /*#[foo]#*/ b", "a   #[foo]    b");
     }
+
+    #[test]
+    fn test_circom2_lang() {
+        use super::*;
+
+        assert_eq!(Lang::Za, Lang::detect("template A() {}"));
+        assert_eq!(Lang::Circom2, Lang::detect("pragma circom 2.0.0;\n"));
+        assert_eq!(
+            "                    \ntemplate",
+            remove_pragmas("pragma circom 2.0.0;\ntemplate")
+        );
+
+        let code = "
+            pragma circom 2.0.0;
+            template T() {
+                signal input a;
+                signal private input b;
+                signal output c;
+                c <== a * b;
+            }";
+        assert_eq!(
+            "template T() {signal private input a; signal private input b; signal output c; c <== (a * b);}",
+            format!("{:?}", parse(code).unwrap()[0])
+        );
+        assert_eq!(
+            "template T() {signal input a; signal private input b; signal output c; c <== (a * b);}",
+            format!("{:?}", parse(&code.replace("pragma circom 2.0.0;", "")).unwrap()[0])
+        );
+        assert!(parse_lang(code, Lang::Za).is_err());
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        use super::*;
+
+        let code = "
+            template A() { signal input a b; }
+            template B() { var x = ; var y = 1; }
+            component main = A();";
+        let errors = parse(code).unwrap_err().errors();
+        assert_eq!(2, errors.len());
+        assert_eq!(code.find("b;").unwrap(), errors[0].1.start);
+        assert!(errors[0].0.starts_with("unexpected `b`, expected "));
+        assert!(errors[0].0.contains("`;`"));
+        assert_eq!(code.find("; var y").unwrap(), errors[1].1.start);
+        let recovered = parse(code).unwrap_err().recovered().len();
+        assert_eq!(3, recovered);
+
+        match parse("template A() { signal a; ").unwrap_err() {
+            Error::ParseError(message, _) => assert!(message.starts_with("unexpected end of file")),
+            err => panic!("unexpected {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_tuple_error() {
+        use super::*;
+
+        let code = "template A() { var (a, b[0] + 1) = (1, 2); }";
+        let errors = parse(code).unwrap_err().errors();
+        assert_eq!(
+            vec![(
+                "tuple elements must be variables".to_string(),
+                code.find("b[0] + 1").unwrap(),
+                code.find("1)").unwrap() + 1
+            )],
+            errors
+                .into_iter()
+                .map(|(message, meta)| (message, meta.start, meta.end))
+                .collect::<Vec<_>>()
+        );
+    }
 }
@@ -6,6 +6,7 @@ pub enum Error {
     Bincode(bincode::Error),
     Algebra(za_compiler::algebra::Error),
     Evaluator(za_compiler::evaluator::Error),
+    Project(za_compiler::project::Error),
     BadFormat(String),
     Unexpected(String),
     Json(serde_json::error::Error),
@@ -53,4 +54,10 @@ impl From<za_compiler::evaluator::Error> for Error {
     }
 }
 
+impl From<za_compiler::project::Error> for Error {
+    fn from(err: za_compiler::project::Error) -> Self {
+        Error::Project(err)
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
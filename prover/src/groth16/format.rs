@@ -3,7 +3,7 @@ extern crate rand;
 use pairing::bn256::Bn256;
 
 use za_compiler::algebra::{SignalId, Value, FS, LC};
-use za_compiler::types::Constraints;
+use za_compiler::types::{Constraints, Provenance};
 use za_parser::ast::BodyElementP;
 
 use bellman::LinearCombination;
@@ -11,7 +11,7 @@ use bellman::LinearCombination;
 use std::io::{Read, Write};
 
 use bellman::groth16::{Parameters, Proof};
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
 use ff_ce::PrimeField;
 use pairing::Engine;
 
@@ -25,9 +25,12 @@ type G2JsonStruct = [[String; 2]; 2];
 
 pub struct ProvingKey {
     pub asts: Vec<BodyElementP>,
+    pub main: Option<String>,
     pub constraints: Constraints,
     pub ignore_signals: Vec<SignalId>,
     pub params: Parameters<Bn256>,
+    // `Constraints::circuit_hash`, not in the keys written before the sections
+    pub circuit_hash: Option<String>,
 }
 
 fn str_to_fq(s: &str) -> Result<pairing::bn256::Fq> {
@@ -210,7 +213,7 @@ pub fn lc_to_bellman<E: Engine>(
     lc: &LC,
 ) -> LinearCombination<E> {
     use std::ops::Add;
-    for (s, v) in &lc.0 {
+    for (s, v) in lc {
         let signal = signals[*s];
         if signal.is_none() {
             panic!("signal {} not defined", *s);
@@ -220,13 +223,40 @@ pub fn lc_to_bellman<E: Engine>(
     base
 }
 
+// the proving keys start with the magic and the format version, the keys
+//   written before them start with the length of the asts
+const PK_MAGIC: [u8; 4] = *b"ZAPK";
+const PK_VERSION: u32 = 1;
+
+// optional sections written after the parameters, as tag, length and bytes
+const SECTION_MAIN: u32 = 1;
+const SECTION_PROVENANCE: u32 = 2;
+const SECTION_CIRCUIT_HASH: u32 = 3;
+
+fn write_section<W: Write>(pk: &mut W, tag: u32, bytes: &[u8]) -> Result<()> {
+    pk.write_u32::<BigEndian>(tag)?;
+    pk.write_u32::<BigEndian>(bytes.len() as u32)?;
+    pk.write_all(bytes)?;
+    Ok(())
+}
+
+fn section_to_string(bytes: Vec<u8>) -> Result<String> {
+    String::from_utf8(bytes).map_err(|err| Error::BadFormat(err.to_string()))
+}
+
 pub fn write_pk<W: Write>(
     mut pk: W,
     asts: &[BodyElementP],
+    main: Option<&str>,
     constraints: &Constraints,
     ignore_signals: &[SignalId],
     params: &Parameters<Bn256>,
+    circuit_hash: &str,
 ) -> Result<()> {
+    // write header
+    pk.write_all(&PK_MAGIC)?;
+    pk.write_u32::<BigEndian>(PK_VERSION)?;
+
     // write asts
     let ast_serial = bincode::serialize(asts)?;
     pk.write_u32::<BigEndian>(ast_serial.len() as u32)?;
@@ -247,22 +277,59 @@ pub fn write_pk<W: Write>(
     }
 
     // write signalid
-    params.write(pk)?;
+    params.write(&mut pk)?;
+
+    // write main template invocation
+    if let Some(main) = main {
+        write_section(&mut pk, SECTION_MAIN, main.as_bytes())?;
+    }
+
+    // write where the constraints have been generated
+    let provenance = (0..constraints.len())
+        .map(|i| constraints.get_provenance(i))
+        .collect::<Vec<_>>();
+    write_section(
+        &mut pk,
+        SECTION_PROVENANCE,
+        &bincode::serialize(&provenance)?,
+    )?;
+
+    // write circuit hash
+    write_section(&mut pk, SECTION_CIRCUIT_HASH, circuit_hash.as_bytes())?;
     Ok(())
 }
 
 pub fn read_pk<R: Read>(mut pk: R) -> Result<ProvingKey> {
     let mut buffer = Vec::with_capacity(1024);
-    let mut constraints = Constraints::default();
+
+    // read header, the keys without it have only the asts, constraints,
+    //   signal aliases and parameters
+    let mut header = [0u8; 4];
+    pk.read_exact(&mut header)?;
+    let legacy = header != PK_MAGIC;
+    if !legacy {
+        let version = pk.read_u32::<BigEndian>()?;
+        if version != PK_VERSION {
+            return Err(Error::BadFormat(format!(
+                "unsupported proving key version {}",
+                version
+            )));
+        }
+    }
 
     // read asts
-    let bytes = pk.read_u32::<BigEndian>()?;
+    let bytes = if legacy {
+        BigEndian::read_u32(&header)
+    } else {
+        pk.read_u32::<BigEndian>()?
+    };
     let mut ast_serial = vec![0; bytes as usize];
     pk.read_exact(&mut ast_serial)?;
     let asts = bincode::deserialize(&ast_serial)?;
 
     // read constraints
     let count = pk.read_u32::<BigEndian>()?;
+    let mut qeqs = Vec::with_capacity(count as usize);
     for _ in 0..count {
         let len = pk.read_u32::<BigEndian>()? as usize;
         if len > buffer.capacity() {
@@ -270,8 +337,7 @@ pub fn read_pk<R: Read>(mut pk: R) -> Result<ProvingKey> {
         }
         buffer.resize(len, 0u8);
         pk.read_exact(&mut buffer)?;
-        let qeq = bincode::deserialize(&buffer)?;
-        constraints.push(qeq, None);
+        qeqs.push(bincode::deserialize(&buffer)?);
     }
 
     // read signal aliases
@@ -282,13 +348,52 @@ pub fn read_pk<R: Read>(mut pk: R) -> Result<ProvingKey> {
     }
 
     // read proving key
-    let params: Parameters<Bn256> = Parameters::read(pk, true)?;
+    let params: Parameters<Bn256> = Parameters::read(&mut pk, true)?;
+
+    let mut main = None;
+    let mut provenance: Vec<Option<Provenance>> = Vec::new();
+    let mut circuit_hash = None;
+    if !legacy {
+        // read the optional sections until the end, skipping the unknown ones
+        loop {
+            let tag = match pk.read_u32::<BigEndian>() {
+                Ok(tag) => tag,
+                Err(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err.into()),
+            };
+            let len = pk.read_u32::<BigEndian>()?;
+            let mut bytes = vec![0; len as usize];
+            pk.read_exact(&mut bytes)?;
+            match tag {
+                SECTION_MAIN => main = Some(section_to_string(bytes)?),
+                SECTION_PROVENANCE => provenance = bincode::deserialize(&bytes)?,
+                SECTION_CIRCUIT_HASH => circuit_hash = Some(section_to_string(bytes)?),
+                _ => {}
+            }
+        }
+    }
+
+    // the keys without provenance have all the constraints unlocated
+    if !provenance.is_empty() && provenance.len() != qeqs.len() {
+        return Err(Error::BadFormat(format!(
+            "{} constraints but {} provenances",
+            qeqs.len(),
+            provenance.len()
+        )));
+    }
+    provenance.resize(qeqs.len(), None);
+    let mut constraints = Constraints::default();
+    for (qeq, provenance) in qeqs.into_iter().zip(provenance) {
+        constraints.push(qeq, provenance);
+    }
 
     Ok(ProvingKey {
         asts,
+        main,
         constraints,
         ignore_signals,
         params,
+        circuit_hash,
     })
 }
 
@@ -9,8 +9,10 @@ use super::format::{JsonProofAndInput, JsonVerifyingKey, ProvingKey};
 use std::fs::File;
 use std::time::SystemTime;
 
-use za_compiler::tester::dump_error;
-use za_compiler::types::{print_info, Constraints, Signals};
+use za_compiler::cache::CompiledCircuit;
+use za_compiler::diff::ConstraintSystem;
+use za_compiler::project::Project;
+use za_compiler::types::{Constraints, Signals};
 
 use bellman::groth16::{prepare_verifying_key, verify_proof};
 
@@ -24,54 +26,45 @@ pub fn setup(
     proving_key_path: &str,
     verifier_type: VerifierType,
 ) -> Result<String> {
-    let mut eval = Evaluator::new(
-        Mode::GenConstraints,
-        Signals::default(),
-        Constraints::default(),
-    );
+    setup_project(
+        &Project::from_circuit(".", circuit_path),
+        proving_key_path,
+        verifier_type,
+        false,
+        None,
+    )
+}
 
+pub fn setup_project(
+    project: &Project,
+    proving_key_path: &str,
+    verifier_type: VerifierType,
+    update_lock: bool,
+    sym_path: Option<&str>,
+) -> Result<String> {
     info!("Compiling circuit...");
-    let start = SystemTime::now();
-    if let Err(err) = eval.eval_file(".", &circuit_path) {
-        dump_error(&eval, &format!("{:?}", err));
-        return Err(Error::from(err));
-    }
-
-    info!(
-        "Compilation time: {:?}",
-        SystemTime::now().duration_since(start).unwrap()
-    );
-
-    let Evaluator {
-        constraints,
+    let CompiledCircuit {
+        main,
+        asts,
         signals,
-        collected_asts,
+        constraints,
+        removed_signals,
         ..
-    } = eval;
-
-    print_info("compile", &constraints, &signals, &[], false);
-
-    let start = SystemTime::now();
-
-    let irreductible_signals = signals.main_input_ids();
-    let (constraints, removed_signals) =
-        za_compiler::optimizer::optimize(&constraints, &irreductible_signals);
+    } = project.compile(update_lock, false)?;
 
-    info!(
-        "Optimization time: {:?}",
-        SystemTime::now().duration_since(start).unwrap()
-    );
-    print_info("optimized", &constraints, &signals, &removed_signals, false);
+    if let Some(sym_path) = sym_path {
+        std::fs::write(sym_path, signals.sym(&removed_signals))?;
+    }
 
-    let eval = Evaluator::new(Mode::GenConstraints, signals, constraints);
     info!("Running setup");
 
     let pk = File::create(proving_key_path)?;
 
     let (vk, inputs) = super::setup(
-        &collected_asts,
-        &eval.signals,
-        &eval.constraints,
+        &asts,
+        main.as_ref().map(String::as_str),
+        &signals,
+        &constraints,
         &removed_signals,
         pk,
     )?;
@@ -92,11 +85,19 @@ pub fn prove(proving_key_path: &str, inputs: Vec<(String, FS)>) -> Result<String
     let pk = File::open(proving_key_path)?;
     let ProvingKey {
         asts,
+        main,
         constraints,
         ignore_signals,
         params,
+        circuit_hash,
     } = super::format::read_pk(pk)?;
 
+    if let Some(main) = main {
+        info!("Main component: {}", main);
+    }
+    if let Some(circuit_hash) = circuit_hash {
+        info!("Circuit hash: {}", circuit_hash);
+    }
     info!("Generating witness...");
 
     let mut ev_witness =
@@ -146,6 +147,31 @@ pub fn prove(proving_key_path: &str, inputs: Vec<(String, FS)>) -> Result<String
     Ok(String::from_utf8_lossy(&proof).to_string())
 }
 
+/// Reads the constraints of a proving key, the signals are generated again
+///   from its sources
+pub fn read_constraint_system(proving_key_path: &str) -> Result<ConstraintSystem> {
+    let pk = File::open(proving_key_path)?;
+    let ProvingKey {
+        asts,
+        constraints,
+        ignore_signals,
+        ..
+    } = super::format::read_pk(pk)?;
+
+    let mut eval = Evaluator::new(
+        Mode::GenConstraints,
+        Signals::default(),
+        Constraints::default(),
+    );
+    eval.eval_asts(&asts)?;
+
+    Ok(ConstraintSystem {
+        signals: eval.signals,
+        constraints,
+        removed_signals: ignore_signals,
+    })
+}
+
 pub fn verify(json_verifying_key: &str, proof_and_public_input: &str) -> Result<bool> {
     info!("Reading vk...");
     let vk = JsonVerifyingKey::from_json(json_verifying_key)?.to_bellman()?;
@@ -20,7 +20,7 @@ use ff_ce::PrimeField;
 
 use rand::thread_rng;
 
-use super::error::Result;
+use super::error::{Error, Result};
 use super::format::*;
 
 const BELLMAN_VERBOSE: &str = "BELLMAN_VERBOSE";
@@ -104,6 +104,7 @@ impl<'a, E: Engine> Circuit<E> for CircomCircuit<'a, E> {
 
 pub fn setup<W: Write>(
     asts: &[BodyElementP],
+    main: Option<&str>,
     signals: &Signals,
     constraints: &Constraints,
     ignore_signals: &[SignalId],
@@ -124,8 +125,17 @@ pub fn setup<W: Write>(
         "Setup time: {:?}",
         SystemTime::now().duration_since(start).unwrap()
     );
+    let circuit_hash = constraints.circuit_hash(signals);
     let start = SystemTime::now();
-    write_pk(out_pk, &asts, &constraints, &ignore_signals, &params)?;
+    write_pk(
+        out_pk,
+        &asts,
+        main,
+        &constraints,
+        &ignore_signals,
+        &params,
+        &circuit_hash,
+    )?;
     info!(
         "Proving key write time: {:?}",
         SystemTime::now().duration_since(start).unwrap()
@@ -152,9 +162,9 @@ pub fn generate_verified_proof<W: Write>(
     );
 
     let start = SystemTime::now();
-    constraints
-        .satisfies_with_signals(&signals)
-        .expect("check_constrains_eval_zero failed");
+    if let Err(report) = constraints.satisfies_with_signals(&signals) {
+        return Err(Error::Unexpected(report.to_string()));
+    }
     info!(
         "Constraint check time: {:?} for {} constraint",
         SystemTime::now().duration_since(start).unwrap(),
@@ -332,6 +342,7 @@ mod test {
         let pk = File::create("/tmp/pk").unwrap();
         let (_, _) = setup(
             &ev_r1cs.collected_asts,
+            Some("t()"),
             &ev_r1cs.signals,
             &ev_r1cs.constraints,
             &Vec::new(),
@@ -342,6 +353,15 @@ mod test {
         // Compute witness -------------------------------------------
         let pk = File::open("/tmp/pk").unwrap();
         let pk = read_pk(pk).unwrap();
+        assert_eq!(Some("t()".to_string()), pk.main);
+        assert_eq!(
+            Some(ev_r1cs.constraints.circuit_hash(&ev_r1cs.signals)),
+            pk.circuit_hash
+        );
+        assert_eq!(
+            ev_r1cs.constraints.get_provenance(0),
+            pk.constraints.get_provenance(0)
+        );
 
         let mut ev_witness =
             Evaluator::new(Mode::GenWitness, Signals::default(), Constraints::default());
@@ -371,4 +391,101 @@ mod test {
 
         assert_eq!("[(\"main.c\", 21)]", format!("{:?}", public_input));
     }
+
+    // the `write_pk` of the proving keys before the format version
+    fn write_baseline_pk<W: std::io::Write>(
+        mut pk: W,
+        asts: &[BodyElementP],
+        constraints: &Constraints,
+        ignore_signals: &[SignalId],
+        params: &Parameters<Bn256>,
+    ) {
+        use byteorder::{BigEndian, WriteBytesExt};
+
+        let ast_serial = bincode::serialize(asts).unwrap();
+        pk.write_u32::<BigEndian>(ast_serial.len() as u32).unwrap();
+        pk.write_all(&ast_serial).unwrap();
+
+        pk.write_u32::<BigEndian>(constraints.len() as u32).unwrap();
+        for i in 0..constraints.len() {
+            let qeq = bincode::serialize(&constraints.get(i)).unwrap();
+            pk.write_u32::<BigEndian>(qeq.len() as u32).unwrap();
+            pk.write_all(&qeq).unwrap();
+        }
+
+        pk.write_u32::<BigEndian>(ignore_signals.len() as u32)
+            .unwrap();
+        for signal in ignore_signals {
+            pk.write_u32::<BigEndian>(*signal as u32).unwrap();
+        }
+
+        params.write(pk).unwrap();
+    }
+
+    #[test]
+    fn test_read_baseline_pk() {
+        let circuit = "
+            template t() {
+                signal private input a;
+                signal private input b;
+                signal output c;
+
+                c <== a * b;
+            }
+            component main = t();
+        ";
+
+        let mut ev_r1cs = Evaluator::new(
+            Mode::GenConstraints,
+            Signals::default(),
+            Constraints::default(),
+        );
+        ev_r1cs
+            .eval_inline(&mut Scope::new(true, None, "root".to_string()), circuit)
+            .unwrap();
+
+        let params = generate_random_parameters(
+            CircomCircuit::<Bn256> {
+                signals: &ev_r1cs.signals,
+                ignore_signals: &Vec::new(),
+                constraints: &ev_r1cs.constraints,
+                phantom: PhantomData,
+            },
+            &mut thread_rng(),
+        )
+        .unwrap();
+
+        let mut pk = Vec::new();
+        write_baseline_pk(
+            &mut pk,
+            &ev_r1cs.collected_asts,
+            &ev_r1cs.constraints,
+            &Vec::new(),
+            &params,
+        );
+
+        let pk = read_pk(&pk[..]).unwrap();
+        assert_eq!(None, pk.main);
+        assert_eq!(None, pk.circuit_hash);
+        assert_eq!(ev_r1cs.constraints.len(), pk.constraints.len());
+        assert_eq!(ev_r1cs.constraints.get(0), pk.constraints.get(0));
+        assert_eq!(None, pk.constraints.get_provenance(0));
+
+        let mut ev_witness =
+            Evaluator::new(Mode::GenWitness, Signals::default(), Constraints::default());
+        ev_witness.set_deferred_value("main.a".to_string(), Value::from(7));
+        ev_witness.set_deferred_value("main.b".to_string(), Value::from(3));
+        ev_witness.eval_asts(&pk.asts).unwrap();
+
+        let mut proof = Vec::new();
+        let public_input = generate_verified_proof(
+            &ev_witness.signals,
+            &pk.ignore_signals,
+            &pk.constraints,
+            &pk.params,
+            &mut proof,
+        )
+        .unwrap();
+        assert_eq!("[(\"main.c\", 21)]", format!("{:?}", public_input));
+    }
 }